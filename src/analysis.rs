@@ -0,0 +1,96 @@
+use std::{collections::BTreeSet, fmt::{Display, self}};
+
+use crate::{ast::{Sentence, Variable}, cfg::{Cfg, build}};
+
+/// a warning about the sentence, `i` is the index of the sentence in codes
+/// 
+/// the code is used to suppress the warning, see `lint::LINTS`
+#[derive(Debug, PartialEq)]
+pub struct Warning {
+    pub i: usize,
+    pub code: &'static str,
+    pub msg: String,
+}
+
+impl Warning {
+    pub fn new(i: usize, code: &'static str, msg: String) -> Self {
+        Warning { i, code, msg }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[warn] line {}: {} [{}]", self.i + 1, self.msg, self.code)
+    }
+}
+
+pub const UNINITIALIZED: &str = "W009";
+
+/// find the reads of variables which may not be assigned on some path
+///
+/// the checking of interpreter only looks at the source order,
+/// so a variable assigned in one branch and read after the join can pass it
+pub fn uninitialized_reads(codes: &[Sentence]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for cfg in build(codes) {
+        let assigned_in = definitely_assigned(codes, &cfg);
+        for (k, block) in cfg.blocks.iter().enumerate() {
+            // the unreachable block is never executed
+            let Some(mut assigned) = assigned_in[k].clone() else { continue };
+            for (i, code) in codes.iter().enumerate().take(block.end).skip(block.start) {
+                for id in reads(code) {
+                    if !assigned.contains(id) {
+                        warnings.push(Warning::new(
+                            i, UNINITIALIZED, format!("`{}` may be read before it's assigned", id)
+                        ));
+                    }
+                }
+                if let Some(id) = code.def() {
+                    assigned.insert(id);
+                }
+            }
+        }
+    }
+    warnings.sort_by_key(|w| w.i);
+    warnings.dedup();
+    warnings
+}
+
+/// the ids read by the sentence, taking the address of an id also needs it to exist
+fn reads<'a>(code: &Sentence<'a>) -> Vec<&'a str> {
+    let mut ids = code.uses();
+    ids.extend(code.operands().into_iter().filter_map(|var| match var {
+        Variable::Pointer(id) => Some(*id),
+        _ => None
+    }));
+    ids
+}
+
+/// the ids which are assigned on every path to the beginning of each block,
+/// `None` means the block is unreachable
+fn definitely_assigned<'a>(codes: &[Sentence<'a>], cfg: &Cfg) -> Vec<Option<BTreeSet<&'a str>>> {
+    let blocks = &cfg.blocks;
+    let defs: Vec<BTreeSet<&str>> = blocks.iter()
+        .map(|block| codes[block.start..block.end].iter().filter_map(|code| code.def()).collect())
+        .collect();
+
+    let mut assigned_in: Vec<Option<BTreeSet<&str>>> = vec![None; blocks.len()];
+    assigned_in[0] = Some(BTreeSet::new());
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for k in 1..blocks.len() {
+            // intersect the outs of the reachable predecessors
+            let new_in = blocks[k].preds.iter()
+                .filter_map(|p| assigned_in[*p].as_ref().map(|set| set | &defs[*p]))
+                .reduce(|a, b| &a & &b);
+            if new_in != assigned_in[k] {
+                assigned_in[k] = new_in;
+                changed = true;
+            }
+        }
+    }
+
+    assigned_in
+}
@@ -1,20 +1,24 @@
 
-#[derive(Debug, PartialEq)]
+use std::fmt::{Display, self};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Operator {
     Plus, Sub, Mul, Div, 
-    Equal, Greater, Less, GreaterEqual, LessEqual
+    Equal, NotEqual, Greater, Less, GreaterEqual, LessEqual
 }
 
 impl Operator {
+    /// wraps around on overflow like the backends, the divisor must not be 0
     pub fn calculate(&self, l: i32, r: i32) -> i32 {
         match self {
-            Operator::Plus => l + r,
-            Operator::Sub => l - r,
-            Operator::Mul => l * r,
-            Operator::Div => l / r,
+            Operator::Plus => l.wrapping_add(r),
+            Operator::Sub => l.wrapping_sub(r),
+            Operator::Mul => l.wrapping_mul(r),
+            Operator::Div => l.wrapping_div(r),
             _ => {
                 let flag = match self {
                     Operator::Equal => l == r,
+                    Operator::NotEqual => l != r,
                     Operator::Greater => l > r,
                     Operator::Less => l < r,
                     Operator::GreaterEqual => l >= r,
@@ -25,6 +29,17 @@ impl Operator {
             }
         }
     }
+
+    /// like `calculate`, but `None` if it would overflow or divide by zero
+    pub fn try_calculate(&self, l: i32, r: i32) -> Option<i32> {
+        match self {
+            Operator::Plus => l.checked_add(r),
+            Operator::Sub => l.checked_sub(r),
+            Operator::Mul => l.checked_mul(r),
+            Operator::Div => l.checked_div(r),
+            _ => Some(self.calculate(l, r))
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -48,7 +63,7 @@ impl <'a>Variable<'a> {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Sentence<'a> {
     Label(&'a str),
     Func(&'a str),
@@ -83,4 +98,99 @@ pub enum Sentence<'a> {
     Param(Variable<'a>),
     Read(Variable<'a>),
     Write(Variable<'a>)
-}
\ No newline at end of file
+}
+
+impl<'a> Sentence<'a> {
+    /// the variables used as right value
+    pub fn operands(&self) -> Vec<&Variable<'a>> {
+        match self {
+            Sentence::Assign { var, .. } => vec![var],
+            Sentence::Arith { l, r, .. } | Sentence::IfGoto { l, r, .. } => vec![l, r],
+            Sentence::Return(var) | Sentence::Arg(var) | Sentence::Write(var) => vec![var],
+            _ => vec![]
+        }
+    }
+
+    /// the left value, `*p` in `*p := x` is also included
+    pub fn target(&self) -> Option<&Variable<'a>> {
+        match self {
+            Sentence::Assign { target, .. } | Sentence::Arith { target, .. }
+                | Sentence::Call { target, .. } | Sentence::Dec { target, .. } 
+                | Sentence::Read(target) | Sentence::Param(target) => Some(target),
+            _ => None
+        }
+    }
+
+    /// the id which is assigned, `*p := x` doesn't assign `p`
+    pub fn def(&self) -> Option<&'a str> {
+        match self.target() {
+            Some(Variable::Id(id)) => Some(id),
+            _ => None
+        }
+    }
+
+    /// the ids whose value are read, the `p` of `*p` is read wherever it is
+    pub fn uses(&self) -> Vec<&'a str> {
+        let mut ids: Vec<&'a str> = self.operands().into_iter()
+            .filter_map(|var| match var {
+                Variable::Id(id) | Variable::Deref(id) => Some(*id),
+                _ => None
+            })
+            .collect();
+        if let Some(Variable::Deref(id)) = self.target() {
+            ids.push(id);
+        }
+        ids
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opt = match self {
+            Operator::Plus => "+",
+            Operator::Sub => "-",
+            Operator::Mul => "*",
+            Operator::Div => "/",
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::Greater => ">",
+            Operator::Less => "<",
+            Operator::GreaterEqual => ">=",
+            Operator::LessEqual => "<=",
+        };
+        write!(f, "{}", opt)
+    }
+}
+
+impl Display for Variable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variable::Number(number) => write!(f, "#{}", number),
+            Variable::Pointer(id) => write!(f, "&{}", id),
+            Variable::Deref(id) => write!(f, "*{}", id),
+            Variable::Id(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+impl Display for Sentence<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sentence::Label(label) => write!(f, "LABEL {} :", label),
+            Sentence::Func(func) => write!(f, "FUNCTION {} :", func),
+            Sentence::Assign { target, var } => write!(f, "{} := {}", target, var),
+            Sentence::Arith { l, r, opt, target } 
+                => write!(f, "{} := {} {} {}", target, l, opt, r),
+            Sentence::Goto(label) => write!(f, "GOTO {}", label),
+            Sentence::IfGoto { l, r, opt, label } 
+                => write!(f, "IF {} {} {} GOTO {}", l, opt, r, label),
+            Sentence::Return(var) => write!(f, "RETURN {}", var),
+            Sentence::Dec { target, size } => write!(f, "DEC {} {}", target, size),
+            Sentence::Arg(var) => write!(f, "ARG {}", var),
+            Sentence::Call { target, func } => write!(f, "{} := CALL {}", target, func),
+            Sentence::Param(var) => write!(f, "PARAM {}", var),
+            Sentence::Read(var) => write!(f, "READ {}", var),
+            Sentence::Write(var) => write!(f, "WRITE {}", var),
+        }
+    }
+}
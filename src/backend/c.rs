@@ -0,0 +1,209 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{
+    ast::{Sentence, Variable},
+    cfg::{foreign_jump, functions},
+    error::GenerateError,
+    opt::address_taken,
+};
+
+/// the runtime shared by all the functions
+///
+/// the arrays and the variables whose address is taken live in `mem`,
+/// so an address is a byte offset in it and fits in `int` like the interpreter
+const PRELUDE: &str = r#"#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define MEM_SIZE (1 << 24)
+#define ARG_SIZE (1 << 16)
+
+char mem[MEM_SIZE];
+int sp = 4;
+int args[ARG_SIZE];
+int nargs = 0;
+
+int load(int addr) {
+    int value;
+    if (addr < 0 || addr > MEM_SIZE - 4) {
+        fprintf(stderr, "invalid memory address %d\n", addr);
+        exit(1);
+    }
+    memcpy(&value, mem + addr, sizeof value);
+    return value;
+}
+
+void store(int addr, int value) {
+    if (addr < 0 || addr > MEM_SIZE - 4) {
+        fprintf(stderr, "invalid memory address %d\n", addr);
+        exit(1);
+    }
+    memcpy(mem + addr, &value, sizeof value);
+}
+
+int enter(int size) {
+    int fp = sp;
+    if (sp > MEM_SIZE - size) {
+        fprintf(stderr, "stack overflow\n");
+        exit(1);
+    }
+    sp += size;
+    return fp;
+}
+
+int read_int(void) {
+    int value;
+    if (scanf("%d", &value) != 1) {
+        fprintf(stderr, "no more input to read\n");
+        exit(1);
+    }
+    return value;
+}
+"#;
+
+/// translate the program into C, every IR function becomes a C function without parameters,
+/// and the arguments are passed by a stack just like the interpreter.
+/// a jump to the label of another function can't be expressed by `goto`, it's an error
+pub fn generate(codes: &[Sentence]) -> Result<String, GenerateError> {
+    if let Some(i) = foreign_jump(codes) {
+        return GenerateError::new_err(format!("`{}` jumps out of the function, C can't `goto` it", codes[i]), i)
+    }
+
+    let funcs = functions(codes);
+    let mut c = String::from(PRELUDE);
+
+    c.push('\n');
+    for (start, _) in &funcs {
+        if let Sentence::Func(name) = codes[*start] {
+            let _ = writeln!(c, "int {}(void);", func_name(name));
+        }
+    }
+
+    for (start, end) in funcs {
+        c.push('\n');
+        c.push_str(&Function::new(&codes[start..end]).generate(&codes[start..end]));
+    }
+
+    c.push_str("\nint main(void) {\n    f_main();\n    return 0;\n}\n");
+    Ok(c)
+}
+
+/// the names are prefixed to avoid the keywords of C
+fn func_name(name: &str) -> String {
+    format!("f_{}", name)
+}
+
+fn var_name(name: &str) -> String {
+    format!("v_{}", name)
+}
+
+struct Function<'a> {
+    name: &'a str,
+    // the offset in the frame of the variables in memory
+    offsets: BTreeMap<&'a str, i32>,
+    frame_size: i32,
+    // the variables which are C locals
+    locals: Vec<&'a str>,
+}
+
+impl<'a> Function<'a> {
+    fn new(codes: &[Sentence<'a>]) -> Self {
+        let Sentence::Func(name) = codes[0] else { unreachable!() };
+        let taken = address_taken(codes);
+        let (mut offsets, mut frame_size, mut locals) = (BTreeMap::new(), 0, Vec::new());
+
+        for code in codes {
+            let mut vars = code.operands();
+            vars.extend(code.target());
+            for id in vars.into_iter().filter_map(|var| var.get_id()) {
+                if !taken.contains(&id) {
+                    if !locals.contains(&id) {
+                        locals.push(id);
+                    }
+                } else if !offsets.contains_key(id) {
+                    offsets.insert(id, frame_size);
+                    frame_size += match code {
+                        Sentence::Dec { size, .. } => *size,
+                        _ => 4,
+                    };
+                }
+            }
+        }
+
+        Function { name, offsets, frame_size, locals }
+    }
+
+    /// the C expression of the right value
+    fn value(&self, var: &Variable) -> String {
+        match var {
+            Variable::Number(n) if *n == i32::MIN => "(-2147483647 - 1)".into(),
+            Variable::Number(n) => n.to_string(),
+            Variable::Id(id) => match self.offsets.get(id) {
+                Some(offset) => format!("load(fp + {})", offset),
+                None => var_name(id),
+            },
+            Variable::Pointer(id) => format!("(fp + {})", self.offsets[id]),
+            Variable::Deref(id) => format!("load({})", self.value(&Variable::Id(id))),
+        }
+    }
+
+    /// the C statement to assign the expression to target
+    fn assign(&self, target: &Variable, expr: String) -> String {
+        match target {
+            Variable::Id(id) => match self.offsets.get(id) {
+                Some(offset) => format!("store(fp + {}, {});", offset, expr),
+                None => format!("{} = {};", var_name(id), expr),
+            },
+            Variable::Deref(id) => format!("store({}, {});", self.value(&Variable::Id(id)), expr),
+            _ => unreachable!()
+        }
+    }
+
+    fn generate(&self, codes: &[Sentence<'a>]) -> String {
+        let mut c = format!("int {}(void) {{\n", func_name(self.name));
+        let _ = writeln!(c, "    int fp = enter({});", self.frame_size);
+        if !self.locals.is_empty() {
+            let locals: Vec<String> = self.locals.iter().map(|id| format!("{} = 0", var_name(id))).collect();
+            let _ = writeln!(c, "    int {};", locals.join(", "));
+        }
+        // fp is unused if no variable is in memory
+        c.push_str("    (void)fp;\n");
+
+        for code in &codes[1..] {
+            let line = match code {
+                Sentence::Label(label) => {
+                    let _ = writeln!(c, "l_{}:;", label);
+                    continue
+                }
+                Sentence::Assign { target, var } => self.assign(target, self.value(var)),
+                Sentence::Arith { l, r, opt, target } => {
+                    let expr = format!("{} {} {}", self.value(l), opt, self.value(r));
+                    self.assign(target, expr)
+                }
+                Sentence::Goto(label) => format!("goto l_{};", label),
+                Sentence::IfGoto { l, r, opt, label } => {
+                    format!("if ({} {} {}) goto l_{};", self.value(l), opt, self.value(r), label)
+                }
+                Sentence::Return(var) => {
+                    format!("{{ int value = {}; sp = fp; return value; }}", self.value(var))
+                }
+                // the memory is allocated in the frame
+                Sentence::Dec { .. } => continue,
+                Sentence::Arg(var) => format!("args[nargs++] = {};", self.value(var)),
+                Sentence::Call { target, func } => self.assign(target, format!("{}()", func_name(func))),
+                Sentence::Param(var) => self.assign(var, "args[--nargs]".into()),
+                Sentence::Read(var) => self.assign(var, "read_int()".into()),
+                Sentence::Write(var) => format!("printf(\"%d\\n\", {});", self.value(var)),
+                Sentence::Func(_) => unreachable!(),
+            };
+            let _ = writeln!(c, "    {} // {}", line, code);
+        }
+
+        // running past the end of function
+        if !matches!(codes.last(), Some(Sentence::Return(_))) {
+            c.push_str("    sp = fp;\n    return 0;\n");
+        }
+        c.push_str("}\n");
+        c
+    }
+}
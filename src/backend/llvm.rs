@@ -0,0 +1,360 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{
+    ast::{Operator, Sentence, Variable},
+    cfg::{Cfg, build, foreign_jump},
+    error::GenerateError,
+};
+
+/// the runtime helpers, an address of IR is the offset from `@base`,
+/// which is a local of `main`, so it fits in `i32` like the interpreter
+///
+/// the `alloca`s are still real pointers, only the values of `&x` are offsets. IR has no types,
+/// an address is an `i32` kept in an ordinary variable and computed by `+`, e.g.
+/// `t1 := &arr` then `t2 := t1 + #4` and `*t2 := #1`, so a 64-bit `ptr` can't be stored in
+/// the `i32` slot of `t1`. `irsim_addr` and `irsim_ptr` convert at `&x` and `*p`.
+/// the offsets stay small because all the frames are on the stack below `main`,
+/// it's not guaranteed by LLVM but holds for `lli` and the native targets
+const PRELUDE: &str = r#"@base = internal global i64 0
+@fmt.read = private constant [3 x i8] c"%d\00"
+@fmt.write = private constant [4 x i8] c"%d\0A\00"
+
+declare i32 @scanf(ptr, ...)
+declare i32 @printf(ptr, ...)
+declare void @exit(i32)
+
+define internal i32 @irsim_read() {
+  %value = alloca i32
+  %n = call i32 (ptr, ...) @scanf(ptr @fmt.read, ptr %value)
+  %ok = icmp eq i32 %n, 1
+  br i1 %ok, label %done, label %fail
+fail:
+  call void @exit(i32 1)
+  unreachable
+done:
+  %result = load i32, ptr %value
+  ret i32 %result
+}
+
+define internal void @irsim_write(i32 %value) {
+  call i32 (ptr, ...) @printf(ptr @fmt.write, i32 %value)
+  ret void
+}
+
+define internal i32 @irsim_addr(ptr %p) {
+  %base = load i64, ptr @base
+  %i = ptrtoint ptr %p to i64
+  %offset = sub i64 %i, %base
+  %addr = trunc i64 %offset to i32
+  ret i32 %addr
+}
+
+define internal ptr @irsim_ptr(i32 %addr) {
+  %base = load i64, ptr @base
+  %offset = sext i32 %addr to i64
+  %i = add i64 %base, %offset
+  %p = inttoptr i64 %i to ptr
+  ret ptr %p
+}
+"#;
+
+/// translate the program into LLVM IR with opaque pointers
+///
+/// every variable is an `alloca` and every block of the CFG is a basic block,
+/// so `opt -passes=mem2reg` or `-O2` is expected to make it SSA.
+/// a function takes its `PARAM`s as parameters, and the last `ARG` before `CALL` is the first one
+pub fn generate(codes: &[Sentence]) -> Result<String, GenerateError> {
+    if let Some(i) = foreign_jump(codes) {
+        return GenerateError::new_err(format!("`{}` jumps out of the function, `br` can't reach it", codes[i]), i)
+    }
+
+    let cfgs = build(codes);
+    // the number of parameters of every function
+    let params: BTreeMap<&str, usize> = cfgs.iter()
+        .map(|cfg| {
+            let count = codes[cfg.start..cfg.end].iter().filter(|code| matches!(code, Sentence::Param(_))).count();
+            (cfg.name, count)
+        })
+        .collect();
+
+    let mut ll = String::from(PRELUDE);
+    for cfg in &cfgs {
+        ll.push('\n');
+        ll.push_str(&Function::new(codes, cfg, &params).generate());
+    }
+
+    ll.push_str(concat!(
+        "\ndefine i32 @main() {\n",
+        "  %anchor = alloca i8\n",
+        "  %base = ptrtoint ptr %anchor to i64\n",
+        "  store i64 %base, ptr @base\n",
+        "  %result = call i32 @f_main()\n",
+        "  ret i32 0\n",
+        "}\n",
+    ));
+    Ok(ll)
+}
+
+struct Function<'a, 'c> {
+    codes: &'c [Sentence<'a>],
+    cfg: &'c Cfg<'a>,
+    params: &'c BTreeMap<&'a str, usize>,
+    labels: BTreeMap<&'a str, usize>,
+    // the values of ARG before CALL
+    args: Vec<String>,
+    // the number of PARAM met
+    param: usize,
+    temps: usize,
+    ll: String,
+}
+
+impl<'a, 'c> Function<'a, 'c> {
+    fn new(codes: &'c [Sentence<'a>], cfg: &'c Cfg<'a>, params: &'c BTreeMap<&'a str, usize>) -> Self {
+        // the labels of other functions are rejected by `generate`
+        let labels = cfg.blocks.iter().enumerate()
+            .filter_map(|(k, block)| match codes[block.start] {
+                Sentence::Label(label) => Some((label, k)),
+                _ => None
+            })
+            .collect();
+        Function { codes, cfg, params, labels, args: Vec::new(), param: 0, temps: 0, ll: String::new() }
+    }
+
+    fn emit(&mut self, inst: String) {
+        let _ = writeln!(self.ll, "  {}", inst);
+    }
+
+    /// a new SSA value
+    fn temp(&mut self) -> String {
+        self.temps += 1;
+        format!("%t{}", self.temps)
+    }
+
+    /// load the value of variable into an SSA value or a constant
+    fn value(&mut self, var: &Variable) -> String {
+        match var {
+            Variable::Number(n) => n.to_string(),
+            Variable::Id(id) => {
+                let t = self.temp();
+                self.emit(format!("{} = load i32, ptr %v_{}", t, id));
+                t
+            }
+            Variable::Pointer(id) => {
+                let t = self.temp();
+                self.emit(format!("{} = call i32 @irsim_addr(ptr %v_{})", t, id));
+                t
+            }
+            Variable::Deref(id) => {
+                let p = self.pointer(id);
+                let t = self.temp();
+                self.emit(format!("{} = load i32, ptr {}", t, p));
+                t
+            }
+        }
+    }
+
+    /// the pointer which the value of `id` points to
+    fn pointer(&mut self, id: &str) -> String {
+        let addr = self.value(&Variable::Id(id));
+        let p = self.temp();
+        self.emit(format!("{} = call ptr @irsim_ptr(i32 {})", p, addr));
+        p
+    }
+
+    fn assign(&mut self, target: &Variable, value: String) {
+        let p = match target {
+            Variable::Id(id) => format!("%v_{}", id),
+            Variable::Deref(id) => self.pointer(id),
+            _ => unreachable!()
+        };
+        self.emit(format!("store i32 {}, ptr {}", value, p));
+    }
+
+    fn sentence(&mut self, code: &Sentence<'a>) {
+        match code {
+            Sentence::Assign { target, var } => {
+                let value = self.value(var);
+                self.assign(target, value);
+            }
+            Sentence::Arith { l, r, opt, target } => {
+                let (l, r) = (self.value(l), self.value(r));
+                let inst = match opt {
+                    Operator::Plus => "add",
+                    Operator::Sub => "sub",
+                    Operator::Mul => "mul",
+                    _ => "sdiv",
+                };
+                let t = self.temp();
+                self.emit(format!("{} = {} i32 {}, {}", t, inst, l, r));
+                self.assign(target, t);
+            }
+            Sentence::Return(var) => {
+                let value = self.value(var);
+                self.emit(format!("ret i32 {}", value));
+            }
+            Sentence::Arg(var) => {
+                let value = self.value(var);
+                self.args.push(value);
+            }
+            Sentence::Call { target, func } => {
+                // the missing arguments are 0, and the extra ones are dropped
+                let count = self.params.get(func).copied().unwrap_or(0);
+                let mut args: Vec<String> = self.args.drain(..).rev().map(|a| format!("i32 {}", a)).collect();
+                args.resize(count, "i32 0".into());
+                let t = self.temp();
+                self.emit(format!("{} = call i32 @f_{}({})", t, func, args.join(", ")));
+                self.assign(target, t);
+            }
+            Sentence::Param(var) => {
+                let value = format!("%p{}", self.param);
+                self.param += 1;
+                self.assign(var, value);
+            }
+            Sentence::Read(var) => {
+                let t = self.temp();
+                self.emit(format!("{} = call i32 @irsim_read()", t));
+                self.assign(var, t);
+            }
+            Sentence::Write(var) => {
+                let value = self.value(var);
+                self.emit(format!("call void @irsim_write(i32 {})", value));
+            }
+            // the labels and jumps are handled by the blocks, and the arrays are allocated at entry
+            _ => ()
+        }
+    }
+
+    fn generate(mut self) -> String {
+        let (codes, cfg) = (self.codes, self.cfg);
+        let body = &codes[cfg.start..cfg.end];
+        let params: Vec<String> = (0..self.params[cfg.name]).map(|k| format!("i32 %p{}", k)).collect();
+        let mut ll = format!("define i32 @f_{}({}) {{\nentry:\n", cfg.name, params.join(", "));
+
+        // every variable has a slot, the arrays are allocated here too
+        let mut slots: Vec<&str> = Vec::new();
+        for code in body {
+            let mut vars = code.operands();
+            vars.extend(code.target());
+            for id in vars.into_iter().filter_map(|var| var.get_id()) {
+                if slots.contains(&id) {
+                    continue
+                }
+                slots.push(id);
+                match code {
+                    Sentence::Dec { size, .. } => {
+                        let _ = writeln!(ll, "  %v_{} = alloca [{} x i8], align 4", id, size);
+                    }
+                    _ => { let _ = writeln!(ll, "  %v_{} = alloca i32", id); }
+                }
+            }
+        }
+        let _ = writeln!(ll, "  br label %b0");
+
+        let mut fall_off = false;
+        for (k, block) in cfg.blocks.iter().enumerate() {
+            let _ = writeln!(self.ll, "b{}:", k);
+            for code in &codes[block.start..block.end] {
+                if !matches!(code, Sentence::Func(_) | Sentence::Label(_)) {
+                    let _ = writeln!(self.ll, "  ; {}", code);
+                }
+                self.sentence(code);
+            }
+
+            // the block after this one, the last block runs past the end of function
+            let mut next = || match k + 1 < cfg.blocks.len() {
+                true => format!("%b{}", k + 1),
+                false => { fall_off = true; "%fall".into() }
+            };
+            match &codes[block.end - 1] {
+                Sentence::Return(_) => (),
+                Sentence::Goto(label) => {
+                    let target = self.labels[label];
+                    self.emit(format!("br label %b{}", target));
+                }
+                Sentence::IfGoto { l, r, opt, label } => {
+                    let (l, r) = (self.value(l), self.value(r));
+                    let cond = match opt {
+                        Operator::Equal => "eq",
+                        Operator::NotEqual => "ne",
+                        Operator::Greater => "sgt",
+                        Operator::Less => "slt",
+                        Operator::GreaterEqual => "sge",
+                        _ => "sle",
+                    };
+                    let t = self.temp();
+                    self.emit(format!("{} = icmp {} i32 {}, {}", t, cond, l, r));
+                    let target = self.labels[label];
+                    self.emit(format!("br i1 {}, label %b{}, label {}", t, target, next()));
+                }
+                _ => self.emit(format!("br label {}", next())),
+            }
+        }
+        // running past the end of function returns 0
+        if fall_off {
+            self.ll.push_str("fall:\n  ret i32 0\n");
+        }
+
+        ll.push_str(&self.ll);
+        ll.push_str("}\n");
+        ll
+    }
+}
+
+/// count the instructions run by the LLVM IR, usually the output of `opt -O2`
+///
+/// every basic block adds its size to `@irsim.count` when it's entered, `phi` and
+/// the `llvm.*` intrinsics aren't counted since they aren't instructions on the machine.
+/// `main` is renamed and wrapped to print `instructions: N` to stderr when it returns
+pub fn instrument(ll: &str) -> String {
+    let lines: Vec<&str> = ll.lines().collect();
+    let is_inst = |line: &str| line.starts_with("  ") && !line.trim_start().starts_with(';');
+    let is_phi = |line: &str| line.contains(" = phi ");
+
+    let mut result = String::new();
+    let (mut in_func, mut blocks, mut i) = (false, 0, 0);
+    while i < lines.len() {
+        let line = lines[i];
+        match line.strip_prefix("define i32 @main(") {
+            Some(rest) => { let _ = writeln!(result, "define i32 @irsim_main({}", rest); }
+            None => { let _ = writeln!(result, "{}", line); }
+        }
+        i += 1;
+
+        // a block starts right after its label, or `define` if the entrance has no label
+        let starts = match line {
+            _ if line.starts_with("define ") => { in_func = true; lines.get(i).is_some_and(|line| is_inst(line)) }
+            "}" => { in_func = false; false }
+            _ => in_func && !line.starts_with(' ') && line.contains(':'),
+        };
+        if !starts {
+            continue
+        }
+        let body: Vec<&str> = lines[i..].iter().copied().take_while(|line| is_inst(line)).collect();
+        let size = body.iter().filter(|line| !is_phi(line) && !line.contains("@llvm.")).count();
+        // the `phi`s must be at the beginning of block
+        for phi in body.iter().take_while(|line| is_phi(line)) {
+            let _ = writeln!(result, "{}", phi);
+            i += 1;
+        }
+        let _ = writeln!(result, "  %irsim.{} = atomicrmw add ptr @irsim.count, i64 {} monotonic", blocks, size);
+        blocks += 1;
+    }
+    // `source_filename` must be at the beginning, so they are appended
+    result.push_str(COUNTER);
+    result
+}
+
+/// the counter and the `main` wrapping the renamed one, for `instrument`
+const COUNTER: &str = r#"
+@irsim.count = internal global i64 0
+@fmt.count = private constant [19 x i8] c"instructions: %ld\0A\00"
+
+declare i32 @dprintf(i32, ptr, ...)
+
+define i32 @main() {
+  %result = call i32 @irsim_main()
+  %count = load i64, ptr @irsim.count
+  call i32 (i32, ptr, ...) @dprintf(i32 2, ptr @fmt.count, i64 %count)
+  ret i32 %result
+}
+"#;
@@ -0,0 +1,316 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{
+    ast::{Operator, Sentence, Variable},
+    cfg::functions,
+};
+
+/// the registers which can hold variables, `$t8` and `$t9` are kept for temporary values
+const REGS: [&str; 8] = ["$t0", "$t1", "$t2", "$t3", "$t4", "$t5", "$t6", "$t7"];
+const SCRATCH: [&str; 2] = ["$t8", "$t9"];
+
+/// translate the program into MIPS32 assembly which can be run by SPIM or MARS
+///
+/// every function has a frame like this, the arguments are pushed by caller
+/// and the first `PARAM` gets the last `ARG`, just like the interpreter
+/// ```text
+/// | ARG n-1 |  12($fp)
+/// | ARG n   |   8($fp)
+/// | $ra     |   4($fp)
+/// | old $fp |   0($fp)
+/// | locals  |  -4($fp) ...
+/// ```
+pub fn generate(codes: &[Sentence]) -> String {
+    let mut asm = String::from(concat!(
+        ".data\n",
+        "_ret: .asciiz \"\\n\"\n",
+        ".globl main\n",
+        ".text\n",
+    ));
+
+    for (start, end) in functions(codes) {
+        let mut func = Function::new(&codes[start..end]);
+        func.generate(&codes[start..end]);
+        asm.push_str(&func.asm);
+    }
+
+    asm
+}
+
+/// the name of function and label in assembly, they can't be the same as instructions
+fn func_name(name: &str) -> String {
+    if name == "main" { name.into() } else { format!("f_{}", name) }
+}
+
+fn label_name(name: &str) -> String {
+    format!("l_{}", name)
+}
+
+struct Function<'a> {
+    name: &'a str,
+    asm: String,
+    // the offset from $fp of every variable, an array starts from its offset
+    offsets: BTreeMap<&'a str, i32>,
+    frame_size: i32,
+    // the variables which may be changed by pointer, they are always in memory
+    in_memory: Vec<&'a str>,
+    // the variable in the register and whether it's changed, ordered by the last use
+    regs: Vec<(&'static str, &'a str, bool)>,
+    // the count of ARG before CALL
+    args: i32,
+    params: i32,
+}
+
+impl<'a> Function<'a> {
+    fn new(codes: &[Sentence<'a>]) -> Self {
+        let Sentence::Func(name) = codes[0] else { unreachable!() };
+        let (mut offsets, mut frame_size, mut in_memory) = (BTreeMap::new(), 0, Vec::new());
+
+        for code in codes {
+            let mut vars: Vec<&Variable<'a>> = code.operands();
+            vars.extend(code.target());
+            for var in vars {
+                let Some(id) = var.get_id() else { continue };
+                if let Variable::Pointer(_) = var {
+                    in_memory.push(id);
+                }
+                if !offsets.contains_key(id) {
+                    let size = match code {
+                        Sentence::Dec { size, .. } => *size,
+                        _ => 4
+                    };
+                    frame_size += size;
+                    offsets.insert(id, -frame_size);
+                }
+            }
+            if let Sentence::Dec { target: Variable::Id(id), .. } = code {
+                in_memory.push(id);
+            }
+        }
+
+        Function {
+            name, asm: String::new(), offsets, frame_size, in_memory,
+            regs: Vec::new(), args: 0, params: 0
+        }
+    }
+
+    fn emit(&mut self, inst: String) {
+        let _ = writeln!(self.asm, "  {}", inst);
+    }
+
+    /// write the changed variables back to memory
+    fn spill(&mut self) {
+        for k in 0..self.regs.len() {
+            let (reg, id, dirty) = self.regs[k];
+            if dirty {
+                self.emit(format!("sw {}, {}($fp)", reg, self.offsets[id]));
+                self.regs[k].2 = false;
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.spill();
+        self.regs.clear();
+    }
+
+    /// get the register of variable, it's loaded from memory if `load`
+    fn reg_of(&mut self, id: &'a str, load: bool) -> &'static str {
+        if let Some(k) = self.regs.iter().position(|(_, var, _)| *var == id) {
+            // move it to the end as the most recently used
+            let item = self.regs.remove(k);
+            self.regs.push(item);
+            return item.0
+        }
+
+        let reg = match REGS.iter().find(|reg| self.regs.iter().all(|(r, _, _)| r != *reg)) {
+            Some(reg) => *reg,
+            None => {
+                // evict the least recently used
+                let (reg, var, dirty) = self.regs.remove(0);
+                if dirty {
+                    self.emit(format!("sw {}, {}($fp)", reg, self.offsets[var]));
+                }
+                reg
+            }
+        };
+        if load {
+            self.emit(format!("lw {}, {}($fp)", reg, self.offsets[id]));
+        }
+        self.regs.push((reg, id, false));
+        reg
+    }
+
+    /// get the value of variable into a register, `scratch` is used if it's not in register
+    fn operand(&mut self, var: &Variable<'a>, scratch: &'static str) -> &'static str {
+        match var {
+            Variable::Number(n) => {
+                self.emit(format!("li {}, {}", scratch, n));
+                scratch
+            }
+            Variable::Id(id) if self.in_memory.contains(id) => {
+                self.emit(format!("lw {}, {}($fp)", scratch, self.offsets[id]));
+                scratch
+            }
+            Variable::Id(id) => self.reg_of(id, true),
+            Variable::Pointer(id) => {
+                self.emit(format!("addi {}, $fp, {}", scratch, self.offsets[id]));
+                scratch
+            }
+            Variable::Deref(id) => {
+                let reg = self.operand(&Variable::Id(id), scratch);
+                self.emit(format!("lw {}, 0({})", scratch, reg));
+                scratch
+            }
+        }
+    }
+
+    /// the register to save the result of target,
+    /// `finish` must be called after the result is in the register
+    fn target(&mut self, target: &Variable<'a>) -> &'static str {
+        match target {
+            Variable::Id(id) if !self.in_memory.contains(id) => self.reg_of(id, false),
+            _ => SCRATCH[0],
+        }
+    }
+
+    fn finish(&mut self, target: &Variable<'a>, reg: &'static str) {
+        match target {
+            Variable::Id(id) if !self.in_memory.contains(id) => {
+                let k = self.regs.iter().position(|(r, _, _)| *r == reg).unwrap();
+                self.regs[k].2 = true;
+            }
+            Variable::Id(id) => self.emit(format!("sw {}, {}($fp)", reg, self.offsets[id])),
+            Variable::Deref(id) => {
+                let addr = self.operand(&Variable::Id(id), SCRATCH[1]);
+                self.emit(format!("sw {}, 0({})", reg, addr));
+            }
+            _ => unreachable!()
+        }
+    }
+
+    fn assign(&mut self, target: &Variable<'a>, value: &'static str) {
+        let reg = self.target(target);
+        if reg != value {
+            self.emit(format!("move {}, {}", reg, value));
+        }
+        self.finish(target, reg);
+    }
+
+    fn generate(&mut self, codes: &[Sentence<'a>]) {
+        let _ = writeln!(self.asm, "\n{}:", func_name(self.name));
+        self.emit("addi $sp, $sp, -8".into());
+        self.emit("sw $ra, 4($sp)".into());
+        self.emit("sw $fp, 0($sp)".into());
+        self.emit("move $fp, $sp".into());
+        if self.frame_size > 0 {
+            self.emit(format!("addi $sp, $sp, {}", -self.frame_size));
+        }
+
+        for code in &codes[1..] {
+            self.emit(format!("# {}", code));
+            self.sentence(code);
+        }
+    }
+
+    fn sentence(&mut self, code: &Sentence<'a>) {
+        match code {
+            Sentence::Label(label) => {
+                self.clear();
+                let _ = writeln!(self.asm, "{}:", label_name(label));
+            }
+            Sentence::Assign { target, var } => {
+                let value = self.operand(var, SCRATCH[0]);
+                self.assign(target, value);
+            }
+            Sentence::Arith { l, r, opt, target } => {
+                let l = self.operand(l, SCRATCH[0]);
+                let r = self.operand(r, SCRATCH[1]);
+                let reg = self.target(target);
+                match opt {
+                    Operator::Plus => self.emit(format!("addu {}, {}, {}", reg, l, r)),
+                    Operator::Sub => self.emit(format!("subu {}, {}, {}", reg, l, r)),
+                    Operator::Mul => self.emit(format!("mul {}, {}, {}", reg, l, r)),
+                    Operator::Div => {
+                        self.emit(format!("div {}, {}", l, r));
+                        self.emit(format!("mflo {}", reg));
+                    }
+                    _ => unreachable!()
+                }
+                self.finish(target, reg);
+            }
+            Sentence::Goto(label) => {
+                self.clear();
+                self.emit(format!("j {}", label_name(label)));
+            }
+            Sentence::IfGoto { l, r, opt, label } => {
+                let l = self.operand(l, SCRATCH[0]);
+                let r = self.operand(r, SCRATCH[1]);
+                // the registers are still valid if the branch isn't taken
+                self.spill();
+                let inst = match opt {
+                    Operator::Equal => "beq",
+                    Operator::NotEqual => "bne",
+                    Operator::Greater => "bgt",
+                    Operator::Less => "blt",
+                    Operator::GreaterEqual => "bge",
+                    Operator::LessEqual => "ble",
+                    _ => unreachable!()
+                };
+                self.emit(format!("{} {}, {}, {}", inst, l, r, label_name(label)));
+            }
+            Sentence::Return(var) => {
+                let value = self.operand(var, SCRATCH[0]);
+                self.regs.clear();
+                if self.name == "main" {
+                    self.emit("li $v0, 10".into());
+                    self.emit("syscall".into());
+                    return
+                }
+                self.emit(format!("move $v0, {}", value));
+                self.emit("move $sp, $fp".into());
+                self.emit("lw $fp, 0($sp)".into());
+                self.emit("lw $ra, 4($sp)".into());
+                self.emit("addi $sp, $sp, 8".into());
+                self.emit("jr $ra".into());
+            }
+            // the memory is allocated in the frame
+            Sentence::Dec { .. } => (),
+            Sentence::Arg(var) => {
+                let value = self.operand(var, SCRATCH[0]);
+                self.emit("addi $sp, $sp, -4".into());
+                self.emit(format!("sw {}, 0($sp)", value));
+                self.args += 1;
+            }
+            Sentence::Call { target, func } => {
+                self.clear();
+                self.emit(format!("jal {}", func_name(func)));
+                if self.args > 0 {
+                    self.emit(format!("addi $sp, $sp, {}", 4 * self.args));
+                    self.args = 0;
+                }
+                self.assign(target, "$v0");
+            }
+            Sentence::Param(var) => {
+                self.emit(format!("lw {}, {}($fp)", SCRATCH[0], 8 + 4 * self.params));
+                self.params += 1;
+                self.assign(var, SCRATCH[0]);
+            }
+            Sentence::Read(var) => {
+                self.emit("li $v0, 5".into());
+                self.emit("syscall".into());
+                self.assign(var, "$v0");
+            }
+            Sentence::Write(var) => {
+                let value = self.operand(var, SCRATCH[0]);
+                self.emit(format!("move $a0, {}", value));
+                self.emit("li $v0, 1".into());
+                self.emit("syscall".into());
+                self.emit("la $a0, _ret".into());
+                self.emit("li $v0, 4".into());
+                self.emit("syscall".into());
+            }
+            Sentence::Func(_) => unreachable!(),
+        }
+    }
+}
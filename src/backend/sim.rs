@@ -0,0 +1,408 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use crate::{
+    ast::Operator,
+    error::{AsmError, RuntimeError as RError, RuntimeErrorKind::*},
+    utils::io::{ReadFunc, WriteFunc},
+};
+
+const TEXT_BASE: u32 = 0x0040_0000;
+const DATA_BASE: u32 = 0x1001_0000;
+const STACK_TOP: u32 = 0x7fff_f000;
+const STACK_SIZE: u32 = 1 << 22;
+
+const REG_NAMES: [&str; 32] = [
+    "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3",
+    "t0", "t1", "t2", "t3", "t4", "t5", "t6", "t7",
+    "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7",
+    "t8", "t9", "k0", "k1", "gp", "sp", "fp", "ra",
+];
+const V0: usize = 2;
+const A0: usize = 4;
+const SP: usize = 29;
+const RA: usize = 31;
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Reg(usize),
+    Imm(i32),
+}
+
+/// the pseudo instructions are kept as one instruction, so they are counted once
+#[derive(Debug)]
+enum Inst {
+    // only Plus, Sub and Mul
+    Arith(Operator, usize, usize, Operand),
+    Li(usize, i32),
+    Move(usize, usize),
+    Div(usize, usize),
+    Mflo(usize),
+    Mfhi(usize),
+    Lw(usize, i32, usize),
+    Sw(usize, i32, usize),
+    J(usize),
+    Jal(usize),
+    Jr(usize),
+    // jump to the target if `rs opt rt`
+    Branch(Operator, usize, Operand, usize),
+    Syscall,
+    Nop,
+}
+
+/// run the MIPS32 subset used by the generated code,
+/// it uses the same i/o and counts the instructions like the interpreter
+///
+/// supported instructions: `add(u) addi(u) sub(u) mul div mflo mfhi li la move lw sw
+/// j jal jr beq bne bgt blt bge ble beqz bnez syscall nop`,
+/// and syscalls: print int (1), print string (4), read int (5), exit (10), print char (11)
+pub struct Simulator {
+    insts: Vec<Inst>,
+    // the line number of every instruction
+    lines: Vec<usize>,
+    pc: usize,
+    regs: [i32; 32],
+    hi: i32,
+    lo: i32,
+    data: Vec<u8>,
+    stack: Vec<u8>,
+    count: usize,
+    limit: Option<usize>,
+    read: ReadFunc,
+    write: WriteFunc,
+}
+
+/// the items of a line after the label is removed
+fn tokens(line: &str) -> Vec<&str> {
+    line.split(|c: char| c == ',' || c.is_whitespace()).filter(|s| !s.is_empty()).collect()
+}
+
+fn strip_comment(line: &str) -> &str {
+    // `#` in a string is not a comment
+    let mut quoted = false;
+    for (k, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..k],
+            _ => ()
+        }
+    }
+    line
+}
+
+fn parse_string(text: &str, i: usize) -> Result<Vec<u8>, AsmError> {
+    let Some(inner) = text.strip_prefix('"').and_then(|s| s.strip_suffix('"')) else {
+        return AsmError::new_err(format!("invalid string `{}`", text), i)
+    };
+    let (mut bytes, mut chars) = (Vec::new(), inner.chars());
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('0') => '\0',
+                Some(c @ ('\\' | '"')) => c,
+                _ => return AsmError::new_err(format!("invalid escape in `{}`", text), i),
+            },
+            c => c,
+        };
+        let mut buf = [0; 4];
+        bytes.extend(c.encode_utf8(&mut buf).as_bytes());
+    }
+    Ok(bytes)
+}
+
+fn parse_imm(text: &str, i: usize) -> Result<i32, AsmError> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => i64::from_str(digits).ok(),
+    };
+    match value.map(|v| if negative { -v } else { v }) {
+        Some(v) if (i32::MIN as i64..=u32::MAX as i64).contains(&v) => Ok(v as i32),
+        _ => AsmError::new_err(format!("invalid immediate `{}`", text), i),
+    }
+}
+
+fn parse_reg(text: &str, i: usize) -> Result<usize, AsmError> {
+    let name = text.strip_prefix('$').unwrap_or("");
+    let reg = match usize::from_str(name) {
+        Ok(k) => (k < 32).then_some(k),
+        Err(_) => REG_NAMES.iter().position(|r| *r == name),
+    };
+    match reg {
+        Some(reg) => Ok(reg),
+        None => AsmError::new_err(format!("invalid register `{}`", text), i),
+    }
+}
+
+/// the memory operand like `-4($fp)`
+fn parse_addr(text: &str, i: usize) -> Result<(i32, usize), AsmError> {
+    let Some((offset, rest)) = text.split_once('(') else {
+        return AsmError::new_err(format!("invalid address `{}`", text), i)
+    };
+    let offset = if offset.is_empty() { 0 } else { parse_imm(offset, i)? };
+    let reg = parse_reg(rest.strip_suffix(')').unwrap_or(""), i)?;
+    Ok((offset, reg))
+}
+
+impl Simulator {
+    pub fn new(source: &str, read: ReadFunc, write: WriteFunc) -> Result<Simulator, AsmError> {
+        // 1. lay out the data and find the labels
+        let (mut data, mut texts) = (Vec::new(), Vec::new());
+        let (mut labels, mut in_text) = (BTreeMap::new(), true);
+
+        for (k, line) in source.lines().enumerate() {
+            let i = k + 1;
+            let mut line = strip_comment(line).trim();
+            if let Some((label, rest)) = line.split_once(':').filter(|(l, _)| !l.contains('"')) {
+                let label = label.trim();
+                let addr = if in_text { TEXT_BASE + 4 * texts.len() as u32 } else { DATA_BASE + data.len() as u32 };
+                if labels.insert(label, addr).is_some() {
+                    return AsmError::new_err(format!("label `{}` is defined twice", label), i)
+                }
+                line = rest.trim();
+            }
+            if line.is_empty() {
+                continue
+            }
+
+            let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            match name {
+                ".data" => in_text = false,
+                ".text" => in_text = true,
+                ".globl" | ".align" => (),
+                ".asciiz" | ".ascii" if !in_text => {
+                    data.extend(parse_string(rest, i)?);
+                    if name == ".asciiz" {
+                        data.push(0);
+                    }
+                }
+                ".word" if !in_text => for item in tokens(rest) {
+                    data.extend(parse_imm(item, i)?.to_le_bytes());
+                },
+                ".space" if !in_text => data.resize(data.len() + parse_imm(rest, i)?.max(0) as usize, 0),
+                _ if in_text && !name.starts_with('.') => texts.push((i, name, tokens(rest))),
+                _ => return AsmError::new_err(format!("unsupported `{}`", name), i),
+            }
+        }
+
+        // 2. translate the instructions
+        let (mut insts, mut lines) = (Vec::new(), Vec::new());
+        for (i, name, args) in texts {
+            insts.push(Self::instruction(name, &args, &labels, i)?);
+            lines.push(i);
+        }
+
+        let Some(main) = labels.get("main") else {
+            return AsmError::new_err("`main` not found".into(), source.lines().count())
+        };
+        let mut regs = [0; 32];
+        regs[SP] = STACK_TOP as i32;
+        // returning from main ends the program
+        regs[RA] = (TEXT_BASE + 4 * insts.len() as u32) as i32;
+
+        Ok(Simulator {
+            pc: ((main - TEXT_BASE) / 4) as usize, insts, lines, regs, hi: 0, lo: 0,
+            data, stack: vec![0; STACK_SIZE as usize], count: 0, limit: None, read, write
+        })
+    }
+
+    fn instruction(name: &str, args: &[&str], labels: &BTreeMap<&str, u32>, i: usize) -> Result<Inst, AsmError> {
+        let arity = match name {
+            "syscall" | "nop" => 0,
+            "j" | "jal" | "jr" | "mflo" | "mfhi" => 1,
+            "li" | "la" | "move" | "div" | "lw" | "sw" | "beqz" | "bnez" => 2,
+            _ => 3,
+        };
+        if args.len() != arity {
+            return AsmError::new_err(format!("`{}` needs {} operand(s)", name, arity), i)
+        }
+
+        let reg = |k: usize| parse_reg(args[k], i);
+        let reg_or_imm = |k: usize| match args[k].starts_with('$') {
+            true => parse_reg(args[k], i).map(Operand::Reg),
+            false => parse_imm(args[k], i).map(Operand::Imm),
+        };
+        let label = |k: usize| match labels.get(args[k]) {
+            Some(addr) if *addr >= TEXT_BASE && *addr < DATA_BASE => Ok(((addr - TEXT_BASE) / 4) as usize),
+            _ => AsmError::new_err(format!("undefined label `{}`", args[k]), i),
+        };
+        let branch = |opt: Operator| Ok(Inst::Branch(opt, reg(0)?, reg_or_imm(1)?, label(2)?));
+
+        match name {
+            "add" | "addu" | "addi" | "addiu" => Ok(Inst::Arith(Operator::Plus, reg(0)?, reg(1)?, reg_or_imm(2)?)),
+            "sub" | "subu" => Ok(Inst::Arith(Operator::Sub, reg(0)?, reg(1)?, reg_or_imm(2)?)),
+            "mul" => Ok(Inst::Arith(Operator::Mul, reg(0)?, reg(1)?, reg_or_imm(2)?)),
+            "div" => Ok(Inst::Div(reg(0)?, reg(1)?)),
+            "mflo" => Ok(Inst::Mflo(reg(0)?)),
+            "mfhi" => Ok(Inst::Mfhi(reg(0)?)),
+            "li" => Ok(Inst::Li(reg(0)?, parse_imm(args[1], i)?)),
+            "la" => match labels.get(args[1]) {
+                Some(addr) => Ok(Inst::Li(reg(0)?, *addr as i32)),
+                None => AsmError::new_err(format!("undefined label `{}`", args[1]), i),
+            },
+            "move" => Ok(Inst::Move(reg(0)?, reg(1)?)),
+            "lw" => { let (offset, base) = parse_addr(args[1], i)?; Ok(Inst::Lw(reg(0)?, offset, base)) }
+            "sw" => { let (offset, base) = parse_addr(args[1], i)?; Ok(Inst::Sw(reg(0)?, offset, base)) }
+            "j" => Ok(Inst::J(label(0)?)),
+            "jal" => Ok(Inst::Jal(label(0)?)),
+            "jr" => Ok(Inst::Jr(reg(0)?)),
+            "beq" => branch(Operator::Equal),
+            "bne" => branch(Operator::NotEqual),
+            "bgt" => branch(Operator::Greater),
+            "blt" => branch(Operator::Less),
+            "bge" => branch(Operator::GreaterEqual),
+            "ble" => branch(Operator::LessEqual),
+            "beqz" => Ok(Inst::Branch(Operator::Equal, reg(0)?, Operand::Imm(0), label(1)?)),
+            "bnez" => Ok(Inst::Branch(Operator::NotEqual, reg(0)?, Operand::Imm(0), label(1)?)),
+            "syscall" => Ok(Inst::Syscall),
+            "nop" => Ok(Inst::Nop),
+            _ => AsmError::new_err(format!("unsupported instruction `{}`", name), i),
+        }
+    }
+
+    /// stop the program with an error once it runs more than `limit` instructions
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+
+    /// the 4 bytes at the address, it must be aligned for a word
+    fn memory(&mut self, addr: u32, aligned: bool) -> Result<&mut [u8], RError> {
+        let line = self.lines.get(self.pc).copied().unwrap_or(0);
+        if aligned && !addr.is_multiple_of(4) {
+            return RError::new_err(MemoryError, line)
+        }
+        let (segment, offset) = if (DATA_BASE..DATA_BASE + self.data.len() as u32).contains(&addr) {
+            (&mut self.data, addr - DATA_BASE)
+        } else if (STACK_TOP - STACK_SIZE..STACK_TOP).contains(&addr) {
+            (&mut self.stack, addr - (STACK_TOP - STACK_SIZE))
+        } else {
+            return RError::new_err(MemoryError, line)
+        };
+        let offset = offset as usize;
+        match segment.get_mut(offset..offset + if aligned { 4 } else { 1 }) {
+            Some(bytes) => Ok(bytes),
+            None => RError::new_err(MemoryError, line),
+        }
+    }
+
+    fn value(&self, operand: Operand) -> i32 {
+        match operand {
+            Operand::Reg(reg) => self.regs[reg],
+            Operand::Imm(imm) => imm,
+        }
+    }
+
+    fn syscall(&mut self, line: usize) -> Result<Option<usize>, RError> {
+        match self.regs[V0] {
+            1 => (self.write)(self.regs[A0].to_string()),
+            4 => {
+                let mut bytes = Vec::new();
+                for addr in self.regs[A0] as u32.. {
+                    match self.memory(addr, false)?[0] {
+                        0 => break,
+                        byte => bytes.push(byte),
+                    }
+                }
+                (self.write)(String::from_utf8_lossy(&bytes).into())
+            }
+            5 => {
+                let input = match (self.read)() {
+                    Some(input) => input,
+                    None => return RError::new_err(InputExhaustedError, line),
+                };
+                self.regs[V0] = match i32::from_str(input.trim()) {
+                    Ok(value) => value,
+                    Err(_) => return RError::new_err(InputError, line),
+                };
+            }
+            10 => return Ok(Some(self.count)),
+            11 => (self.write)(char::from(self.regs[A0] as u8).into()),
+            _ => return RError::new_err(SyscallError, line),
+        }
+        Ok(None)
+    }
+
+    /// execute one instruction, it returns the running count when the program is over
+    pub fn step(&mut self) -> Result<Option<usize>, RError> {
+        // running past the last instruction ends the program like MARS
+        let Some(inst) = self.insts.get(self.pc) else {
+            return Ok(Some(self.count))
+        };
+        let line = self.lines[self.pc];
+
+        self.count += 1;
+        if self.limit.is_some_and(|limit| self.count > limit) {
+            return RError::new_err(StepLimitError, line)
+        }
+
+        let mut next = self.pc + 1;
+        match *inst {
+            Inst::Arith(opt, rd, rs, rt) => {
+                let (l, r) = (self.regs[rs], self.value(rt));
+                self.regs[rd] = match opt {
+                    Operator::Plus => l.wrapping_add(r),
+                    Operator::Sub => l.wrapping_sub(r),
+                    _ => l.wrapping_mul(r),
+                };
+            }
+            Inst::Li(rd, imm) => self.regs[rd] = imm,
+            Inst::Move(rd, rs) => self.regs[rd] = self.regs[rs],
+            Inst::Div(rs, rt) => {
+                // the result of dividing by zero is unpredictable in MIPS, here it's zero
+                let (l, r) = (self.regs[rs], self.regs[rt]);
+                self.lo = l.checked_div(r).unwrap_or(0);
+                self.hi = l.checked_rem(r).unwrap_or(0);
+            }
+            Inst::Mflo(rd) => self.regs[rd] = self.lo,
+            Inst::Mfhi(rd) => self.regs[rd] = self.hi,
+            Inst::Lw(rt, offset, base) => {
+                let addr = self.regs[base].wrapping_add(offset) as u32;
+                let bytes = self.memory(addr, true)?;
+                self.regs[rt] = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            }
+            Inst::Sw(rt, offset, base) => {
+                let (addr, value) = (self.regs[base].wrapping_add(offset) as u32, self.regs[rt]);
+                self.memory(addr, true)?.copy_from_slice(&value.to_le_bytes());
+            }
+            Inst::J(target) => next = target,
+            Inst::Jal(target) => {
+                self.regs[RA] = (TEXT_BASE + 4 * next as u32) as i32;
+                next = target;
+            }
+            Inst::Jr(rs) => {
+                let addr = self.regs[rs] as u32;
+                let end = TEXT_BASE + 4 * self.insts.len() as u32;
+                if !addr.is_multiple_of(4) || !(TEXT_BASE..=end).contains(&addr) {
+                    return RError::new_err(MemoryError, line)
+                }
+                next = ((addr - TEXT_BASE) / 4) as usize;
+            }
+            Inst::Branch(opt, rs, rt, target) => {
+                if opt.calculate(self.regs[rs], self.value(rt)) == 1 {
+                    next = target;
+                }
+            }
+            Inst::Syscall => if let Some(count) = self.syscall(line)? {
+                return Ok(Some(count))
+            },
+            Inst::Nop => (),
+        }
+        self.regs[0] = 0;
+        self.pc = next;
+        Ok(None)
+    }
+
+    /// execute until the program is over and return the running count
+    pub fn run(&mut self) -> Result<usize, RError> {
+        loop {
+            if let Some(count) = self.step()? {
+                break Ok(count)
+            }
+        }
+    }
+}
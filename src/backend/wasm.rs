@@ -0,0 +1,452 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{
+    ast::{Operator, Sentence, Variable},
+    cfg::{Cfg, build, foreign_jump},
+    error::GenerateError,
+    opt::address_taken,
+};
+
+/// the arguments are passed by a stack in `[0, ARG_SIZE)` of the memory,
+/// the frames of functions are allocated after it
+const ARG_SIZE: i32 = 65536;
+const PAGES: i32 = 32;
+
+/// translate the program into WebAssembly text format
+///
+/// the arrays and the variables whose address is taken live in the linear memory,
+/// the other variables are `i32` locals, and `read` and `write` are imported from `env`.
+/// `GOTO` is turned into `block`, `loop` and `br` by the dominator tree of every function,
+/// and the irreducible function falls back to a loop dispatching on the block number
+pub fn generate(codes: &[Sentence]) -> Result<String, GenerateError> {
+    if let Some(i) = foreign_jump(codes) {
+        return GenerateError::new_err(format!("`{}` jumps out of the function, `br` can't reach it", codes[i]), i)
+    }
+
+    let mut wat = String::from("(module\n");
+    let _ = writeln!(wat, "  (import \"env\" \"read\" (func $read (result i32)))");
+    let _ = writeln!(wat, "  (import \"env\" \"write\" (func $write (param i32)))");
+    let _ = writeln!(wat, "  (memory (export \"memory\") {})", PAGES);
+    let _ = writeln!(wat, "  (global $sp (mut i32) (i32.const {}))", ARG_SIZE);
+    let _ = writeln!(wat, "  (global $ap (mut i32) (i32.const 0))");
+
+    for cfg in build(codes) {
+        wat.push('\n');
+        wat.push_str(&Function::new(codes, &cfg).generate());
+    }
+
+    let _ = writeln!(wat, "\n  (export \"main\" (func $f_main))");
+    wat.push_str(")\n");
+    Ok(wat)
+}
+
+/// how the control leaves a block
+enum Exit {
+    Return,
+    Jump(usize),
+    // the target if the condition is true, and the next block otherwise
+    Cond(usize, Option<usize>),
+    // `None` means running past the end of function
+    Fall(Option<usize>),
+}
+
+/// the enclosing `block`, `loop` and `if`, `br` uses the depth of them
+#[derive(PartialEq)]
+enum Context {
+    If,
+    LoopHeadedBy(usize),
+    BlockFollowedBy(usize),
+}
+
+struct Function<'a, 'c> {
+    codes: &'c [Sentence<'a>],
+    cfg: &'c Cfg<'a>,
+    // the offset in the frame of the variables in memory
+    offsets: BTreeMap<&'a str, i32>,
+    frame_size: i32,
+    locals: Vec<&'a str>,
+    exits: Vec<Exit>,
+    wat: String,
+    indent: usize,
+}
+
+impl<'a, 'c> Function<'a, 'c> {
+    fn new(codes: &'c [Sentence<'a>], cfg: &'c Cfg<'a>) -> Self {
+        let body = &codes[cfg.start..cfg.end];
+        let taken = address_taken(body);
+        let (mut offsets, mut frame_size, mut locals) = (BTreeMap::new(), 0, Vec::new());
+        for code in body {
+            let mut vars = code.operands();
+            vars.extend(code.target());
+            for id in vars.into_iter().filter_map(|var| var.get_id()) {
+                if !taken.contains(&id) {
+                    if !locals.contains(&id) {
+                        locals.push(id);
+                    }
+                } else if !offsets.contains_key(id) {
+                    offsets.insert(id, frame_size);
+                    frame_size += match code {
+                        Sentence::Dec { size, .. } => *size,
+                        _ => 4,
+                    };
+                }
+            }
+        }
+
+        let labels: BTreeMap<&str, usize> = cfg.blocks.iter().enumerate()
+            .filter_map(|(k, block)| match codes[block.start] {
+                Sentence::Label(label) => Some((label, k)),
+                _ => None
+            })
+            .collect();
+        let exits = cfg.blocks.iter().enumerate().map(|(k, block)| {
+            let next = (k + 1 < cfg.blocks.len()).then_some(k + 1);
+            // the labels of other functions are rejected by `generate`
+            match &codes[block.end - 1] {
+                Sentence::Return(_) => Exit::Return,
+                Sentence::Goto(label) => Exit::Jump(labels[label]),
+                // the condition is useless if both ways go to the same block
+                Sentence::IfGoto { label, .. } if next == Some(labels[label]) => Exit::Jump(labels[label]),
+                Sentence::IfGoto { label, .. } => Exit::Cond(labels[label], next),
+                _ => Exit::Fall(next),
+            }
+        }).collect();
+
+        Function {
+            codes, cfg, offsets, frame_size, locals, exits, wat: String::new(), indent: 2
+        }
+    }
+
+    fn emit(&mut self, inst: &str) {
+        if inst == "end" || inst == "else" {
+            self.indent -= 1;
+        }
+        let _ = writeln!(self.wat, "{}{}", "  ".repeat(self.indent), inst);
+        if inst == "block" || inst == "loop" || inst == "if" || inst == "else" {
+            self.indent += 1;
+        }
+    }
+
+    /// push the value of variable
+    fn value(&mut self, var: &Variable) {
+        match var {
+            Variable::Number(n) => self.emit(&format!("i32.const {}", n)),
+            Variable::Id(id) => match self.offsets.get(id) {
+                Some(offset) => {
+                    let offset = *offset;
+                    self.emit("local.get $fp");
+                    self.emit(&format!("i32.load offset={}", offset));
+                }
+                None => self.emit(&format!("local.get $v_{}", id)),
+            },
+            Variable::Pointer(id) => {
+                let offset = self.offsets[id];
+                self.emit("local.get $fp");
+                self.emit(&format!("i32.const {}", offset));
+                self.emit("i32.add");
+            }
+            Variable::Deref(id) => {
+                self.value(&Variable::Id(id));
+                self.emit("i32.load");
+            }
+        }
+    }
+
+    /// save the value pushed by `push` to target
+    fn assign(&mut self, target: &Variable, push: impl FnOnce(&mut Self)) {
+        match target {
+            Variable::Id(id) => match self.offsets.get(id) {
+                Some(offset) => {
+                    let offset = *offset;
+                    self.emit("local.get $fp");
+                    push(self);
+                    self.emit(&format!("i32.store offset={}", offset));
+                }
+                None => {
+                    push(self);
+                    self.emit(&format!("local.set $v_{}", id));
+                }
+            },
+            Variable::Deref(id) => {
+                self.value(&Variable::Id(id));
+                push(self);
+                self.emit("i32.store");
+            }
+            _ => unreachable!()
+        }
+    }
+
+    /// restore the stack of frames and return the value on the stack
+    fn leave(&mut self) {
+        self.emit("local.get $fp");
+        self.emit("global.set $sp");
+        self.emit("return");
+    }
+
+    fn sentence(&mut self, code: &Sentence) {
+        match code {
+            Sentence::Assign { target, var } => self.assign(target, |f| f.value(var)),
+            Sentence::Arith { l, r, opt, target } => self.assign(target, |f| {
+                f.value(l);
+                f.value(r);
+                f.emit(match opt {
+                    Operator::Plus => "i32.add",
+                    Operator::Sub => "i32.sub",
+                    Operator::Mul => "i32.mul",
+                    _ => "i32.div_s",
+                });
+            }),
+            Sentence::Return(var) => {
+                self.value(var);
+                self.leave();
+            }
+            Sentence::Arg(var) => {
+                self.emit("global.get $ap");
+                self.value(var);
+                self.emit("i32.store");
+                self.emit("global.get $ap");
+                self.emit("i32.const 4");
+                self.emit("i32.add");
+                self.emit("global.set $ap");
+            }
+            Sentence::Param(var) => {
+                self.emit("global.get $ap");
+                self.emit("i32.const 4");
+                self.emit("i32.sub");
+                self.emit("global.set $ap");
+                self.assign(var, |f| {
+                    f.emit("global.get $ap");
+                    f.emit("i32.load");
+                });
+            }
+            // the callee may change the pointer of target, so the result is saved first
+            Sentence::Call { target, func } => {
+                self.emit(&format!("call $f_{}", func));
+                self.emit("local.set $tmp");
+                self.assign(target, |f| f.emit("local.get $tmp"));
+            }
+            Sentence::Read(var) => self.assign(var, |f| f.emit("call $read")),
+            Sentence::Write(var) => {
+                self.value(var);
+                self.emit("call $write");
+            }
+            // the labels and jumps are handled by the blocks
+            _ => ()
+        }
+    }
+
+    /// the sentences of block except the jump at the end
+    fn block_body(&mut self, k: usize) {
+        let (codes, block) = (self.codes, &self.cfg.blocks[k]);
+        for code in &codes[block.start..block.end] {
+            let text = code.to_string();
+            if !matches!(code, Sentence::Func(_)) {
+                self.emit(&format!(";; {}", text));
+            }
+            self.sentence(code);
+        }
+    }
+
+    /// push the condition of the `IF` at the end of block
+    fn condition(&mut self, k: usize) {
+        let codes = self.codes;
+        let Sentence::IfGoto { l, r, opt, .. } = &codes[self.cfg.blocks[k].end - 1] else { unreachable!() };
+        self.value(l);
+        self.value(r);
+        self.emit(match opt {
+            Operator::Equal => "i32.eq",
+            Operator::NotEqual => "i32.ne",
+            Operator::Greater => "i32.gt_s",
+            Operator::Less => "i32.lt_s",
+            Operator::GreaterEqual => "i32.ge_s",
+            _ => "i32.le_s",
+        });
+    }
+
+    fn generate(mut self) -> String {
+        let mut header = format!("  (func $f_{} (result i32)\n", self.cfg.name);
+        let _ = writeln!(header, "    (local $fp i32) (local $tmp i32) (local $label i32)");
+        for id in &self.locals {
+            let _ = writeln!(header, "    (local $v_{} i32)", id);
+        }
+
+        // allocate the frame
+        self.emit("global.get $sp");
+        self.emit("local.tee $fp");
+        self.emit(&format!("i32.const {}", self.frame_size));
+        self.emit("i32.add");
+        self.emit("global.set $sp");
+
+        match Stackifier::new(self.cfg) {
+            Some(stackifier) => stackifier.do_tree(&mut self, 0, &mut Vec::new()),
+            None => self.dispatch(),
+        }
+        // every path is ended by `return` or `br`
+        self.emit("unreachable");
+
+        header.push_str(&self.wat);
+        header.push_str("  )\n");
+        header
+    }
+
+    /// go to block `target` in the irreducible function, see `dispatch`
+    fn dispatch_to(&mut self, target: usize, depth: usize) {
+        self.emit(&format!("i32.const {}", target));
+        self.emit("local.set $label");
+        self.emit(&format!("br {}", depth));
+    }
+
+    /// the blocks are placed one by one in a loop, and `$label` is the next block to run:
+    /// ```text
+    /// loop
+    ///   block ... block
+    ///     br_table $label
+    ///   end
+    ///   block 0
+    ///   end
+    ///   block 1
+    ///   ...
+    /// end
+    /// ```
+    fn dispatch(&mut self) {
+        let n = self.cfg.blocks.len();
+        self.emit("loop");
+        for _ in 0..n {
+            self.emit("block");
+        }
+        self.emit("local.get $label");
+        let targets: Vec<String> = (0..n).map(|k| k.to_string()).collect();
+        self.emit(&format!("br_table {} {}", targets.join(" "), n - 1));
+
+        for k in 0..n {
+            self.emit("end");
+            self.block_body(k);
+            // the depth of loop from the code of block k
+            let depth = n - 1 - k;
+            match self.exits[k] {
+                Exit::Return => (),
+                Exit::Jump(target) => self.dispatch_to(target, depth),
+                Exit::Cond(target, next) => {
+                    self.condition(k);
+                    self.emit("if");
+                    self.dispatch_to(target, depth + 1);
+                    self.emit("end");
+                    match next {
+                        // the next block is just after this one
+                        Some(_) => (),
+                        None => self.fall_off(),
+                    }
+                }
+                Exit::Fall(Some(_)) => (),
+                Exit::Fall(None) => self.fall_off(),
+            }
+        }
+        self.emit("end");
+    }
+
+    /// running past the end of function returns 0
+    fn fall_off(&mut self) {
+        self.emit("i32.const 0");
+        self.leave();
+    }
+}
+
+/// place the blocks by the dominator tree, see "Beyond Relooper" by Norman Ramsey
+struct Stackifier {
+    // the index of every block in reverse postorder
+    rank: Vec<usize>,
+    // the children of every block in the dominator tree
+    children: Vec<Vec<usize>>,
+    loop_headers: Vec<bool>,
+    merge_nodes: Vec<bool>,
+}
+
+impl Stackifier {
+    /// it's `None` if the graph is irreducible
+    fn new(cfg: &Cfg) -> Option<Self> {
+        let n = cfg.blocks.len();
+        let idom = cfg.dominators();
+        let mut rank = vec![usize::MAX; n];
+        cfg.reverse_postorder().iter().enumerate().for_each(|(r, k)| rank[*k] = r);
+
+        let (mut loop_headers, mut merge_nodes) = (vec![false; n], vec![false; n]);
+        for (k, block) in cfg.blocks.iter().enumerate() {
+            let preds = block.preds.iter().filter(|p| rank[**p] != usize::MAX);
+            let (backward, forward): (Vec<usize>, Vec<usize>) = preds.partition(|p| rank[**p] >= rank[k]);
+            // a back edge must go to a block dominating its source
+            if backward.iter().any(|p| !cfg.dominates(&idom, k, *p)) {
+                return None
+            }
+            loop_headers[k] = !backward.is_empty();
+            merge_nodes[k] = forward.len() >= 2;
+        }
+
+        let mut children = vec![Vec::new(); n];
+        for (k, d) in idom.iter().enumerate() {
+            if let Some(d) = d {
+                children[*d].push(k);
+            }
+        }
+        Some(Stackifier { rank, children, loop_headers, merge_nodes })
+    }
+
+    fn do_tree(&self, f: &mut Function, x: usize, context: &mut Vec<Context>) {
+        // the merge node placed later is the outer one
+        let mut merges: Vec<usize> = self.children[x].iter().copied().filter(|y| self.merge_nodes[*y]).collect();
+        merges.sort_by_key(|y| std::cmp::Reverse(self.rank[*y]));
+
+        if self.loop_headers[x] {
+            f.emit("loop");
+            context.push(Context::LoopHeadedBy(x));
+            self.node_within(f, x, &merges, context);
+            context.pop();
+            f.emit("end");
+        } else {
+            self.node_within(f, x, &merges, context);
+        }
+    }
+
+    fn node_within(&self, f: &mut Function, x: usize, merges: &[usize], context: &mut Vec<Context>) {
+        if let Some((y, rest)) = merges.split_first() {
+            f.emit("block");
+            context.push(Context::BlockFollowedBy(*y));
+            self.node_within(f, x, rest, context);
+            context.pop();
+            f.emit("end");
+            return self.do_tree(f, *y, context)
+        }
+
+        f.block_body(x);
+        match f.exits[x] {
+            Exit::Return => (),
+            Exit::Jump(target) | Exit::Fall(Some(target)) => self.do_branch(f, x, target, context),
+            Exit::Cond(target, next) => {
+                f.condition(x);
+                f.emit("if");
+                context.push(Context::If);
+                self.do_branch(f, x, target, context);
+                f.emit("else");
+                match next {
+                    Some(next) => self.do_branch(f, x, next, context),
+                    None => f.fall_off(),
+                }
+                context.pop();
+                f.emit("end");
+            }
+            Exit::Fall(None) => f.fall_off(),
+        }
+    }
+
+    fn do_branch(&self, f: &mut Function, source: usize, target: usize, context: &mut Vec<Context>) {
+        let wanted = if self.rank[target] <= self.rank[source] {
+            Context::LoopHeadedBy(target)
+        } else if self.merge_nodes[target] {
+            Context::BlockFollowedBy(target)
+        } else {
+            // the target is only reached from here, so it's placed inline
+            return self.do_tree(f, target, context)
+        };
+        let k = context.iter().rposition(|c| *c == wanted).unwrap();
+        f.emit(&format!("br {}", context.len() - 1 - k));
+    }
+}
@@ -0,0 +1,66 @@
+//! The exit codes of `irsim-cli`
+//!
+//! | code | meaning                                                |
+//! |------|--------------------------------------------------------|
+//! | 0    | success                                                |
+//! | 1    | `test`, `fmt --check`, `opt` or `diff` failed          |
+//! | 2    | invalid command line arguments (from clap)             |
+//! | 3    | parse error                                            |
+//! | 4    | semantic error found when checking or compiling        |
+//! | 5    | runtime error                                          |
+//! | 6    | step limit exceeded                                    |
+//! | 7    | I/O error, e.g. the file can't be read                 |
+//!
+//! With `--exit-with-return`, a successful run exits with
+//! the value returned by `main` instead of 0. The codes are not
+//! reserved for it: the os keeps only the low 8 bits of the value,
+//! so returning 5 or 261 looks the same as a runtime error, and a
+//! caller should read the output or stderr to tell them apart.
+use core::error::{
+    CompileError, CompileErrorKind,
+    InterpreterError as IError, InterpreterErrorKind,
+    RuntimeError as RError, RuntimeErrorKind,
+};
+
+pub const SUCCESS: i32 = 0;
+pub const CHECK_FAILED: i32 = 1;
+pub const USAGE_ERROR: i32 = 2;
+pub const PARSE_ERROR: i32 = 3;
+pub const SEMANTIC_ERROR: i32 = 4;
+pub const RUNTIME_ERROR: i32 = 5;
+pub const LIMIT_EXCEEDED: i32 = 6;
+pub const IO_ERROR: i32 = 7;
+
+pub const HELP: &str = "\
+Exit codes:
+  0  success (or the value returned by main with --exit-with-return)
+  1  `test`, `fmt --check`, `opt` or `diff` failed
+  2  invalid command line arguments
+  3  parse error
+  4  semantic error
+  5  runtime error
+  6  step limit exceeded
+  7  I/O error
+With --exit-with-return, the value returned by main is truncated to
+0-255 by the os and may collide with the codes above.";
+
+pub fn of_static(err: &IError) -> i32 {
+    match err.kind() {
+        InterpreterErrorKind::ParseError(_) => PARSE_ERROR,
+        _ => SEMANTIC_ERROR,
+    }
+}
+
+pub fn of_compile(err: &CompileError) -> i32 {
+    match err.kind() {
+        CompileErrorKind::LexicalError | CompileErrorKind::SyntaxError => PARSE_ERROR,
+        _ => SEMANTIC_ERROR,
+    }
+}
+
+pub fn of_runtime(err: &RError) -> i32 {
+    match err.kind() {
+        RuntimeErrorKind::StepLimitError => LIMIT_EXCEEDED,
+        _ => RUNTIME_ERROR,
+    }
+}
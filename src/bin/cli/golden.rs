@@ -0,0 +1,163 @@
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{Mutex, atomic::{AtomicUsize, Ordering}},
+    thread,
+};
+use core::{
+    interpreter::Interpreter,
+    debugger::Debugger,
+    utils::io::{read_from_values, read_values, write_to_buffer},
+};
+
+/// a golden case is made up of `foo.ir`, `foo.out` and an optional `foo.in`
+pub struct Case {
+    name: String,
+    ir: PathBuf,
+    input: Option<PathBuf>,
+    output: PathBuf,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Pass(usize),
+    Fail(String),
+}
+
+pub struct Options {
+    pub jobs: usize,
+    pub budget: Option<usize>,
+    pub max_steps: usize,
+}
+
+pub fn collect_cases(dir: &Path) -> Result<Vec<Case>, String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("can't read directory {}: {}", dir.display(), e))?;
+
+    let mut cases: Vec<Case> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ir"))
+        .map(|ir| {
+            let input = ir.with_extension("in");
+            Case {
+                name: ir.file_stem().unwrap().to_string_lossy().into(),
+                input: input.exists().then_some(input),
+                output: ir.with_extension("out"),
+                ir,
+            }
+        })
+        .collect();
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(cases)
+}
+
+/// run all the cases and print the summary, return whether all the cases passed
+pub fn run(cases: &[Case], options: &Options) -> bool {
+    let mut failed = 0;
+    for (case, outcome) in cases.iter().zip(run_cases(cases, options)) {
+        match outcome {
+            Outcome::Pass(count) => println!("PASS {} ({} instructions)", case.name, count),
+            Outcome::Fail(reason) => {
+                failed += 1;
+                println!("FAIL {}\n{}", case.name, reason);
+            }
+        }
+    }
+    println!("\n{} passed, {} failed, {} total", cases.len() - failed, failed, cases.len());
+
+    failed == 0
+}
+
+/// run the cases in `jobs` threads, the outcomes are in the order of the cases
+pub fn run_cases(cases: &[Case], options: &Options) -> Vec<Outcome> {
+    let next = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<Option<Outcome>>> =
+        Mutex::new(cases.iter().map(|_| None).collect());
+
+    thread::scope(|s| {
+        for _ in 0..options.jobs.max(1) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                let Some(case) = cases.get(i) else { break };
+                // a panicking case fails alone instead of the whole run
+                let outcome = panic::catch_unwind(AssertUnwindSafe(|| run_case(case, options)))
+                    .unwrap_or_else(|payload| {
+                        let msg = payload.downcast_ref::<String>().cloned()
+                            .or_else(|| payload.downcast_ref::<&str>().map(|msg| msg.to_string()))
+                            .unwrap_or_default();
+                        Outcome::Fail(format!("  panicked: {}", msg))
+                    });
+                outcomes.lock().unwrap()[i] = Some(outcome);
+            });
+        }
+    });
+
+    outcomes.into_inner().unwrap().into_iter().map(Option::unwrap).collect()
+}
+
+fn run_case(case: &Case, options: &Options) -> Outcome {
+    let read = |path: &Path| fs::read_to_string(path)
+        .map_err(|e| format!("  can't read {}: {}", path.display(), e));
+
+    let (source, expected) = match (read(&case.ir), read(&case.output)) {
+        (Ok(source), Ok(expected)) => (source, expected),
+        (Err(e), _) | (_, Err(e)) => return Outcome::Fail(e),
+    };
+    let input = match &case.input {
+        Some(path) => match read(path) {
+            Ok(text) => read_values(&text),
+            Err(e) => return Outcome::Fail(e),
+        },
+        None => Vec::new(),
+    };
+
+    let lines: Vec<&str> = source.lines().map(|line| line.trim()).collect();
+    let (output, write_func) = write_to_buffer();
+    let interpreter = match Interpreter::from_lines(&lines, read_from_values(input), write_func) {
+        Ok(i) => i,
+        Err(err) => return Outcome::Fail(format!("  {}", err)),
+    };
+    interpreter.set_limit(Some(options.max_steps));
+
+    let count = match Debugger::new(interpreter).run() {
+        Ok(count) => count,
+        Err(msg) => return Outcome::Fail(format!("  {}", msg)),
+    };
+
+    let actual = output.borrow();
+    if let Some(diff) = diff(&expected, &actual) {
+        return Outcome::Fail(diff);
+    }
+    match options.budget {
+        Some(budget) if count > budget => Outcome::Fail(
+            format!("  instruction count {} exceeds the budget {}", count, budget)
+        ),
+        _ => Outcome::Pass(count),
+    }
+}
+
+/// compare the output line by line, trailing whitespace is ignored
+pub fn diff(expected: &str, actual: &str) -> Option<String> {
+    let normalize = |text: &str| -> Vec<String> {
+        let mut lines: Vec<String> = text.lines().map(|l| l.trim_end().into()).collect();
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+        lines
+    };
+    let (expected, actual) = (normalize(expected), normalize(actual));
+
+    let mut result = String::new();
+    for i in 0..expected.len().max(actual.len()) {
+        let (e, a) = (expected.get(i), actual.get(i));
+        if e != a {
+            result.push_str(&format!("  line {}:\n", i + 1));
+            result.push_str(&format!("    - {}\n", e.map_or("<none>", |s| s)));
+            result.push_str(&format!("    + {}\n", a.map_or("<none>", |s| s)));
+        }
+    }
+
+    (!result.is_empty()).then_some(result)
+}
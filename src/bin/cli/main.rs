@@ -1,31 +1,304 @@
 
-use std::{io::stdin, process::exit};
-use clap::Parser;
+mod code;
+mod golden;
+mod repl;
+mod report;
+
+#[cfg(test)]
+#[path = "../../test/cli.rs"]
+mod test;
+
+use std::{fs, path::Path, process::{self, exit}, thread};
+use clap::{Parser, Subcommand, ValueEnum};
 use core::{
-    interpreter::Interpreter,
+    ast::Sentence,
+    backend::{c, llvm, mips, wasm, sim::Simulator},
+    cfg,
+    diff,
+    format::format_source,
+    interpreter::{Interpreter, parse_lines},
+    lint::{self, LINTS},
     debugger::{Debugger, Message}, 
+    error::RuntimeError as RError,
+    frontend,
+    opt::{self, Pass, peephole, to_source},
     utils::io::{
-        read_line, read_lines_from_file
+        read_line, read_lines_from_file, read_from_stdin,
+        read_from_values, read_values, read_args, write_to_buffer,
+        ReadFunc, WriteFunc
     },
 };
 
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_help = code::HELP)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
     /// The file you want to run
-    file: String,
+    #[arg(required = true)]
+    file: Option<String>,
 
     /// Open debug mode
     #[arg(short, long)]
     debug: bool,
+
+    /// Read the input of READ from this file instead of stdin
+    #[arg(short, long, conflicts_with = "args")]
+    input: Option<String>,
+
+    /// The input of READ separated by comma, e.g. `3,5,7`
+    #[arg(short, long, allow_hyphen_values = true)]
+    args: Option<String>,
+
+    /// The format of the result
+    #[arg(short, long, value_enum, default_value_t = Format::Text, conflicts_with = "debug")]
+    format: Format,
+
+    /// Report the running count of every sentence, only works with `--format json`
+    #[arg(short, long)]
+    profile: bool,
+
+    /// Stop the program when it runs more instructions than this
+    #[arg(long)]
+    max_steps: Option<usize>,
+
+    /// Use the value returned by main as the exit code, truncated to 0-255
+    /// by the os, so it may collide with the error codes
+    #[arg(short, long)]
+    exit_with_return: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run every `foo.ir` in a directory and compare its output with `foo.out`
+    Test {
+        /// The directory of the cases, `foo.in` is used as input if it exists
+        dir: String,
+
+        /// Fail the case when its instruction count exceeds the budget
+        #[arg(short, long)]
+        budget: Option<usize>,
+
+        /// Stop the case when it runs more instructions than this
+        #[arg(long, default_value_t = 10_000_000)]
+        max_steps: usize,
+
+        /// The number of cases to run in parallel
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+    /// Execute IR sentences interactively
+    Repl,
+    /// Show the control flow graph of every function
+    Cfg {
+        /// The IR file
+        file: String,
+
+        /// Write the graph in Graphviz DOT
+        #[arg(long)]
+        dot: bool,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Check the suspicious patterns in IR
+    Lint {
+        /// The IR file
+        file: String,
+
+        /// Suppress the lint by its code or name, e.g. `W002` or `unused-label`
+        #[arg(short = 'A', long, value_delimiter = ',')]
+        allow: Vec<String>,
+
+        /// List all the lints
+        #[arg(long)]
+        list: bool,
+    },
+    /// Format IR in canonical syntax
+    Fmt {
+        /// The IR file
+        file: String,
+
+        /// Indent the sentences under `FUNCTION` and `LABEL` by this width
+        #[arg(long)]
+        indent: Option<usize>,
+
+        /// Fail if the file isn't formatted instead of printing it
+        #[arg(long, conflicts_with = "write")]
+        check: bool,
+
+        /// Write the result back to the file
+        #[arg(short, long)]
+        write: bool,
+    },
+    /// Compile IR into another language
+    Compile {
+        /// The IR file
+        file: String,
+
+        /// The target language
+        #[arg(short, long, value_enum)]
+        target: Target,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Run MIPS assembly with the built-in simulator
+    Sim {
+        /// The assembly file
+        file: String,
+
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Run IR and its MIPS translation with the same input and compare the output
+    Crosscheck {
+        /// The IR file
+        file: String,
+
+        /// Also run the LLVM translation optimized by `opt -O2` with `lli`,
+        /// and count the LLVM instructions it runs
+        #[arg(long)]
+        llvm: bool,
+
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Compile C-- into IR
+    Cc {
+        /// The C-- file
+        file: String,
+
+        /// Write the IR to this file instead of stdout
+        #[arg(short, long, conflicts_with = "run")]
+        output: Option<String>,
+
+        /// Run the IR instead of printing it
+        #[arg(short, long)]
+        run: bool,
+
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Optimize IR by a pipeline of passes
+    Opt {
+        /// The IR file
+        file: String,
+
+        /// The optimization level, e.g. `-O2`
+        #[arg(short = 'O', default_value_t = 1, value_parser = clap::value_parser!(u8).range(0..=2))]
+        level: u8,
+
+        /// Run these passes in order instead of the level, e.g. `fold,copy,dce`
+        #[arg(short, long, value_delimiter = ',')]
+        passes: Vec<String>,
+
+        /// Add the peephole rules in this file
+        #[arg(long)]
+        rules: Option<String>,
+
+        /// Write the result to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Print every change made by the passes
+        #[arg(short, long)]
+        verbose: bool,
+
+        /// Run both programs with the input, fail if they behave differently
+        #[arg(long)]
+        verify: bool,
+
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Run two IR programs on the same inputs and compare their behaviors
+    Diff {
+        /// The original IR file
+        a: String,
+
+        /// The IR file to compare with
+        b: String,
+
+        /// Use every `foo.in` in this directory as an input
+        #[arg(long, conflicts_with = "random", required_unless_present = "random")]
+        inputs: Option<String>,
+
+        /// Use this many random inputs
+        #[arg(long)]
+        random: Option<usize>,
+
+        /// The seed of the random inputs
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+
+        /// The number of values in a random input
+        #[arg(long, default_value_t = 16)]
+        length: usize,
+
+        /// Stop the program when it runs more instructions than this
+        #[arg(long, default_value_t = 10_000_000)]
+        max_steps: usize,
+
+        /// Print the result of every input
+        #[arg(short, long)]
+        verbose: bool,
+    },
+}
+
+/// The input of READ shared by the commands which run a program
+#[derive(clap::Args, Debug)]
+struct InputArgs {
+    /// Read the input of READ from this file instead of stdin
+    #[arg(short, long, conflicts_with = "args")]
+    input: Option<String>,
+
+    /// The input of READ separated by comma, e.g. `3,5,7`
+    #[arg(short, long, allow_hyphen_values = true)]
+    args: Option<String>,
+
+    /// Stop the program when it runs more instructions than this
+    #[arg(long)]
+    max_steps: Option<usize>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Target {
+    /// MIPS32 assembly for SPIM or MARS
+    Mips,
+    /// Standalone C source
+    C,
+    /// WebAssembly text format importing `read` and `write` from `env`
+    Wat,
+    /// LLVM IR text with opaque pointers
+    Llvm,
 }
 
 #[inline]
 fn print_run_result(result: Result<usize, Message>) {
     match result {
         Ok(count) => print_over(count),
-        Err(message) => eprint!("{}", message)
+        Err(message) => eprintln!("{}", message)
+    }
+}
+
+#[inline]
+fn exit_code(result: &Result<usize, RError>, interpreter: &Interpreter, exit_with_return: bool) -> i32 {
+    match result {
+        Ok(_) if exit_with_return => interpreter.return_value().unwrap_or(code::SUCCESS),
+        Ok(_) => code::SUCCESS,
+        Err(err) => code::of_runtime(err),
     }
 }
 
@@ -35,38 +308,600 @@ fn print_over(count: usize) {
     eprintln!("Total instructions = {}", count);
 }
 
-fn main() {
-    let read_func = Box::new(||{
-        let mut input = String::new();
-        let _ = stdin().read_line(&mut input).unwrap();
-        input
-    });
+fn read_source(file: &str) -> Vec<String> {
+    match read_lines_from_file(file) {
+        Ok(lines) => lines,
+        Err(e) => { eprintln!("can't read {}: {}", file, e); exit(code::IO_ERROR) }
+    }
+}
+
+/// the sentences with their line numbers
+fn parse_source(lines: &[String]) -> (Vec<Sentence<'_>>, Vec<usize>) {
+    let lines: Vec<&str> = lines.iter().map(|s| s as &str).collect();
+    match parse_lines(&lines) {
+        Ok(result) => result,
+        Err(err) => { eprintln!("{}", err); exit(code::of_static(&err)) }
+    }
+}
+
+/// only the program passing the checking can be compiled or optimized
+fn check_source(lines: &[String]) {
+    let (_, write_func) = write_to_buffer();
+    let refs: Vec<&str> = lines.iter().map(|s| s as &str).collect();
+    if let Err(err) = Interpreter::from_lines(&refs, read_from_values(vec![]), write_func) {
+        eprintln!("{}", err);
+        exit(code::of_static(&err));
+    }
+}
+
+fn write_output(output: Option<String>, text: &str) {
+    match output {
+        Some(path) => if let Err(e) = fs::write(&path, text) {
+            eprintln!("can't write {}: {}", path, e);
+            exit(code::IO_ERROR)
+        },
+        None => print!("{}", text),
+    }
+}
+
+fn print_cfg(file: &str, dot: bool, output: Option<String>) {
+    let lines = read_source(file);
+    let (codes, _) = parse_source(&lines);
+    let cfgs = cfg::build(&codes);
+
+    if dot {
+        return write_output(output, &cfg::to_dot(&codes, &cfgs));
+    }
+
+    let mut text = String::new();
+    for cfg in &cfgs {
+        text.push_str(&format!("FUNCTION {}\n", cfg.name));
+        for (k, block) in cfg.blocks.iter().enumerate() {
+            text.push_str(&format!("  block {} [{}, {}) -> {:?}", k, block.start, block.end, block.succs));
+            if !block.calls.is_empty() {
+                text.push_str(&format!(" calls {:?}", block.calls));
+            }
+            text.push('\n');
+        }
+    }
+    write_output(output, &text);
+}
+
+fn format_file(file: &str, indent: Option<usize>, check: bool, write: bool) {
+    let source = match fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(e) => { eprintln!("can't read {}: {}", file, e); exit(code::IO_ERROR) }
+    };
+    let lines: Vec<&str> = source.lines().collect();
+    let formatted = match format_source(&lines, indent) {
+        Ok(formatted) => formatted,
+        Err(err) => { eprintln!("{}", err); exit(code::of_static(&err)) }
+    };
+
+    if check {
+        if formatted != source {
+            eprintln!("{} is not formatted", file);
+            exit(code::CHECK_FAILED);
+        }
+    } else if write {
+        write_output(Some(file.into()), &formatted);
+    } else {
+        print!("{}", formatted);
+    }
+}
+
+fn print_lint(file: &str, allow: Vec<String>, list: bool) {
+    if list {
+        for (code, name, description) in LINTS {
+            println!("{}  {:<18}{}", code, name, description);
+        }
+        return;
+    }
+
+    let allowed: Vec<&str> = allow.iter().map(|lint| match lint::find_code(lint) {
+        Some(code) => code,
+        None => { eprintln!("unknown lint `{}`, see `--list`", lint); exit(code::USAGE_ERROR) }
+    }).collect();
+
+    let lines = read_source(file);
+    let (codes, source_lines) = parse_source(&lines);
+    let warnings = lint::lint(&codes, &allowed);
+    for warning in &warnings {
+        println!("{}:{}: [{}] {}", file, source_lines[warning.i], warning.code, warning.msg);
+    }
+    eprintln!("{} warning(s)", warnings.len());
+}
+
+/// the input values from `--input` or `--args`, `None` means stdin is used
+fn input_values(input: Option<String>, args: Option<String>) -> Option<Vec<String>> {
+    match read_input(input, args) {
+        Ok(values) => values,
+        Err((message, code)) => { eprintln!("{}", message); exit(code) }
+    }
+}
+
+/// the same as `input_values`, but returns the message and the exit code on error
+fn read_input(input: Option<String>, args: Option<String>) -> Result<Option<Vec<String>>, (String, i32)> {
+    match (input, args) {
+        (Some(path), _) => match fs::read_to_string(&path) {
+            Ok(text) => Ok(Some(read_values(&text))),
+            Err(e) => Err((format!("can't read {}: {}", path, e), code::IO_ERROR)),
+        },
+        (_, Some(args)) => Ok(Some(read_args(&args))),
+        _ => Ok(None),
+    }
+}
 
-    let write_func = Box::new(|text: String|{
-        print!("{}", text)
+fn simulate(file: &str, input: InputArgs) {
+    let source = match fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(e) => { eprintln!("can't read {}: {}", file, e); exit(code::IO_ERROR) }
+    };
+    let read_func = match input_values(input.input, input.args) {
+        Some(values) => read_from_values(values),
+        None => read_from_stdin(),
+    };
+    let write_func: WriteFunc = Box::new(|text: String| print!("{}", text));
+
+    let mut simulator = match Simulator::new(&source, read_func, write_func) {
+        Ok(simulator) => simulator,
+        Err(err) => { eprintln!("{}", err); exit(code::PARSE_ERROR) }
+    };
+    simulator.set_limit(input.max_steps);
+    match simulator.run() {
+        Ok(count) => print_over(count),
+        Err(err) => { eprintln!("[error] {}", err); exit(code::of_runtime(&err)) }
+    }
+}
+
+fn crosscheck(file: &str, llvm: bool, input: InputArgs) {
+    // stdin is read at once, so both programs get the same input
+    let values = input_values(input.input, input.args).unwrap_or_else(|| {
+        let mut text = String::new();
+        let _ = std::io::Read::read_to_string(&mut std::io::stdin(), &mut text);
+        read_values(&text)
     });
 
+    let lines = read_source(file);
+    let refs: Vec<&str> = lines.iter().map(|s| s as &str).collect();
+    let (ir_output, write_func) = write_to_buffer();
+    let interpreter = match Interpreter::from_lines(&refs, read_from_values(values.clone()), write_func) {
+        Ok(interpreter) => interpreter,
+        Err(err) => { eprintln!("{}", err); exit(code::of_static(&err)) }
+    };
+    interpreter.set_limit(input.max_steps);
+    let ir_result = interpreter.run();
+
+    let (codes, _) = parse_source(&lines);
+    let asm = mips::generate(&codes);
+    let (mips_output, write_func) = write_to_buffer();
+    let mut simulator = match Simulator::new(&asm, read_from_values(values.clone()), write_func) {
+        Ok(simulator) => simulator,
+        Err(err) => { eprintln!("generated assembly is invalid: {}", err); exit(code::CHECK_FAILED) }
+    };
+    simulator.set_limit(input.max_steps);
+    let mips_result = simulator.run();
+    let llvm_result = llvm.then(|| run_llvm(&codes, &values));
+
+    let describe = |result: &Result<usize, RError>| match result {
+        Ok(count) => format!("exited after {} instructions", count),
+        Err(err) => format!("failed: {}", err),
+    };
+    println!("IR:   {}", describe(&ir_result));
+    println!("MIPS: {}", describe(&mips_result));
+    match &llvm_result {
+        Some(Ok((_, count))) => println!("LLVM: exited after {} instructions (opt -O2)", count),
+        Some(Err(msg)) => println!("LLVM: failed: {}", msg),
+        None => (),
+    }
+
+    let mut same = ir_result.is_ok() == mips_result.is_ok()
+        && llvm_result.as_ref().is_none_or(|result| result.is_ok() == ir_result.is_ok());
+    if !same {
+        println!("the programs terminate differently");
+    }
+    if let Some(diff) = golden::diff(&ir_output.borrow(), &mips_output.borrow()) {
+        println!("the output diverges (- IR, + MIPS):\n{}", diff.trim_end());
+        same = false;
+    }
+    if let Some(Ok((output, _))) = &llvm_result {
+        if let Some(diff) = golden::diff(&ir_output.borrow(), output) {
+            println!("the output diverges (- IR, + LLVM):\n{}", diff.trim_end());
+            same = false;
+        }
+    }
+    if !same {
+        exit(code::CHECK_FAILED);
+    }
+    println!("the output is the same");
+}
+
+/// optimize the LLVM translation by `opt -O2` and run it by `lli` on the values,
+/// return the output and the number of LLVM instructions run
+fn run_llvm(codes: &[Sentence], values: &[String]) -> Result<(String, usize), String> {
+    let ll = llvm::generate(codes).map_err(|err| err.to_string())?;
+    let version = process::Command::new("lli").arg("--version").output()
+        .map_err(|e| format!("can't run lli: {}", e))?;
+    // LLVM 14 needs a flag for opaque pointers
+    let flags: &[&str] = match String::from_utf8_lossy(&version.stdout).contains("version 14") {
+        true => &["-opaque-pointers"],
+        false => &[],
+    };
+
+    let dir = std::env::temp_dir().join(format!("irsim-llvm-{}", process::id()));
+    let (source, optimized) = (dir.join("source.ll"), dir.join("optimized.ll"));
+    fs::create_dir_all(&dir).and_then(|_| fs::write(&source, ll)).map_err(|e| e.to_string())?;
+    let status = process::Command::new("opt").args(flags).args(["-O2", "-S", "-o"]).arg(&optimized).arg(&source)
+        .status()
+        .map_err(|e| format!("can't run opt: {}", e))?;
+    if !status.success() {
+        return Err("opt failed".into())
+    }
+    let counted = fs::read_to_string(&optimized).map(|ll| llvm::instrument(&ll)).map_err(|e| e.to_string())?;
+    fs::write(&optimized, counted).map_err(|e| e.to_string())?;
+
+    let mut child = process::Command::new("lli").args(flags).arg(&optimized)
+        .stdin(process::Stdio::piped()).stdout(process::Stdio::piped()).stderr(process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("can't run lli: {}", e))?;
+    let _ = std::io::Write::write_all(&mut child.stdin.take().unwrap(), values.join(" ").as_bytes());
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    let _ = fs::remove_dir_all(&dir);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let count = stderr.lines().rev().find_map(|line| line.strip_prefix("instructions: "));
+    match (output.status.success(), count.and_then(|count| count.parse().ok())) {
+        (true, Some(count)) => Ok((String::from_utf8_lossy(&output.stdout).into(), count)),
+        _ => Err(format!("lli exited with {}", output.status)),
+    }
+}
+
+fn compile(file: &str, target: Target, output: Option<String>) {
+    let lines = read_source(file);
+    check_source(&lines);
+    let (codes, source_lines) = parse_source(&lines);
+    let text = match target {
+        Target::Mips => Ok(mips::generate(&codes)),
+        Target::C => c::generate(&codes),
+        Target::Wat => wasm::generate(&codes),
+        Target::Llvm => llvm::generate(&codes),
+    };
+    match text {
+        Ok(text) => write_output(output, &text),
+        Err(err) => {
+            let line = source_lines[err.line()];
+            eprintln!("{}", err.at(line));
+            exit(code::SEMANTIC_ERROR)
+        }
+    }
+}
+
+fn compile_cmm(file: &str, output: Option<String>, run: bool, input: InputArgs) {
+    let source = match fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(e) => { eprintln!("can't read {}: {}", file, e); exit(code::IO_ERROR) }
+    };
+    let codes = match frontend::compile(&source) {
+        Ok(codes) => codes,
+        Err(err) => { eprintln!("{}", err); exit(code::of_compile(&err)) }
+    };
+    if !run {
+        write_output(output, &to_source(&codes));
+        return;
+    }
+
+    let read_func = match input_values(input.input, input.args) {
+        Some(values) => read_from_values(values),
+        None => read_from_stdin(),
+    };
+    let write_func: WriteFunc = Box::new(|text: String| print!("{}", text));
+    let interpreter = match Interpreter::from_codes(codes, read_func, write_func) {
+        Ok(interpreter) => interpreter,
+        Err(err) => { eprintln!("{}", err); exit(code::of_static(&err)) }
+    };
+    interpreter.set_limit(input.max_steps);
+    match interpreter.run() {
+        Ok(count) => print_over(count),
+        Err(err) => { eprintln!("[error] {}", err); exit(code::of_runtime(&err)) }
+    }
+}
+
+/// the options of `opt`
+struct OptOptions {
+    level: u8,
+    passes: Vec<String>,
+    rules: Option<String>,
+    output: Option<String>,
+    verbose: bool,
+    verify: bool,
+}
+
+fn optimize(file: &str, options: OptOptions, input: InputArgs) {
+    let lines = read_source(file);
+    check_source(&lines);
+    let (codes, _) = parse_source(&lines);
+
+    let passes: Vec<Pass> = match options.passes.is_empty() {
+        true => Pass::level(options.level).to_vec(),
+        false => options.passes.iter().map(|name| match Pass::from_name(name) {
+            Some(pass) => pass,
+            None => {
+                let names: Vec<&str> = Pass::ALL.iter().map(|pass| pass.name()).collect();
+                eprintln!("unknown pass `{}`, expect one of {}", name, names.join(", "));
+                exit(code::USAGE_ERROR)
+            }
+        }).collect(),
+    };
+    let text = match &options.rules {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => { eprintln!("can't read {}: {}", path, e); exit(code::IO_ERROR) }
+        },
+        None => String::new(),
+    };
+    let mut rules = peephole::default_rules();
+    match peephole::parse_rules(&text) {
+        Ok(extra) => rules.extend(extra),
+        Err((line, msg)) => {
+            eprintln!("{}:{}: {}", options.rules.unwrap(), line, msg);
+            exit(code::PARSE_ERROR)
+        }
+    }
+
+    let (result, changes) = opt::optimize(&codes, &passes, &rules);
+    if options.verbose {
+        for (pass, change) in &changes {
+            eprintln!("[{}] {}", pass.name(), change);
+        }
+    }
+    eprintln!("sentences: {} -> {}", codes.len(), result.len());
+
+    // a pass must never turn a valid program into an invalid one
+    let (_, write_func) = write_to_buffer();
+    if let Err(err) = Interpreter::from_codes(result.clone(), read_from_values(vec![]), write_func) {
+        eprintln!("the optimized program is invalid: {}", err);
+        exit(code::CHECK_FAILED);
+    }
+
+    if options.verify {
+        // stdin is read at once, so both programs get the same input, and it's read only if needed
+        let values = input_values(input.input, input.args).unwrap_or_else(|| {
+            if !codes.iter().any(|code| matches!(code, Sentence::Read(_))) {
+                return Vec::new()
+            }
+            let mut text = String::new();
+            let _ = std::io::Read::read_to_string(&mut std::io::stdin(), &mut text);
+            read_values(&text)
+        });
+
+        let before = diff::run(&codes, &values, input.max_steps);
+        let after = diff::run(&result, &values, input.max_steps);
+
+        match (&before.status, &after.status) {
+            (diff::Status::Exited(before), diff::Status::Exited(after))
+                => eprintln!("{}", opt::Report { before: *before, after: *after }),
+            (before, after) => eprintln!("original: {}, optimized: {}", before, after),
+        }
+        if let Some(divergence) = diff::compare(&before, &after) {
+            eprintln!("the optimized program diverges: {}", divergence);
+            exit(code::CHECK_FAILED);
+        }
+    }
+
+    write_output(options.output, &to_source(&result));
+}
+
+/// the options of `diff`
+struct DiffOptions {
+    inputs: Option<String>,
+    random: Option<usize>,
+    seed: u64,
+    length: usize,
+    max_steps: usize,
+    verbose: bool,
+}
+
+fn diff_programs(a: &str, b: &str, options: DiffOptions) {
+    let (a_lines, b_lines) = (read_source(a), read_source(b));
+    check_source(&a_lines);
+    check_source(&b_lines);
+    let ((a_codes, _), (b_codes, _)) = (parse_source(&a_lines), parse_source(&b_lines));
+
+    // the inputs with their names
+    let inputs: Vec<(String, Vec<String>)> = match (options.inputs, options.random) {
+        (Some(dir), _) => {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) => { eprintln!("can't read directory {}: {}", dir, e); exit(code::IO_ERROR) }
+            };
+            let mut paths: Vec<_> = entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "in"))
+                .collect();
+            paths.sort();
+            paths.into_iter().map(|path| match fs::read_to_string(&path) {
+                Ok(text) => (path.file_name().unwrap().to_string_lossy().into(), read_values(&text)),
+                Err(e) => { eprintln!("can't read {}: {}", path.display(), e); exit(code::IO_ERROR) }
+            }).collect()
+        }
+        (None, Some(count)) => {
+            eprintln!("{} random inputs with seed {}", count, options.seed);
+            diff::random_inputs(options.seed, count, options.length).into_iter()
+                .enumerate()
+                .map(|(k, input)| (format!("#{}", k), input))
+                .collect()
+        }
+        (None, None) => unreachable!(),
+    };
+
+    let (mut first, mut diverged) = (None, 0);
+    let (mut before, mut after, mut exited) = (0, 0, 0);
+    for (k, (name, input)) in inputs.iter().enumerate() {
+        let a_outcome = diff::run(&a_codes, input, Some(options.max_steps));
+        let b_outcome = diff::run(&b_codes, input, Some(options.max_steps));
+        let divergence = diff::compare(&a_outcome, &b_outcome);
+
+        if let (diff::Status::Exited(a_count), diff::Status::Exited(b_count)) = (&a_outcome.status, &b_outcome.status) {
+            before += a_count;
+            after += b_count;
+            exited += 1;
+        }
+        if options.verbose {
+            match &divergence {
+                Some(divergence) => println!("{}: {}", name, divergence),
+                None => println!("{}: {} / {}", name, a_outcome.status, b_outcome.status),
+            }
+        }
+        if let Some(divergence) = divergence {
+            diverged += 1;
+            first.get_or_insert((k, divergence));
+        }
+    }
+
+    if let Some((k, divergence)) = &first {
+        let (name, input) = &inputs[*k];
+        println!("the first divergence is at input {} ({} of {}): {}", name, k + 1, inputs.len(), divergence);
+        println!("  input: {}", input.join(" "));
+    }
+    println!("{} of {} inputs diverge", diverged, inputs.len());
+    if exited > 0 {
+        println!("{} over {} inputs where both exit", opt::Report { before, after }, exited);
+    }
+    if first.is_some() {
+        exit(code::CHECK_FAILED);
+    }
+}
+
+fn main() {
     // define cli i/o function
-    let Args {file, debug} = Args::parse();
+    let Args {
+        file, debug, input, args, format, profile, 
+        max_steps, exit_with_return, command
+    } = Args::parse();
+
+    if let Some(Command::Repl) = command {
+        repl::run();
+        return;
+    }
+
+    if let Some(Command::Cfg { file, dot, output }) = command {
+        print_cfg(&file, dot, output);
+        return;
+    }
+
+    if let Some(Command::Fmt { file, indent, check, write }) = command {
+        format_file(&file, indent, check, write);
+        return;
+    }
+
+    if let Some(Command::Compile { file, target, output }) = command {
+        compile(&file, target, output);
+        return;
+    }
+
+    if let Some(Command::Sim { file, input }) = command {
+        simulate(&file, input);
+        return;
+    }
+
+    if let Some(Command::Crosscheck { file, llvm, input }) = command {
+        crosscheck(&file, llvm, input);
+        return;
+    }
+
+    if let Some(Command::Cc { file, output, run, input }) = command {
+        compile_cmm(&file, output, run, input);
+        return;
+    }
+
+    if let Some(Command::Opt { file, level, passes, rules, output, verbose, verify, input }) = command {
+        optimize(&file, OptOptions { level, passes, rules, output, verbose, verify }, input);
+        return;
+    }
+
+    if let Some(Command::Diff { a, b, inputs, random, seed, length, max_steps, verbose }) = command {
+        diff_programs(&a, &b, DiffOptions { inputs, random, seed, length, max_steps, verbose });
+        return;
+    }
+
+    if let Some(Command::Lint { file, allow, list }) = command {
+        print_lint(&file, allow, list);
+        return;
+    }
+
+    if let Some(Command::Test { dir, budget, max_steps, jobs }) = command {
+        let cases = match golden::collect_cases(Path::new(&dir)) {
+            Ok(cases) => cases,
+            Err(msg) => { eprintln!("{}", msg); exit(code::IO_ERROR) }
+        };
+        let jobs = jobs.unwrap_or_else(
+            || thread::available_parallelism().map_or(1, |n| n.get())
+        );
+        let options = golden::Options { jobs, budget, max_steps };
+        exit(if golden::run(&cases, &options) { code::SUCCESS } else { code::CHECK_FAILED });
+    }
+    let file = file.unwrap();
+
+    // the debugger reads commands from stdin
+    // so READ should be fed by `--input` or `--args` in debug mode
+    let read_func: ReadFunc = match input_values(input, args) {
+        Some(values) => read_from_values(values),
+        None => read_from_stdin(),
+    };
+
+    // the output is captured in json format, it will be a field of the result
+    let (output, write_func): (_, WriteFunc) = match format {
+        Format::Text => (None, Box::new(|text: String| print!("{}", text))),
+        Format::Json => {
+            let (buffer, write_func) = write_to_buffer();
+            (Some(buffer), write_func)
+        }
+    };
 
     // read lines
-    let lines = read_lines_from_file(&file);
+    let lines = read_source(&file);
     let ref_liens = &lines.iter().map(|s| s as &str).collect();
 
     let interpreter = match Interpreter::from_lines(ref_liens, read_func, write_func) {
         Ok(i) => i,
-        Err(err) => { dbg!(err); exit(1) }
+        Err(err) => { 
+            match format {
+                Format::Text => eprintln!("{}", err),
+                Format::Json => println!("{}", report::static_error(&err)),
+            }
+            exit(code::of_static(&err)) 
+        }
     };
+    interpreter.set_limit(max_steps);
 
-
-    let debugger = Debugger::new(interpreter);
+    let warnings = interpreter.warnings();
+    if format == Format::Text {
+        for (line, warning) in &warnings {
+            eprintln!("[warn] line {}: {} [{}]", line, warning.msg, warning.code);
+        }
+    }
 
     if !debug {
-        let result = debugger.run();
-        print_run_result(result);
-        return;
+        let result = interpreter.run();
+        let exit_code = exit_code(&result, &interpreter, exit_with_return);
+        match output {
+            Some(output) => {
+                let profile = profile.then(|| interpreter.profile());
+                let report = report::run_result(
+                    &result, exit_code, &output.borrow(), &warnings, profile
+                );
+                println!("{}", report);
+            }
+            None => match result {
+                Ok(count) => print_over(count),
+                Err(err) => eprintln!("[error] {}", err),
+            }
+        }
+        exit(exit_code);
     }
 
+    let debugger = Debugger::new(interpreter);
+
     loop {
         eprint!("> ");
         let cmd = read_line();
@@ -90,6 +925,4 @@ fn main() {
             _ => eprintln!("Input Error: Command not found!")
         };
     }
-
-    
 }
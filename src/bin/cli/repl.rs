@@ -0,0 +1,100 @@
+use std::{collections::BTreeSet, io::{stdin, stderr, Write}};
+use core::{
+    ast::Sentence,
+    interpreter::{Interpreter, parser::LineParser},
+    utils::io::{read_from_stdin, WriteFunc},
+};
+
+const HELP: &str = "\
+Type an IR sentence to execute it, e.g. `t1 := #3`, `WRITE t1`, `DEC arr 40`.
+A function is defined by `FUNCTION f :` and its body, finished by an empty line.
+Commands:
+  :vars    list the variables and their values
+  :funcs   list the defined functions
+  :help    show this message
+  :quit    exit the repl";
+
+fn prompt(text: &str) -> Option<&'static str> {
+    eprint!("{}", text);
+    let _ = stderr().flush();
+
+    let mut line = String::new();
+    match stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        // the sentences borrow the line as long as the repl lives,
+        // so the line is leaked, it's tiny and the repl is short-lived
+        Ok(_) => Some(Box::leak(line.trim().to_string().into_boxed_str())),
+    }
+}
+
+pub fn run() {
+    let write_func: WriteFunc = Box::new(|text: String| print!("{}", text));
+    let interpreter = Interpreter::empty(read_from_stdin(), write_func);
+    let parser = LineParser::new();
+    let mut symbol_table = BTreeSet::new();
+
+    eprintln!("IR repl, type :help for help");
+    while let Some(line) = prompt(">>> ") {
+        match line {
+            "" => continue,
+            ":quit" | ":q" | "exit" => break,
+            ":help" => { eprintln!("{}", HELP); continue }
+            ":vars" => {
+                for (id, values) in interpreter.variables() {
+                    match values.as_slice() {
+                        [value] => eprintln!("{} = {}", id, value),
+                        _ => eprintln!("{} = {:?}", id, values),
+                    }
+                }
+                continue
+            }
+            ":funcs" => {
+                interpreter.functions().iter().for_each(|f| eprintln!("{}", f));
+                continue
+            }
+            _ => ()
+        }
+
+        let code = match parser.parse(line) {
+            Ok(Some(code)) => code,
+            Ok(None) => continue,
+            Err(err) => { eprintln!("[error] {}", err); continue }
+        };
+
+        if let Sentence::Func(_) = code {
+            let result = match read_function(&parser, code, || prompt("... ")) {
+                Some(codes) => interpreter.define(codes),
+                None => { eprintln!("[error] the function is not defined"); Ok(()) }
+            };
+            if let Err(err) = result {
+                eprintln!("[error] {}", err);
+            }
+            continue
+        }
+
+        if let Err(err) = interpreter.eval(code, &mut symbol_table) {
+            eprintln!("[error] {}", err);
+        }
+    }
+}
+
+/// collect the body of the function until an empty line,
+/// `None` if any line can't be parsed, the rest of the body is still consumed
+pub fn read_function<'a>(
+    parser: &LineParser,
+    head: Sentence<'a>,
+    mut next_line: impl FnMut() -> Option<&'a str>) -> Option<Vec<Sentence<'a>>>
+{
+    let (mut codes, mut failed) = (vec![head], false);
+    while let Some(line) = next_line() {
+        if line.is_empty() {
+            break
+        }
+        match parser.parse(line) {
+            Ok(Some(code)) => codes.push(code),
+            Ok(None) => (),
+            Err(err) => { eprintln!("[error] {}", err); failed = true }
+        }
+    }
+    (!failed).then_some(codes)
+}
@@ -0,0 +1,65 @@
+use crate::code;
+use core::{
+    analysis::Warning,
+    error::{InterpreterError as IError, RuntimeError as RError},
+    utils::json::Json,
+};
+
+pub fn static_error(err: &IError) -> Json {
+    Json::Object(vec![
+        ("status", "static_error".into()),
+        ("exit_code", code::of_static(err).into()),
+        ("count", Json::Null),
+        ("output", "".into()),
+        ("error", Json::Object(vec![
+            ("kind", err.kind().name().into()),
+            ("line", err.line().into()),
+            ("message", err.to_string().into()),
+        ])),
+        // nothing is linted before the source is parsed
+        ("warnings", Json::Array(vec![])),
+    ])
+}
+
+pub fn run_result(
+    result: &Result<usize, RError>,
+    exit_code: i32,
+    output: &str, 
+    warnings: &[(usize, Warning)],
+    profile: Option<Vec<(usize, usize)>>) -> Json 
+{
+    let (status, count, error) = match result {
+        Ok(count) => ("ok", Json::from(*count), Json::Null),
+        Err(err) => ("runtime_error", Json::Null, Json::Object(vec![
+            ("kind", err.kind().name().into()),
+            ("line", err.line().into()),
+            ("message", err.to_string().into()),
+        ])),
+    };
+
+    let mut fields = vec![
+        ("status", status.into()),
+        ("exit_code", exit_code.into()),
+        ("count", count),
+        ("output", output.into()),
+        ("error", error),
+        ("warnings", Json::Array(warnings.iter().map(|(line, warning)| Json::Object(vec![
+            ("line", (*line).into()),
+            ("code", warning.code.into()),
+            ("message", warning.msg.as_str().into()),
+        ])).collect())),
+    ];
+    if let Some(profile) = profile {
+        // only the sentences which have been executed are reported
+        let lines = profile.into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(line, count)| Json::Object(vec![
+                ("line", line.into()),
+                ("count", count.into()),
+            ]))
+            .collect();
+        fields.push(("profile", Json::Array(lines)));
+    }
+
+    Json::Object(fields)
+}
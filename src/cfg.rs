@@ -0,0 +1,280 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::ast::Sentence;
+
+/// the sentences in `[start, end)` are always executed one by one
+#[derive(Debug, PartialEq)]
+pub struct Block<'a> {
+    pub start: usize,
+    pub end: usize,
+    pub succs: Vec<usize>,
+    pub preds: Vec<usize>,
+    // the functions called in this block
+    pub calls: Vec<&'a str>,
+}
+
+/// the control flow graph of a function,
+/// the first block is the entrance which starts with `FUNCTION`
+#[derive(Debug)]
+pub struct Cfg<'a> {
+    pub name: &'a str,
+    pub start: usize,
+    pub end: usize,
+    pub blocks: Vec<Block<'a>>,
+    // the blocks jumped to by `GOTO` or `IF` of other functions,
+    // the labels are global so they are entered besides the entrance
+    pub entries: Vec<usize>,
+}
+
+impl<'a> Cfg<'a> {
+    /// build the graph of the function in `codes[start..end]`
+    pub fn new(codes: &[Sentence<'a>], start: usize, end: usize) -> Self {
+        let name = match codes[start] {
+            Sentence::Func(name) => name,
+            _ => panic!("function must start with FUNCTION")
+        };
+
+        // 1. find the first sentence of every block
+        let mut leaders = vec![start];
+        for i in start + 1..end {
+            let is_leader = matches!(codes[i], Sentence::Label(_))
+                || matches!(codes[i - 1], Sentence::Goto(_) | Sentence::IfGoto { .. } | Sentence::Return(_));
+            if is_leader {
+                leaders.push(i);
+            }
+        }
+
+        let labels: BTreeMap<&str, usize> = leaders.iter().enumerate()
+            .filter_map(|(k, i)| match codes[*i] {
+                Sentence::Label(label) => Some((label, k)),
+                _ => None
+            })
+            .collect();
+
+        // 2. link the blocks
+        let mut blocks: Vec<Block> = leaders.iter().enumerate().map(|(k, start)| {
+            let end = leaders.get(k + 1).copied().unwrap_or(end);
+            let calls = codes[*start..end].iter()
+                .filter_map(|code| match code {
+                    Sentence::Call { func, .. } => Some(*func),
+                    _ => None
+                })
+                .collect();
+            Block { start: *start, end, succs: Vec::new(), preds: Vec::new(), calls }
+        }).collect();
+
+        for k in 0..blocks.len() {
+            let next = (k + 1 < blocks.len()).then_some(k + 1);
+            // a label out of this function has no block
+            let succs: Vec<usize> = match &codes[blocks[k].end - 1] {
+                Sentence::Goto(label) => labels.get(label).copied().into_iter().collect(),
+                Sentence::IfGoto { label, .. } => next.into_iter()
+                    .chain(labels.get(label).copied())
+                    .collect(),
+                Sentence::Return(_) => Vec::new(),
+                _ => next.into_iter().collect(),
+            };
+            for succ in &succs {
+                if !blocks[*succ].preds.contains(&k) {
+                    blocks[*succ].preds.push(k);
+                }
+            }
+            let mut unique = Vec::new();
+            succs.into_iter().for_each(|s| if !unique.contains(&s) { unique.push(s) });
+            blocks[k].succs = unique;
+        }
+
+        let mut entries: Vec<usize> = codes[..start].iter().chain(&codes[end..])
+            .filter_map(|code| match code {
+                Sentence::Goto(label) | Sentence::IfGoto { label, .. } => labels.get(label).copied(),
+                _ => None
+            })
+            .collect();
+        entries.sort();
+        entries.dedup();
+
+        Cfg { name, start, end, blocks, entries }
+    }
+
+    /// the entrance and the blocks entered from other functions
+    pub fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        std::iter::once(0).chain(self.entries.iter().copied())
+    }
+
+    /// the block which the i-th sentence belongs to
+    pub fn block_of(&self, i: usize) -> Option<usize> {
+        self.blocks.iter().position(|block| block.start <= i && i < block.end)
+    }
+
+    /// whether the blocks can be reached from the entrance or other functions
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut stack: Vec<usize> = self.roots().collect();
+        while let Some(k) = stack.pop() {
+            if visited[k] {
+                continue
+            }
+            visited[k] = true;
+            stack.extend(self.blocks[k].succs.iter().filter(|s| !visited[**s]));
+        }
+        visited
+    }
+
+    /// the reachable blocks in reverse postorder, a block comes before its successors
+    /// unless the edge is a back edge
+    pub fn reverse_postorder(&self) -> Vec<usize> {
+        let (mut order, mut visited) = (Vec::new(), vec![false; self.blocks.len()]);
+        for root in self.roots() {
+            if visited[root] {
+                continue
+            }
+            // the block and the index of the next successor to visit
+            let mut stack = vec![(root, 0)];
+            visited[root] = true;
+            while let Some((k, next)) = stack.pop() {
+                match self.blocks[k].succs.get(next) {
+                    Some(succ) => {
+                        stack.push((k, next + 1));
+                        if !visited[*succ] {
+                            visited[*succ] = true;
+                            stack.push((*succ, 0));
+                        }
+                    }
+                    None => order.push(k),
+                }
+            }
+        }
+        order.reverse();
+        order
+    }
+
+    /// the immediate dominator of every block,
+    /// the roots and the unreachable blocks have none
+    pub fn dominators(&self) -> Vec<Option<usize>> {
+        // a virtual block after the others is the only predecessor of the roots
+        let (order, root) = (self.reverse_postorder(), self.blocks.len());
+        let mut rank = vec![usize::MAX; self.blocks.len() + 1];
+        rank[root] = 0;
+        order.iter().enumerate().for_each(|(r, k)| rank[*k] = r + 1);
+
+        // the algorithm of Cooper, Harvey and Kennedy
+        let mut idom: Vec<Option<usize>> = vec![None; self.blocks.len() + 1];
+        let roots: Vec<usize> = self.roots().collect();
+        idom[root] = Some(root);
+        roots.iter().for_each(|k| idom[*k] = Some(root));
+        let intersect = |idom: &[Option<usize>], mut a: usize, mut b: usize| {
+            while a != b {
+                while rank[a] > rank[b] { a = idom[a].unwrap() }
+                while rank[b] > rank[a] { b = idom[b].unwrap() }
+            }
+            a
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for k in order.iter().filter(|k| !roots.contains(k)) {
+                let new_idom = self.blocks[*k].preds.iter()
+                    .filter(|p| idom[**p].is_some())
+                    .fold(None, |acc, p| match acc {
+                        None => Some(*p),
+                        Some(acc) => Some(intersect(&idom, acc, *p)),
+                    });
+                if new_idom != idom[*k] {
+                    idom[*k] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        idom.pop();
+        idom.iter().map(|d| d.filter(|d| *d != root)).collect()
+    }
+
+    /// whether block `a` dominates block `b`
+    pub fn dominates(&self, idom: &[Option<usize>], a: usize, mut b: usize) -> bool {
+        loop {
+            if a == b {
+                return true
+            }
+            match idom[b] {
+                Some(d) => b = d,
+                None => return false,
+            }
+        }
+    }
+}
+
+/// the range of every function in codes, the first sentence is `FUNCTION`
+pub fn functions(codes: &[Sentence]) -> Vec<(usize, usize)> {
+    let starts: Vec<usize> = codes.iter().enumerate()
+        .filter(|(_, code)| matches!(code, Sentence::Func(_)))
+        .map(|(i, _)| i)
+        .collect();
+
+    starts.iter().enumerate()
+        .map(|(k, start)| (*start, starts.get(k + 1).copied().unwrap_or(codes.len())))
+        .collect()
+}
+
+/// the first `GOTO` or `IF` jumping to a label of another function,
+/// the interpreter allows it since the labels are global
+pub fn foreign_jump(codes: &[Sentence]) -> Option<usize> {
+    functions(codes).into_iter().find_map(|(start, end)| {
+        let labels: Vec<&str> = codes[start..end].iter()
+            .filter_map(|code| match code {
+                Sentence::Label(label) => Some(*label),
+                _ => None
+            })
+            .collect();
+        (start..end).find(|i| match &codes[*i] {
+            Sentence::Goto(label) | Sentence::IfGoto { label, .. } => !labels.contains(label),
+            _ => false
+        })
+    })
+}
+
+/// build the graph of every function in codes
+pub fn build<'a>(codes: &[Sentence<'a>]) -> Vec<Cfg<'a>> {
+    functions(codes).into_iter()
+        .map(|(start, end)| Cfg::new(codes, start, end))
+        .collect()
+}
+
+/// export the graphs in Graphviz DOT, every function is a cluster
+/// and the calls are the dashed edges to the entrance of callee
+pub fn to_dot(codes: &[Sentence], cfgs: &[Cfg]) -> String {
+    let mut dot = String::from("digraph cfg {\n    node [shape=box, fontname=monospace];\n");
+    let node = |func: &str, k: usize| format!("\"{}_{}\"", func, k);
+
+    for cfg in cfgs {
+        let _ = writeln!(dot, "    subgraph \"cluster_{}\" {{", cfg.name);
+        let _ = writeln!(dot, "        label = \"{}\";", cfg.name);
+        for (k, block) in cfg.blocks.iter().enumerate() {
+            // `\l` makes the lines left-justified
+            let text: String = codes[block.start..block.end].iter()
+                .map(|code| format!("{}\\l", code.to_string().replace('"', "\\\"")))
+                .collect();
+            let _ = writeln!(dot, "        {} [label=\"{}\"];", node(cfg.name, k), text);
+        }
+        for (k, block) in cfg.blocks.iter().enumerate() {
+            for succ in &block.succs {
+                let _ = writeln!(dot, "        {} -> {};", node(cfg.name, k), node(cfg.name, *succ));
+            }
+        }
+        let _ = writeln!(dot, "    }}");
+    }
+
+    for cfg in cfgs {
+        for (k, block) in cfg.blocks.iter().enumerate() {
+            for func in &block.calls {
+                if cfgs.iter().any(|cfg| cfg.name == *func) {
+                    let _ = writeln!(dot, "    {} -> {} [style=dashed];", node(cfg.name, k), node(func, 0));
+                }
+            }
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
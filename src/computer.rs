@@ -1,23 +1,36 @@
-use std::ops::AddAssign;
+use std::{cell::Cell, ops::AddAssign};
 
 const MAX_SIZE: usize = usize::pow(2, 10);
 
 pub struct Computer {
     memory: [i32; MAX_SIZE as usize],  
     pointer_stack: Vec<i32>,  
+    // an invalid address is accessed, e.g. the memory runs out
+    fault: Cell<bool>,
 }
 
 impl Computer {
     pub fn new() -> Self {
-        Computer { memory: [0; MAX_SIZE], pointer_stack: vec![0] }
+        Computer { memory: [0; MAX_SIZE], pointer_stack: vec![0], fault: Cell::new(false) }
     }
 
     pub fn load(&self, address: i32) -> i32 {
-        self.memory[get_addr(address)]
+        match self.memory.get(get_addr(address)) {
+            Some(value) => *value,
+            None => { self.fault.set(true); 0 }
+        }
     }
 
     pub fn save(&mut self, address: i32, value: i32) {
-        self.memory[get_addr(address)] = value;
+        match self.memory.get_mut(get_addr(address)) {
+            Some(cell) => *cell = value,
+            None => self.fault.set(true),
+        }
+    }
+
+    /// whether an invalid address is accessed since the last call
+    pub fn fault(&self) -> bool {
+        self.fault.replace(false)
     }
 
     pub fn allocate(&mut self, size: i32) -> i32 {
@@ -37,6 +50,7 @@ impl Computer {
     }
 
     pub fn clear(&mut self) {
+        self.fault.set(false);
         self.pointer_stack.clear();
         self.pointer_stack.push(0);
     }
@@ -49,14 +49,8 @@ impl<'a> Debugger<'a> {
     } 
 
     pub fn run(&self) -> Result<usize, Message> {
-        loop {
-            let result = self.interpreter.execute().or_else(
-                |e| Err(Message::new(e.message(), MessageKind::Error))
-            )?;
-            if let Some(count) = result {
-                break Ok(count)
-            }
-        }
+        self.interpreter.run()
+            .map_err(|e| Message::new(e.message(), MessageKind::Error))
     }
 
     pub fn step(&self) -> Result<Option<usize>, Message> {
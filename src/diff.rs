@@ -0,0 +1,100 @@
+use std::fmt::{Display, self};
+
+use crate::{
+    ast::Sentence,
+    interpreter::Interpreter,
+    utils::{io::{read_from_values, write_to_buffer}, rng::Rng},
+};
+
+/// how a program ends
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    /// exited with the running count
+    Exited(usize),
+    /// stopped by the runtime error, e.g. `StepLimitError` or `DivideByZeroError`
+    Failed { kind: &'static str, msg: String },
+}
+
+impl Status {
+    /// the same kind of ending, the running count and the messages don't matter
+    pub fn same(&self, other: &Status) -> bool {
+        match (self, other) {
+            (Status::Exited(_), Status::Exited(_)) => true,
+            (Status::Failed { kind: a, .. }, Status::Failed { kind: b, .. }) => a == b,
+            _ => false
+        }
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Exited(count) => write!(f, "exited after {} instructions", count),
+            Status::Failed { msg, .. } => write!(f, "failed: {}", msg),
+        }
+    }
+}
+
+/// the output and the ending of a program on an input
+#[derive(Debug)]
+pub struct Outcome {
+    pub output: String,
+    pub status: Status,
+}
+
+/// the first difference of two outcomes, the output is compared before the status
+#[derive(Debug, PartialEq)]
+pub enum Divergence {
+    /// the lines of output at `line` (starting from 0), `None` means the output is over
+    Output { line: usize, a: Option<String>, b: Option<String> },
+    Status(Status, Status),
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::Output { line, a, b } => {
+                let show = |text: &Option<String>| text.as_ref().map_or("the end".into(), |text| format!("`{}`", text));
+                write!(f, "the output diverges at line {}: {} vs {}", line + 1, show(a), show(b))
+            }
+            Divergence::Status(a, b) => write!(f, "the status diverges: {} vs {}", a, b),
+        }
+    }
+}
+
+/// run the codes on the input, the codes must pass the checking
+pub fn run(codes: &[Sentence], input: &[String], limit: Option<usize>) -> Outcome {
+    let (output, write_func) = write_to_buffer();
+    let interpreter = Interpreter::from_codes(codes.to_vec(), read_from_values(input.to_vec()), write_func)
+        .unwrap_or_else(|err| panic!("the codes must be checked: {}", err));
+    interpreter.set_limit(limit);
+
+    let status = match interpreter.run() {
+        Ok(count) => Status::Exited(count),
+        Err(err) => Status::Failed { kind: err.kind().name(), msg: err.to_string() },
+    };
+    let output = output.borrow().clone();
+    Outcome { output, status }
+}
+
+/// the first divergence of two outcomes, the trailing whitespace of lines is ignored
+pub fn compare(a: &Outcome, b: &Outcome) -> Option<Divergence> {
+    let (mut a_lines, mut b_lines) = (a.output.lines(), b.output.lines());
+    let mut line = 0;
+    loop {
+        match (a_lines.next().map(str::trim_end), b_lines.next().map(str::trim_end)) {
+            (None, None) => break,
+            (x, y) if x == y => line += 1,
+            (x, y) => return Some(Divergence::Output { line, a: x.map(String::from), b: y.map(String::from) }),
+        }
+    }
+    (!a.status.same(&b.status)).then(|| Divergence::Status(a.status.clone(), b.status.clone()))
+}
+
+/// the random inputs of `len` values in `[-100, 100]`, the small values keep the loops short
+pub fn random_inputs(seed: u64, count: usize, len: usize) -> Vec<Vec<String>> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .map(|_| (0..len).map(|_| (rng.below(201) as i32 - 100).to_string()).collect())
+        .collect()
+}
@@ -20,6 +20,18 @@ impl <'a>InterpreterError<'a> {
     pub fn new_err<T>(kind: InterpreterErrorKind<'a>, i: usize) -> Result<T, Self> {
         Err(InterpreterError{kind, i})
     }
+
+    pub fn kind(&self) -> &InterpreterErrorKind<'a> {
+        &self.kind
+    }
+
+    pub fn line(&self) -> usize {
+        self.i
+    }
+
+    pub(crate) fn at(self, i: usize) -> Self {
+        InterpreterError { i, ..self }
+    }
 }
 
 #[derive(Debug)]
@@ -39,11 +51,59 @@ pub enum InterpreterErrorKind<'a> {
     CurrentFuncNoneError,
     DuplicatedFuncError,
     UndefinedFuncError,
+    // a function defined in the repl doesn't end with `RETURN` or `GOTO`
+    FuncEndError,
 
     // input
     LeftValueError,
 }
 
+impl InterpreterErrorKind<'_> {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InterpreterErrorKind::ParseError(_) => "ParseError",
+            InterpreterErrorKind::IRSyntaxError => "IRSyntaxError",
+            InterpreterErrorKind::DuplicatedLabelError => "DuplicatedLabelError",
+            InterpreterErrorKind::UndefinedLabelError => "UndefinedLabelError",
+            InterpreterErrorKind::DuplicatedVariableError => "DuplicatedVariableError",
+            InterpreterErrorKind::UndefinedVariableError => "UndefinedVariableError",
+            InterpreterErrorKind::CurrentFuncNoneError => "CurrentFuncNoneError",
+            InterpreterErrorKind::DuplicatedFuncError => "DuplicatedFuncError",
+            InterpreterErrorKind::UndefinedFuncError => "UndefinedFuncError",
+            InterpreterErrorKind::FuncEndError => "FuncEndError",
+            InterpreterErrorKind::LeftValueError => "LeftValueError",
+        }
+    }
+}
+
+impl BaseError for InterpreterError<'_> {
+    fn msg(&self) -> &'static str {
+        match self.kind {
+            InterpreterErrorKind::ParseError(_) => "sentence can't be parsed",
+            InterpreterErrorKind::IRSyntaxError => "invalid IR syntax",
+            InterpreterErrorKind::DuplicatedLabelError => "label is defined more than once",
+            InterpreterErrorKind::UndefinedLabelError => "label is not defined",
+            InterpreterErrorKind::DuplicatedVariableError => "variable is defined more than once",
+            InterpreterErrorKind::UndefinedVariableError => "variable is not defined",
+            InterpreterErrorKind::CurrentFuncNoneError => "sentence is outside of any function",
+            InterpreterErrorKind::DuplicatedFuncError => "function is defined more than once",
+            InterpreterErrorKind::UndefinedFuncError => "function is not defined",
+            InterpreterErrorKind::FuncEndError => "function must end with RETURN or GOTO",
+            InterpreterErrorKind::LeftValueError => "invalid left value",
+        }
+    }
+}
+
+impl Display for InterpreterError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            InterpreterErrorKind::ParseError(err) 
+                => write!(f, "Parse error at line {}: {}", self.i, err),
+            _ => write!(f, "Syntax error at line {}: {}", self.i, self.msg())
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RuntimeError {
     kind: RuntimeErrorKind,
@@ -53,6 +113,16 @@ pub struct RuntimeError {
 #[derive(Debug)]
 pub enum RuntimeErrorKind {
     InputError,
+    InputExhaustedError,
+    StepLimitError,
+    DivideByZeroError,
+    // `PARAM` finds no argument, e.g. in `main`
+    ArgumentError,
+    // a function runs past its last sentence without `RETURN`
+    NoReturnError,
+    MemoryError,
+    // only raised by the mips simulator
+    SyscallError,
 }
 
 impl RuntimeError {
@@ -60,20 +130,189 @@ impl RuntimeError {
         Err(RuntimeError {kind, i})
     }
 
+    pub fn kind(&self) -> &RuntimeErrorKind {
+        &self.kind
+    }
+
+    pub fn line(&self) -> usize {
+        self.i
+    }
+
+    pub(crate) fn at(self, i: usize) -> Self {
+        RuntimeError { i, ..self }
+    }
+
     pub fn message(&self) -> String {
-        let msg = match self.kind {
-            RuntimeErrorKind::InputError => "input must be number",
-        };
+        self.to_string()
+    }
+}
+
+impl RuntimeErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RuntimeErrorKind::InputError => "InputError",
+            RuntimeErrorKind::InputExhaustedError => "InputExhaustedError",
+            RuntimeErrorKind::StepLimitError => "StepLimitError",
+            RuntimeErrorKind::DivideByZeroError => "DivideByZeroError",
+            RuntimeErrorKind::ArgumentError => "ArgumentError",
+            RuntimeErrorKind::NoReturnError => "NoReturnError",
+            RuntimeErrorKind::MemoryError => "MemoryError",
+            RuntimeErrorKind::SyscallError => "SyscallError",
+        }
+    }
+}
 
-        format!("Runtime error at line {}: {}", self.i, msg)
+impl BaseError for RuntimeError {
+    fn msg(&self) -> &'static str {
+        match self.kind {
+            RuntimeErrorKind::InputError => "input must be number",
+            RuntimeErrorKind::InputExhaustedError => "no more input to read",
+            RuntimeErrorKind::StepLimitError => "instruction count exceeds the limit",
+            RuntimeErrorKind::DivideByZeroError => "divided by zero",
+            RuntimeErrorKind::ArgumentError => "no argument is passed for PARAM",
+            RuntimeErrorKind::NoReturnError => "function runs past its end without RETURN",
+            RuntimeErrorKind::MemoryError => "invalid memory address or out of memory",
+            RuntimeErrorKind::SyscallError => "unsupported syscall",
+        }
     }
 }
 
 impl Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let msg = match self.kind {
-            RuntimeErrorKind::InputError => "input must be number",
-        };
-        write!(f, "Runtime error at line {}: {}", self.i, msg)
+        write!(f, "Runtime error at line {}: {}", self.i, self.msg())
+    }
+}
+
+/// the error of assembling mips code, `i` is the line number
+#[derive(Debug)]
+pub struct AsmError {
+    msg: String,
+    i: usize
+}
+
+impl AsmError {
+    pub fn new_err<T>(msg: String, i: usize) -> Result<T, AsmError> {
+        Err(AsmError { msg, i })
+    }
+
+    pub fn line(&self) -> usize {
+        self.i
+    }
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Assemble error at line {}: {}", self.i, self.msg)
+    }
+}
+
+/// the error of generating code for a backend, `i` is the index of sentence
+/// until `at` sets the line number
+#[derive(Debug)]
+pub struct GenerateError {
+    msg: String,
+    i: usize
+}
+
+impl GenerateError {
+    pub fn new_err<T>(msg: String, i: usize) -> Result<T, GenerateError> {
+        Err(GenerateError { msg, i })
+    }
+
+    pub fn line(&self) -> usize {
+        self.i
+    }
+
+    pub fn at(self, i: usize) -> Self {
+        GenerateError { i, ..self }
+    }
+}
+
+impl Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Generate error at line {}: {}", self.i, self.msg)
+    }
+}
+
+/// the error of evaluating a sentence in the repl
+#[derive(Debug)]
+pub enum EvalError<'a> {
+    Static(InterpreterError<'a>),
+    Runtime(RuntimeError),
+}
+
+impl<'a> From<InterpreterError<'a>> for EvalError<'a> {
+    fn from(err: InterpreterError<'a>) -> Self {
+        EvalError::Static(err)
+    }
+}
+
+impl From<RuntimeError> for EvalError<'_> {
+    fn from(err: RuntimeError) -> Self {
+        EvalError::Runtime(err)
+    }
+}
+
+impl Display for EvalError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Static(err) => write!(f, "{}", err),
+            EvalError::Runtime(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// the error of compiling C--, `i` is the line number of the source
+#[derive(Debug)]
+pub struct CompileError {
+    kind: CompileErrorKind,
+    msg: String,
+    i: usize
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CompileErrorKind {
+    LexicalError,
+    SyntaxError,
+    // the error types numbered by the course, e.g. 1 for undefined variable
+    SemanticError(u8),
+    // the program is valid C-- but can't be translated into IR, e.g. `float`
+    UnsupportedError,
+}
+
+impl CompileError {
+    pub fn new_err<T>(kind: CompileErrorKind, msg: String, i: usize) -> Result<T, CompileError> {
+        Err(CompileError { kind, msg, i })
     }
-}
\ No newline at end of file
+
+    pub fn kind(&self) -> &CompileErrorKind {
+        &self.kind
+    }
+
+    pub fn line(&self) -> usize {
+        self.i
+    }
+}
+
+impl CompileErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompileErrorKind::LexicalError => "LexicalError",
+            CompileErrorKind::SyntaxError => "SyntaxError",
+            CompileErrorKind::SemanticError(_) => "SemanticError",
+            CompileErrorKind::UnsupportedError => "UnsupportedError",
+        }
+    }
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            CompileErrorKind::LexicalError => write!(f, "Lexical error at line {}: {}", self.i, self.msg),
+            CompileErrorKind::SyntaxError => write!(f, "Syntax error at line {}: {}", self.i, self.msg),
+            CompileErrorKind::SemanticError(t) 
+                => write!(f, "Semantic error type {} at line {}: {}", t, self.i, self.msg),
+            CompileErrorKind::UnsupportedError => write!(f, "Unsupported at line {}: {}", self.i, self.msg),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{
+    ast::Sentence,
+    error::{InterpreterError as IError, InterpreterErrorKind::ParseError},
+    interpreter::parser::LineParser,
+};
+
+/// split the comment from the line, the comment starts with `;` or `//`
+pub fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let start = [line.find(';'), line.find("//")].into_iter().flatten().min();
+    match start {
+        Some(start) => (line[..start].trim_end(), Some(line[start..].trim_end())),
+        None => (line.trim_end(), None),
+    }
+}
+
+/// format the source in canonical syntax, the comments and single empty lines are kept
+///
+/// with `indent`, the sentences under `FUNCTION` are indented by one level,
+/// and the sentences under `LABEL` are indented by two levels
+pub fn format_source<'a>(lines: &[&'a str], indent: Option<usize>) -> Result<String, IError<'a>> {
+    let parser = LineParser::new();
+    let (mut result, mut level, mut last_empty) = (String::new(), 0, true);
+
+    for (i, line) in lines.iter().enumerate() {
+        let (code, comment) = split_comment(line.trim());
+        let code = match parser.parse(code) {
+            Ok(code) => code,
+            Err(err) => return IError::new_err(ParseError(err), i + 1),
+        };
+
+        // the empty lines are merged, and removed at the beginning
+        if code.is_none() && comment.is_none() {
+            if !last_empty {
+                result.push('\n');
+            }
+            last_empty = true;
+            continue
+        }
+        last_empty = false;
+
+        let (text, this_level) = match &code {
+            Some(code @ Sentence::Func(_)) => { level = 1; (code.to_string(), 0) }
+            Some(code @ Sentence::Label(_)) => { level = 2; (code.to_string(), 1) }
+            Some(code) => (code.to_string(), level),
+            // the comment line follows the indent of next sentence
+            None => (String::new(), level),
+        };
+        if let Some(width) = indent {
+            result.push_str(&" ".repeat(width * this_level));
+        }
+        result.push_str(&text);
+        if let Some(comment) = comment {
+            if !text.is_empty() {
+                result.push(' ');
+            }
+            result.push_str(comment);
+        }
+        result.push('\n');
+    }
+
+    // no empty line at the end
+    while result.ends_with("\n\n") {
+        result.pop();
+    }
+    Ok(result)
+}
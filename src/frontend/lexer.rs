@@ -0,0 +1,194 @@
+use std::fmt::{Display, self};
+
+use crate::{
+    ast::Operator,
+    error::{CompileError, CompileErrorKind::LexicalError},
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Token<'a> {
+    Int(i32),
+    Float(f32),
+    Id(&'a str),
+    // `int` or `float`
+    Type(&'a str),
+    Struct, Return, If, Else, While,
+    Semi, Comma, Assign,
+    Relop(Operator),
+    Plus, Minus, Star, Div,
+    And, Or, Dot, Not,
+    LP, RP, LB, RB, LC, RC,
+}
+
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Int(n) => write!(f, "{}", n),
+            Token::Float(n) => write!(f, "{}", n),
+            Token::Id(id) | Token::Type(id) => write!(f, "{}", id),
+            Token::Relop(opt) => write!(f, "{}", opt),
+            _ => {
+                let text = match self {
+                    Token::Struct => "struct",
+                    Token::Return => "return",
+                    Token::If => "if",
+                    Token::Else => "else",
+                    Token::While => "while",
+                    Token::Semi => ";",
+                    Token::Comma => ",",
+                    Token::Assign => "=",
+                    Token::Plus => "+",
+                    Token::Minus => "-",
+                    Token::Star => "*",
+                    Token::Div => "/",
+                    Token::And => "&&",
+                    Token::Or => "||",
+                    Token::Dot => ".",
+                    Token::Not => "!",
+                    Token::LP => "(",
+                    Token::RP => ")",
+                    Token::LB => "[",
+                    Token::RB => "]",
+                    Token::LC => "{",
+                    Token::RC => "}",
+                    _ => unreachable!()
+                };
+                write!(f, "{}", text)
+            }
+        }
+    }
+}
+
+/// split the source into tokens with their line numbers,
+/// the comments `// ...` and `/* ... */` are skipped
+pub fn tokenize(source: &str) -> Result<Vec<(Token<'_>, usize)>, CompileError> {
+    let bytes = source.as_bytes();
+    let (mut tokens, mut i, mut line) = (Vec::new(), 0, 1);
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        let next = bytes.get(i + 1).copied();
+        match c {
+            b'\n' => { line += 1; i += 1; continue }
+            c if c.is_ascii_whitespace() => { i += 1; continue }
+            b'/' if next == Some(b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue
+            }
+            b'/' if next == Some(b'*') => {
+                let start = line;
+                i += 2;
+                loop {
+                    match bytes.get(i) {
+                        None => return CompileError::new_err(LexicalError, "unterminated comment".into(), start),
+                        Some(b'*') if bytes.get(i + 1) == Some(&b'/') => { i += 2; break }
+                        Some(b'\n') => line += 1,
+                        _ => ()
+                    }
+                    i += 1;
+                }
+                continue
+            }
+            _ => ()
+        }
+
+        // the word is an identifier, keyword or number
+        if c.is_ascii_alphanumeric() || c == b'_' || c == b'.' && next.is_some_and(|n| n.is_ascii_digit()) {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'.') {
+                // `a.b` is a field access, the dot only belongs to numbers
+                if bytes[i] == b'.' && !bytes[start].is_ascii_digit() && bytes[start] != b'.' {
+                    break
+                }
+                i += 1;
+            }
+            // the sign of exponent, e.g. `1.5e-3`
+            if bytes[start].is_ascii_digit() && matches!(bytes[i - 1], b'e' | b'E')
+                && matches!(bytes.get(i), Some(b'+' | b'-')) && source[start..i].contains('.')
+            {
+                i += 1;
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            tokens.push((word(&source[start..i], line)?, line));
+            continue
+        }
+
+        let (token, len) = match (c, next) {
+            (b'&', Some(b'&')) => (Token::And, 2),
+            (b'|', Some(b'|')) => (Token::Or, 2),
+            (b'=', Some(b'=')) => (Token::Relop(Operator::Equal), 2),
+            (b'!', Some(b'=')) => (Token::Relop(Operator::NotEqual), 2),
+            (b'>', Some(b'=')) => (Token::Relop(Operator::GreaterEqual), 2),
+            (b'<', Some(b'=')) => (Token::Relop(Operator::LessEqual), 2),
+            (b'>', _) => (Token::Relop(Operator::Greater), 1),
+            (b'<', _) => (Token::Relop(Operator::Less), 1),
+            (b'=', _) => (Token::Assign, 1),
+            (b'!', _) => (Token::Not, 1),
+            (b';', _) => (Token::Semi, 1),
+            (b',', _) => (Token::Comma, 1),
+            (b'+', _) => (Token::Plus, 1),
+            (b'-', _) => (Token::Minus, 1),
+            (b'*', _) => (Token::Star, 1),
+            (b'/', _) => (Token::Div, 1),
+            (b'.', _) => (Token::Dot, 1),
+            (b'(', _) => (Token::LP, 1),
+            (b')', _) => (Token::RP, 1),
+            (b'[', _) => (Token::LB, 1),
+            (b']', _) => (Token::RB, 1),
+            (b'{', _) => (Token::LC, 1),
+            (b'}', _) => (Token::RC, 1),
+            _ => {
+                let c = source[i..].chars().next().unwrap();
+                return CompileError::new_err(LexicalError, format!("mysterious character '{}'", c), line)
+            }
+        };
+        tokens.push((token, line));
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+/// the token of a word, the integers can be decimal, octal like `017` or hexadecimal like `0x1f`
+fn word(text: &str, line: usize) -> Result<Token<'_>, CompileError> {
+    let first = text.as_bytes()[0];
+    if first.is_ascii_alphabetic() || first == b'_' {
+        return Ok(match text {
+            "int" | "float" => Token::Type(text),
+            "struct" => Token::Struct,
+            "return" => Token::Return,
+            "if" => Token::If,
+            "else" => Token::Else,
+            "while" => Token::While,
+            _ => Token::Id(text),
+        })
+    }
+
+    let invalid = || CompileError::new_err(LexicalError, format!("invalid number '{}'", text), line);
+    if text.contains('.') || (text.contains(['e', 'E']) && !text.starts_with("0x") && !text.starts_with("0X")) {
+        // a float needs digits on both sides of the dot
+        let valid = text.split(['e', 'E']).next()
+            .and_then(|mantissa| mantissa.split_once('.'))
+            .is_some_and(|(l, r)| !l.is_empty() && !r.is_empty());
+        return match text.parse::<f32>() {
+            Ok(n) if valid => Ok(Token::Float(n)),
+            _ => invalid(),
+        }
+    }
+
+    let value = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16)
+    } else if text.len() > 1 && text.starts_with('0') {
+        u32::from_str_radix(&text[1..], 8)
+    } else {
+        text.parse::<u32>()
+    };
+    match value {
+        Ok(n) if n <= i32::MAX as u32 => Ok(Token::Int(n as i32)),
+        _ => invalid(),
+    }
+}
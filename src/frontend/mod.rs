@@ -0,0 +1,15 @@
+use crate::{ast::Sentence, error::CompileError};
+
+pub mod lexer;
+pub mod parser;
+pub mod semantic;
+pub mod syntax;
+pub mod translate;
+
+/// compile the C-- source into IR, the sentences can be run by the `Interpreter` directly
+pub fn compile(source: &str) -> Result<Vec<Sentence<'static>>, CompileError> {
+    let tokens = lexer::tokenize(source)?;
+    let program = parser::parse(&tokens)?;
+    let env = semantic::check(&program)?;
+    translate::translate(&program, &env)
+}
@@ -0,0 +1,324 @@
+use crate::{
+    ast::Operator,
+    error::{CompileError, CompileErrorKind::SyntaxError},
+};
+use super::{
+    lexer::Token,
+    syntax::{Block, Dec, Def, ExtDef, Expr, ExprKind, Function, Program, Specifier, Stmt, StructSpec, VarDec},
+};
+
+type Result<T> = std::result::Result<T, CompileError>;
+
+/// parse the tokens by recursive descent, the expressions by precedence climbing
+pub fn parse<'a>(tokens: &[(Token<'a>, usize)]) -> Result<Program<'a>> {
+    let mut parser = Parser { tokens, i: 0, structs: 0 };
+    let mut defs = Vec::new();
+    while parser.peek().is_some() {
+        defs.push(parser.ext_def()?);
+    }
+    Ok(Program { defs })
+}
+
+struct Parser<'a, 't> {
+    tokens: &'t [(Token<'a>, usize)],
+    i: usize,
+    // the number of struct definitions met
+    structs: usize,
+}
+
+impl<'a> Parser<'a, '_> {
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.i).map(|(token, _)| *token)
+    }
+
+    fn peek_at(&self, k: usize) -> Option<Token<'a>> {
+        self.tokens.get(self.i + k).map(|(token, _)| *token)
+    }
+
+    /// the line of the next token, or the last line at the end
+    fn line(&self) -> usize {
+        self.tokens.get(self.i).or(self.tokens.last()).map_or(1, |(_, line)| *line)
+    }
+
+    fn error<T>(&self) -> Result<T> {
+        let msg = match self.peek() {
+            Some(token) => format!("unexpected '{}'", token),
+            None => "unexpected end of file".into(),
+        };
+        CompileError::new_err(SyntaxError, msg, self.line())
+    }
+
+    fn eat(&mut self, token: Token) -> bool {
+        let matched = self.peek() == Some(token);
+        if matched {
+            self.i += 1;
+        }
+        matched
+    }
+
+    fn expect(&mut self, token: Token) -> Result<()> {
+        match self.eat(token) {
+            true => Ok(()),
+            false => {
+                let found = match self.peek() {
+                    Some(found) => format!("'{}'", found),
+                    None => "end of file".into(),
+                };
+                CompileError::new_err(SyntaxError, format!("expect '{}' but found {}", token, found), self.line())
+            }
+        }
+    }
+
+    fn id(&mut self) -> Result<&'a str> {
+        match self.peek() {
+            Some(Token::Id(id)) => { self.i += 1; Ok(id) }
+            _ => self.error(),
+        }
+    }
+
+    fn ext_def(&mut self) -> Result<ExtDef<'a>> {
+        let line = self.line();
+        let spec = self.specifier()?;
+        if self.eat(Token::Semi) {
+            return Ok(ExtDef::Type(spec))
+        }
+
+        if matches!((self.peek(), self.peek_at(1)), (Some(Token::Id(_)), Some(Token::LP))) {
+            let name = self.id()?;
+            self.expect(Token::LP)?;
+            let mut params = Vec::new();
+            if !self.eat(Token::RP) {
+                loop {
+                    let spec = self.specifier()?;
+                    params.push((spec, self.var_dec()?));
+                    if self.eat(Token::RP) {
+                        break
+                    }
+                    self.expect(Token::Comma)?;
+                }
+            }
+            let body = self.block()?;
+            return Ok(ExtDef::Func(Function { ret: spec, name, params, body, line }))
+        }
+
+        let mut vars = vec![self.var_dec()?];
+        while self.eat(Token::Comma) {
+            vars.push(self.var_dec()?);
+        }
+        self.expect(Token::Semi)?;
+        Ok(ExtDef::Vars(spec, vars))
+    }
+
+    fn specifier(&mut self) -> Result<Specifier<'a>> {
+        match self.peek() {
+            Some(Token::Type(ty)) => {
+                self.i += 1;
+                Ok(if ty == "int" { Specifier::Int } else { Specifier::Float })
+            }
+            Some(Token::Struct) => {
+                let line = self.line();
+                self.i += 1;
+                let name = match self.peek() {
+                    Some(Token::Id(name)) => { self.i += 1; Some(name) }
+                    _ => None,
+                };
+                if !self.eat(Token::LC) {
+                    return match name {
+                        Some(name) => Ok(Specifier::Struct(StructSpec::Tag { name, line })),
+                        None => self.error(),
+                    }
+                }
+                let id = self.structs;
+                self.structs += 1;
+                let fields = self.defs()?;
+                self.expect(Token::RC)?;
+                Ok(Specifier::Struct(StructSpec::Def { name, fields, id, line }))
+            }
+            _ => self.error(),
+        }
+    }
+
+    fn var_dec(&mut self) -> Result<VarDec<'a>> {
+        let line = self.line();
+        let name = self.id()?;
+        let mut dims = Vec::new();
+        while self.eat(Token::LB) {
+            match self.peek() {
+                Some(Token::Int(n)) => { self.i += 1; dims.push(n) }
+                _ => return self.error(),
+            }
+            self.expect(Token::RB)?;
+        }
+        Ok(VarDec { name, dims, line })
+    }
+
+    /// the definitions until a token which can't start a specifier
+    fn defs(&mut self) -> Result<Vec<Def<'a>>> {
+        let mut defs = Vec::new();
+        while matches!(self.peek(), Some(Token::Type(_) | Token::Struct)) {
+            let spec = self.specifier()?;
+            let mut decs = Vec::new();
+            loop {
+                let var = self.var_dec()?;
+                let init = match self.eat(Token::Assign) {
+                    true => Some(self.expr(0)?),
+                    false => None,
+                };
+                decs.push(Dec { var, init });
+                if !self.eat(Token::Comma) {
+                    break
+                }
+            }
+            self.expect(Token::Semi)?;
+            defs.push(Def { spec, decs });
+        }
+        Ok(defs)
+    }
+
+    fn block(&mut self) -> Result<Block<'a>> {
+        self.expect(Token::LC)?;
+        let defs = self.defs()?;
+        let mut stmts = Vec::new();
+        while !self.eat(Token::RC) {
+            stmts.push(self.stmt()?);
+        }
+        Ok(Block { defs, stmts })
+    }
+
+    fn stmt(&mut self) -> Result<Stmt<'a>> {
+        match self.peek() {
+            Some(Token::LC) => Ok(Stmt::Block(self.block()?)),
+            Some(Token::Return) => {
+                self.i += 1;
+                let expr = self.expr(0)?;
+                self.expect(Token::Semi)?;
+                Ok(Stmt::Return(expr))
+            }
+            Some(Token::If) => {
+                self.i += 1;
+                let cond = self.cond()?;
+                let then = Box::new(self.stmt()?);
+                // `else` belongs to the nearest `if`
+                let other = match self.eat(Token::Else) {
+                    true => Some(Box::new(self.stmt()?)),
+                    false => None,
+                };
+                Ok(Stmt::If(cond, then, other))
+            }
+            Some(Token::While) => {
+                self.i += 1;
+                let cond = self.cond()?;
+                Ok(Stmt::While(cond, Box::new(self.stmt()?)))
+            }
+            _ => {
+                let expr = self.expr(0)?;
+                self.expect(Token::Semi)?;
+                Ok(Stmt::Expr(expr))
+            }
+        }
+    }
+
+    /// `( Exp )` after `if` and `while`
+    fn cond(&mut self) -> Result<Expr<'a>> {
+        self.expect(Token::LP)?;
+        let expr = self.expr(0)?;
+        self.expect(Token::RP)?;
+        Ok(expr)
+    }
+
+    /// the expression whose operators bind at least as tight as `min`
+    fn expr(&mut self, min: u8) -> Result<Expr<'a>> {
+        let mut lhs = self.unary()?;
+        loop {
+            // the precedence, `=` is the only right associative one
+            let prec = match self.peek() {
+                Some(Token::Assign) => 1,
+                Some(Token::Or) => 2,
+                Some(Token::And) => 3,
+                Some(Token::Relop(_)) => 4,
+                Some(Token::Plus | Token::Minus) => 5,
+                Some(Token::Star | Token::Div) => 6,
+                _ => break,
+            };
+            if prec < min {
+                break
+            }
+            let token = self.peek().unwrap();
+            let line = self.line();
+            self.i += 1;
+            let rhs = Box::new(self.expr(if prec == 1 { prec } else { prec + 1 })?);
+            let l = Box::new(lhs);
+            let kind = match token {
+                Token::Assign => ExprKind::Assign(l, rhs),
+                Token::Or => ExprKind::Or(l, rhs),
+                Token::And => ExprKind::And(l, rhs),
+                Token::Relop(opt) => ExprKind::Rel(opt, l, rhs),
+                Token::Plus => ExprKind::Arith(Operator::Plus, l, rhs),
+                Token::Minus => ExprKind::Arith(Operator::Sub, l, rhs),
+                Token::Star => ExprKind::Arith(Operator::Mul, l, rhs),
+                _ => ExprKind::Arith(Operator::Div, l, rhs),
+            };
+            lhs = Expr { kind, line };
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr<'a>> {
+        let line = self.line();
+        let kind = match self.peek() {
+            Some(Token::Minus) => { self.i += 1; ExprKind::Neg(Box::new(self.unary()?)) }
+            Some(Token::Not) => { self.i += 1; ExprKind::Not(Box::new(self.unary()?)) }
+            _ => return self.postfix(),
+        };
+        Ok(Expr { kind, line })
+    }
+
+    fn postfix(&mut self) -> Result<Expr<'a>> {
+        let line = self.line();
+        let kind = match self.peek() {
+            Some(Token::Int(n)) => { self.i += 1; ExprKind::Int(n) }
+            Some(Token::Float(n)) => { self.i += 1; ExprKind::Float(n) }
+            Some(Token::LP) => {
+                self.i += 1;
+                let expr = self.expr(0)?;
+                self.expect(Token::RP)?;
+                expr.kind
+            }
+            Some(Token::Id(id)) => {
+                self.i += 1;
+                match self.eat(Token::LP) {
+                    true => {
+                        let mut args = Vec::new();
+                        if !self.eat(Token::RP) {
+                            loop {
+                                args.push(self.expr(0)?);
+                                if self.eat(Token::RP) {
+                                    break
+                                }
+                                self.expect(Token::Comma)?;
+                            }
+                        }
+                        ExprKind::Call(id, args)
+                    }
+                    false => ExprKind::Id(id),
+                }
+            }
+            _ => return self.error(),
+        };
+
+        let mut expr = Expr { kind, line };
+        loop {
+            let line = self.line();
+            if self.eat(Token::LB) {
+                let index = self.expr(0)?;
+                self.expect(Token::RB)?;
+                expr = Expr { kind: ExprKind::Index(Box::new(expr), Box::new(index)), line };
+            } else if self.eat(Token::Dot) {
+                let field = self.id()?;
+                expr = Expr { kind: ExprKind::Field(Box::new(expr), field), line };
+            } else {
+                return Ok(expr)
+            }
+        }
+    }
+}
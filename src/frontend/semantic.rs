@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+
+use crate::error::{CompileError, CompileErrorKind::SemanticError};
+use super::syntax::{Block, Def, ExtDef, Expr, ExprKind, Program, Specifier, Stmt, StructSpec, VarDec};
+
+type Result<T> = std::result::Result<T, CompileError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    // the element and the length
+    Array(Box<Type>, i32),
+    // the id of struct definition
+    Struct(usize),
+}
+
+impl Type {
+    /// the structs are equivalent by name, and the arrays by the element type and the dimensions
+    pub fn same(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Array(a, _), Type::Array(b, _)) => a.same(b),
+            _ => self == other,
+        }
+    }
+
+    fn is_basic(&self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StructType<'a> {
+    pub name: Option<&'a str>,
+    pub fields: Vec<(&'a str, Type)>,
+}
+
+#[derive(Debug)]
+pub struct FuncType {
+    pub ret: Type,
+    pub params: Vec<Type>,
+}
+
+/// the types defined by the program, it's what the translation needs besides the syntax tree
+#[derive(Debug, Default)]
+pub struct Env<'a> {
+    // indexed by the id of struct definition
+    pub structs: Vec<StructType<'a>>,
+    pub funcs: BTreeMap<&'a str, FuncType>,
+    // the names of struct, they are global even if defined in a function
+    names: BTreeMap<&'a str, usize>,
+}
+
+impl<'a> Env<'a> {
+    /// the type of a checked specifier
+    pub fn spec_type(&self, spec: &Specifier) -> Type {
+        match spec {
+            Specifier::Int => Type::Int,
+            Specifier::Float => Type::Float,
+            Specifier::Struct(StructSpec::Def { id, .. }) => Type::Struct(*id),
+            Specifier::Struct(StructSpec::Tag { name, .. }) => Type::Struct(self.names[name]),
+        }
+    }
+
+    /// `int a[2][3]` is an array of 2 arrays of 3 ints
+    pub fn var_type(&self, base: Type, var: &VarDec) -> Type {
+        var.dims.iter().rev().fold(base, |ty, n| Type::Array(Box::new(ty), *n))
+    }
+
+    /// the size in bytes
+    pub fn size(&self, ty: &Type) -> i32 {
+        match ty {
+            Type::Int | Type::Float => 4,
+            Type::Array(elem, n) => self.size(elem) * n,
+            Type::Struct(id) => self.structs[*id].fields.iter().map(|(_, ty)| self.size(ty)).sum(),
+        }
+    }
+
+    /// the offset and the type of a field
+    pub fn field(&self, id: usize, name: &str) -> Option<(i32, &Type)> {
+        let mut offset = 0;
+        for (field, ty) in &self.structs[id].fields {
+            if *field == name {
+                return Some((offset, ty))
+            }
+            offset += self.size(ty);
+        }
+        None
+    }
+}
+
+/// check the types and the names of program,
+/// `read()` and `write(x)` are the functions defined already
+pub fn check<'a>(program: &Program<'a>) -> Result<Env<'a>> {
+    let mut env = Env::default();
+    env.funcs.insert("read", FuncType { ret: Type::Int, params: vec![] });
+    env.funcs.insert("write", FuncType { ret: Type::Int, params: vec![Type::Int] });
+    let mut checker = Checker { env, scopes: vec![BTreeMap::new()], ret: Type::Int };
+
+    for def in &program.defs {
+        match def {
+            ExtDef::Vars(spec, vars) => {
+                let ty = checker.specifier(spec)?;
+                for var in vars {
+                    let var_ty = checker.env.var_type(ty.clone(), var);
+                    checker.declare(var, var_ty)?;
+                }
+            }
+            ExtDef::Type(spec) => { checker.specifier(spec)?; }
+            ExtDef::Func(func) => {
+                let ret = checker.specifier(&func.ret)?;
+                let mut params = Vec::new();
+                for (spec, var) in &func.params {
+                    let ty = checker.specifier(spec)?;
+                    params.push((var, checker.env.var_type(ty, var)));
+                }
+                if checker.env.funcs.contains_key(func.name) {
+                    return error(4, format!("redefined function '{}'", func.name), func.line)
+                }
+                // it's defined before the body to be recursive
+                let func_ty = FuncType { ret: ret.clone(), params: params.iter().map(|(_, ty)| ty.clone()).collect() };
+                checker.env.funcs.insert(func.name, func_ty);
+
+                // the parameters are in the same scope as the outermost definitions
+                checker.ret = ret;
+                checker.scopes.push(BTreeMap::new());
+                for (var, ty) in params {
+                    checker.declare(var, ty)?;
+                }
+                checker.block(&func.body)?;
+                checker.scopes.pop();
+            }
+        }
+    }
+    Ok(checker.env)
+}
+
+fn error<T>(t: u8, msg: String, line: usize) -> Result<T> {
+    CompileError::new_err(SemanticError(t), msg, line)
+}
+
+struct Checker<'a> {
+    env: Env<'a>,
+    // the variables of the nested blocks, the first one is global
+    scopes: Vec<BTreeMap<&'a str, Type>>,
+    // the return type of current function
+    ret: Type,
+}
+
+impl<'a> Checker<'a> {
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn declare(&mut self, var: &VarDec<'a>, ty: Type) -> Result<()> {
+        if self.scopes.last().unwrap().contains_key(var.name) || self.env.names.contains_key(var.name) {
+            return error(3, format!("redefined variable '{}'", var.name), var.line)
+        }
+        self.scopes.last_mut().unwrap().insert(var.name, ty);
+        Ok(())
+    }
+
+    fn specifier(&mut self, spec: &Specifier<'a>) -> Result<Type> {
+        match spec {
+            Specifier::Int => Ok(Type::Int),
+            Specifier::Float => Ok(Type::Float),
+            Specifier::Struct(StructSpec::Tag { name, line }) => match self.env.names.get(name) {
+                Some(id) => Ok(Type::Struct(*id)),
+                None => error(17, format!("undefined structure '{}'", name), *line),
+            },
+            Specifier::Struct(StructSpec::Def { name, fields, id, line }) => {
+                if let Some(name) = name {
+                    if self.env.names.contains_key(name) || self.lookup(name).is_some() {
+                        return error(16, format!("duplicated name '{}'", name), *line)
+                    }
+                }
+
+                let mut struct_ty = StructType { name: *name, fields: Vec::new() };
+                for Def { spec, decs } in fields {
+                    let ty = self.specifier(spec)?;
+                    for dec in decs {
+                        if struct_ty.fields.iter().any(|(field, _)| *field == dec.var.name) {
+                            return error(15, format!("redefined field '{}'", dec.var.name), dec.var.line)
+                        }
+                        if dec.init.is_some() {
+                            return error(15, format!("field '{}' is initialized", dec.var.name), dec.var.line)
+                        }
+                        struct_ty.fields.push((dec.var.name, self.env.var_type(ty.clone(), &dec.var)));
+                    }
+                }
+
+                if self.env.structs.len() <= *id {
+                    self.env.structs.resize_with(id + 1, Default::default);
+                }
+                self.env.structs[*id] = struct_ty;
+                if let Some(name) = name {
+                    self.env.names.insert(name, *id);
+                }
+                Ok(Type::Struct(*id))
+            }
+        }
+    }
+
+    fn defs(&mut self, defs: &[Def<'a>]) -> Result<()> {
+        for Def { spec, decs } in defs {
+            let ty = self.specifier(spec)?;
+            for dec in decs {
+                // the scope of variable starts before its initializer like C
+                let var_ty = self.env.var_type(ty.clone(), &dec.var);
+                self.declare(&dec.var, var_ty.clone())?;
+                if let Some(init) = &dec.init {
+                    if !self.expr(init)?.same(&var_ty) {
+                        return error(5, "type mismatched for assignment".into(), init.line)
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// the block shares the scope of its parent
+    fn block(&mut self, block: &Block<'a>) -> Result<()> {
+        self.defs(&block.defs)?;
+        block.stmts.iter().try_for_each(|stmt| self.stmt(stmt))
+    }
+
+    fn cond(&mut self, cond: &Expr<'a>) -> Result<()> {
+        match self.expr(cond)? {
+            Type::Int => Ok(()),
+            _ => error(7, "type mismatched for condition".into(), cond.line),
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt<'a>) -> Result<()> {
+        match stmt {
+            Stmt::Expr(expr) => { self.expr(expr)?; }
+            Stmt::Block(block) => {
+                self.scopes.push(BTreeMap::new());
+                self.block(block)?;
+                self.scopes.pop();
+            }
+            Stmt::Return(expr) => if !self.expr(expr)?.same(&self.ret) {
+                return error(8, "type mismatched for return".into(), expr.line)
+            },
+            Stmt::If(cond, then, other) => {
+                self.cond(cond)?;
+                self.stmt(then)?;
+                if let Some(other) = other {
+                    self.stmt(other)?;
+                }
+            }
+            Stmt::While(cond, body) => {
+                self.cond(cond)?;
+                self.stmt(body)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr<'a>) -> Result<Type> {
+        let line = expr.line;
+        match &expr.kind {
+            ExprKind::Int(_) => Ok(Type::Int),
+            ExprKind::Float(_) => Ok(Type::Float),
+            ExprKind::Id(id) => match self.lookup(id) {
+                Some(ty) => Ok(ty.clone()),
+                None => error(1, format!("undefined variable '{}'", id), line),
+            },
+            ExprKind::Assign(l, r) => {
+                if !matches!(l.kind, ExprKind::Id(_) | ExprKind::Index(..) | ExprKind::Field(..)) {
+                    return error(6, "the left-hand side of assignment must be a variable".into(), line)
+                }
+                let (l, r) = (self.expr(l)?, self.expr(r)?);
+                match l.same(&r) {
+                    true => Ok(l),
+                    false => error(5, "type mismatched for assignment".into(), line),
+                }
+            }
+            ExprKind::Arith(_, l, r) | ExprKind::Rel(_, l, r) => {
+                let (lt, rt) = (self.expr(l)?, self.expr(r)?);
+                if !lt.same(&rt) || !lt.is_basic() {
+                    return error(7, "type mismatched for operands".into(), line)
+                }
+                Ok(if matches!(expr.kind, ExprKind::Rel(..)) { Type::Int } else { lt })
+            }
+            ExprKind::And(l, r) | ExprKind::Or(l, r) => {
+                match (self.expr(l)?, self.expr(r)?) {
+                    (Type::Int, Type::Int) => Ok(Type::Int),
+                    _ => error(7, "type mismatched for operands".into(), line),
+                }
+            }
+            ExprKind::Neg(e) => match self.expr(e)? {
+                ty if ty.is_basic() => Ok(ty),
+                _ => error(7, "type mismatched for operands".into(), line),
+            },
+            ExprKind::Not(e) => match self.expr(e)? {
+                Type::Int => Ok(Type::Int),
+                _ => error(7, "type mismatched for operands".into(), line),
+            },
+            ExprKind::Call(name, args) => {
+                let args = args.iter().map(|arg| self.expr(arg)).collect::<Result<Vec<Type>>>()?;
+                let Some(func) = self.env.funcs.get(name) else {
+                    return match self.lookup(name) {
+                        Some(_) => error(11, format!("'{}' is not a function", name), line),
+                        None => error(2, format!("undefined function '{}'", name), line),
+                    }
+                };
+                let matched = func.params.len() == args.len()
+                    && func.params.iter().zip(&args).all(|(param, arg)| param.same(arg));
+                match matched {
+                    true => Ok(func.ret.clone()),
+                    false => error(9, format!("the arguments don't match the parameters of '{}'", name), line),
+                }
+            }
+            ExprKind::Index(array, index) => {
+                let Type::Array(elem, _) = self.expr(array)? else {
+                    return error(10, "indexing a value which isn't an array".into(), line)
+                };
+                match self.expr(index)? {
+                    Type::Int => Ok(*elem),
+                    _ => error(12, "the index of array must be an integer".into(), index.line),
+                }
+            }
+            ExprKind::Field(s, name) => {
+                let Type::Struct(id) = self.expr(s)? else {
+                    return error(13, "accessing a field of value which isn't a structure".into(), line)
+                };
+                match self.env.field(id, name) {
+                    Some((_, ty)) => Ok(ty.clone()),
+                    None => error(14, format!("undefined field '{}'", name), line),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::ast::Operator;
+
+/// the syntax tree of C--, the names are borrowed from the source
+#[derive(Debug)]
+pub struct Program<'a> {
+    pub defs: Vec<ExtDef<'a>>,
+}
+
+#[derive(Debug)]
+pub enum ExtDef<'a> {
+    // the global variables
+    Vars(Specifier<'a>, Vec<VarDec<'a>>),
+    // a specifier alone like `struct A { int x; };`
+    Type(Specifier<'a>),
+    Func(Function<'a>),
+}
+
+#[derive(Debug)]
+pub enum Specifier<'a> {
+    Int,
+    Float,
+    Struct(StructSpec<'a>),
+}
+
+#[derive(Debug)]
+pub enum StructSpec<'a> {
+    // `struct A { ... }`, the anonymous one has no name,
+    // `id` is the order of definition in the program
+    Def { name: Option<&'a str>, fields: Vec<Def<'a>>, id: usize, line: usize },
+    // `struct A` which refers to a defined one
+    Tag { name: &'a str, line: usize },
+}
+
+/// `a[2][3]`, the dimensions are in order
+#[derive(Debug)]
+pub struct VarDec<'a> {
+    pub name: &'a str,
+    pub dims: Vec<i32>,
+    pub line: usize,
+}
+
+#[derive(Debug)]
+pub struct Def<'a> {
+    pub spec: Specifier<'a>,
+    pub decs: Vec<Dec<'a>>,
+}
+
+#[derive(Debug)]
+pub struct Dec<'a> {
+    pub var: VarDec<'a>,
+    pub init: Option<Expr<'a>>,
+}
+
+#[derive(Debug)]
+pub struct Function<'a> {
+    pub ret: Specifier<'a>,
+    pub name: &'a str,
+    pub params: Vec<(Specifier<'a>, VarDec<'a>)>,
+    pub body: Block<'a>,
+    pub line: usize,
+}
+
+/// the definitions come before the statements
+#[derive(Debug)]
+pub struct Block<'a> {
+    pub defs: Vec<Def<'a>>,
+    pub stmts: Vec<Stmt<'a>>,
+}
+
+#[derive(Debug)]
+pub enum Stmt<'a> {
+    Expr(Expr<'a>),
+    Block(Block<'a>),
+    Return(Expr<'a>),
+    If(Expr<'a>, Box<Stmt<'a>>, Option<Box<Stmt<'a>>>),
+    While(Expr<'a>, Box<Stmt<'a>>),
+}
+
+#[derive(Debug)]
+pub struct Expr<'a> {
+    pub kind: ExprKind<'a>,
+    pub line: usize,
+}
+
+#[derive(Debug)]
+pub enum ExprKind<'a> {
+    Int(i32),
+    Float(f32),
+    Id(&'a str),
+    Assign(Box<Expr<'a>>, Box<Expr<'a>>),
+    // `+ - * /`
+    Arith(Operator, Box<Expr<'a>>, Box<Expr<'a>>),
+    // the relations like `<`
+    Rel(Operator, Box<Expr<'a>>, Box<Expr<'a>>),
+    And(Box<Expr<'a>>, Box<Expr<'a>>),
+    Or(Box<Expr<'a>>, Box<Expr<'a>>),
+    Neg(Box<Expr<'a>>),
+    Not(Box<Expr<'a>>),
+    Call(&'a str, Vec<Expr<'a>>),
+    Index(Box<Expr<'a>>, Box<Expr<'a>>),
+    Field(Box<Expr<'a>>, &'a str),
+}
@@ -0,0 +1,418 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    ast::{Operator, Sentence, Variable},
+    error::{CompileError, CompileErrorKind::UnsupportedError},
+    utils::names::intern,
+};
+use super::{
+    semantic::{Env, Type},
+    syntax::{Block, Def, ExtDef, Expr, ExprKind, Function, Program, Stmt, VarDec},
+};
+
+type Result<T> = std::result::Result<T, CompileError>;
+
+fn unsupported<T>(msg: &str, line: usize) -> Result<T> {
+    CompileError::new_err(UnsupportedError, msg.into(), line)
+}
+
+/// translate the checked program into IR by the schemes of the course
+///
+/// * the variables are `v1, v2, ...`, the temporaries `t1, ...` and the labels `label1, ...`
+/// * the arrays and structs are allocated by `DEC`, and passed by their address
+/// * `float` and the global variables can't be expressed by IR
+pub fn translate(program: &Program, env: &Env) -> Result<Vec<Sentence<'static>>> {
+    // the functions named like `Foo` or `_foo` aren't valid IR ids
+    let mut funcs = BTreeMap::new();
+    let defined: Vec<&str> = program.defs.iter()
+        .filter_map(|def| match def { ExtDef::Func(func) => Some(func.name), _ => None })
+        .collect();
+    for name in &defined {
+        let mut ir_name = name.to_string();
+        if !name.starts_with(|c: char| c.is_ascii_lowercase()) {
+            ir_name = format!("f_{}", name);
+            while defined.contains(&ir_name.as_str()) {
+                ir_name.push('_');
+            }
+        }
+        funcs.insert(*name, intern(ir_name));
+    }
+
+    let mut translator = Translator {
+        env, funcs, codes: Vec::new(), scopes: Vec::new(), decs: 0, vars: 0, temps: 0, labels: 0
+    };
+    for def in &program.defs {
+        match def {
+            ExtDef::Vars(_, vars) => return unsupported("global variables are not supported by IR", vars[0].line),
+            ExtDef::Type(_) => (),
+            ExtDef::Func(func) => translator.function(func)?,
+        }
+    }
+    Ok(translator.codes)
+}
+
+#[derive(Debug, Clone)]
+struct Local {
+    name: &'static str,
+    ty: Type,
+    // the variable holds the address of array or struct, it's a parameter
+    by_ref: bool,
+}
+
+struct Translator<'e, 'a> {
+    env: &'e Env<'a>,
+    funcs: BTreeMap<&'a str, &'static str>,
+    codes: Vec<Sentence<'static>>,
+    scopes: Vec<BTreeMap<&'a str, Local>>,
+    // where the next `DEC` goes, all of them are after the `PARAM`s of function
+    // so a `DEC` in a loop doesn't allocate the memory again and again
+    decs: usize,
+    vars: usize,
+    temps: usize,
+    labels: usize,
+}
+
+impl<'a> Translator<'_, 'a> {
+    fn emit(&mut self, code: Sentence<'static>) {
+        self.codes.push(code);
+    }
+
+    fn temp(&mut self) -> &'static str {
+        self.temps += 1;
+        intern(format!("t{}", self.temps))
+    }
+
+    fn label(&mut self) -> &'static str {
+        self.labels += 1;
+        intern(format!("label{}", self.labels))
+    }
+
+    fn lookup(&self, name: &str) -> &Local {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).unwrap()
+    }
+
+    fn has_float(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Int => false,
+            Type::Float => true,
+            Type::Array(elem, _) => self.has_float(elem),
+            Type::Struct(id) => self.env.structs[*id].fields.iter().any(|(_, ty)| self.has_float(ty)),
+        }
+    }
+
+    /// a new variable in the current scope
+    fn declare(&mut self, var: &VarDec<'a>, ty: Type, by_ref: bool) -> Result<&'static str> {
+        if self.has_float(&ty) {
+            return unsupported("float is not supported by IR", var.line)
+        }
+        self.vars += 1;
+        let name = intern(format!("v{}", self.vars));
+        self.scopes.last_mut().unwrap().insert(var.name, Local { name, ty, by_ref });
+        Ok(name)
+    }
+
+    fn function(&mut self, func: &Function<'a>) -> Result<()> {
+        if self.env.spec_type(&func.ret) != Type::Int {
+            return unsupported("function must return int in IR", func.line)
+        }
+        self.emit(Sentence::Func(self.funcs[func.name]));
+        self.scopes.push(BTreeMap::new());
+        for (spec, var) in &func.params {
+            let ty = self.env.var_type(self.env.spec_type(spec), var);
+            let by_ref = ty != Type::Int;
+            let name = self.declare(var, ty, by_ref)?;
+            self.emit(Sentence::Param(Variable::Id(name)));
+        }
+        self.decs = self.codes.len();
+        self.block(&func.body)?;
+        self.scopes.pop();
+
+        // running past the end of function returns 0
+        if !matches!(self.codes.last(), Some(Sentence::Return(_))) {
+            self.emit(Sentence::Return(Variable::Number(0)));
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block<'a>) -> Result<()> {
+        for Def { spec, decs } in &block.defs {
+            let base = self.env.spec_type(spec);
+            for dec in decs {
+                let ty = self.env.var_type(base.clone(), &dec.var);
+                let name = self.declare(&dec.var, ty.clone(), false)?;
+                if ty != Type::Int {
+                    let size = self.env.size(&ty);
+                    self.codes.insert(self.decs, Sentence::Dec { target: Variable::Id(name), size });
+                    self.decs += 1;
+                }
+                if let Some(init) = &dec.init {
+                    let target = Expr { kind: ExprKind::Id(dec.var.name), line: dec.var.line };
+                    self.assign(&target, init)?;
+                }
+            }
+        }
+        block.stmts.iter().try_for_each(|stmt| self.stmt(stmt))
+    }
+
+    fn stmt(&mut self, stmt: &Stmt<'a>) -> Result<()> {
+        match stmt {
+            Stmt::Expr(expr) => { self.expr(expr)?; }
+            Stmt::Block(block) => {
+                self.scopes.push(BTreeMap::new());
+                self.block(block)?;
+                self.scopes.pop();
+            }
+            Stmt::Return(expr) => {
+                let value = self.expr(expr)?;
+                self.emit(Sentence::Return(value));
+            }
+            Stmt::If(cond, then, None) => {
+                let (l1, l2) = (self.label(), self.label());
+                self.cond(cond, l1, l2)?;
+                self.emit(Sentence::Label(l1));
+                self.stmt(then)?;
+                self.emit(Sentence::Label(l2));
+            }
+            Stmt::If(cond, then, Some(other)) => {
+                let (l1, l2, l3) = (self.label(), self.label(), self.label());
+                self.cond(cond, l1, l2)?;
+                self.emit(Sentence::Label(l1));
+                self.stmt(then)?;
+                self.emit(Sentence::Goto(l3));
+                self.emit(Sentence::Label(l2));
+                self.stmt(other)?;
+                self.emit(Sentence::Label(l3));
+            }
+            Stmt::While(cond, body) => {
+                let (l1, l2, l3) = (self.label(), self.label(), self.label());
+                self.emit(Sentence::Label(l1));
+                self.cond(cond, l2, l3)?;
+                self.emit(Sentence::Label(l2));
+                self.stmt(body)?;
+                self.emit(Sentence::Goto(l1));
+                self.emit(Sentence::Label(l3));
+            }
+        }
+        Ok(())
+    }
+
+    /// the type of a checked expression
+    fn type_of(&self, expr: &Expr<'a>) -> Type {
+        match &expr.kind {
+            ExprKind::Int(_) | ExprKind::Rel(..) | ExprKind::And(..) | ExprKind::Or(..) | ExprKind::Not(_) => Type::Int,
+            ExprKind::Float(_) => Type::Float,
+            ExprKind::Id(id) => self.lookup(id).ty.clone(),
+            ExprKind::Assign(e, _) | ExprKind::Arith(_, e, _) | ExprKind::Neg(e) => self.type_of(e),
+            ExprKind::Call(name, _) => self.env.funcs[name].ret.clone(),
+            ExprKind::Index(array, _) => match self.type_of(array) {
+                Type::Array(elem, _) => *elem,
+                _ => unreachable!()
+            },
+            ExprKind::Field(s, name) => match self.type_of(s) {
+                Type::Struct(id) => self.env.field(id, name).unwrap().1.clone(),
+                _ => unreachable!()
+            },
+        }
+    }
+
+    /// `*addr` as a right or left value, the address must be in a variable
+    fn deref(&mut self, addr: Variable<'static>) -> Variable<'static> {
+        match addr {
+            Variable::Id(id) => Variable::Deref(id),
+            _ => {
+                let t = self.temp();
+                self.emit(Sentence::Assign { target: Variable::Id(t), var: addr });
+                Variable::Deref(t)
+            }
+        }
+    }
+
+    /// `base + offset`, nothing is emitted for the zero offset
+    fn offset(&mut self, base: Variable<'static>, offset: i32) -> Variable<'static> {
+        if offset == 0 {
+            return base
+        }
+        let t = self.temp();
+        self.emit(Sentence::Arith { l: base, r: Variable::Number(offset), opt: Operator::Plus, target: Variable::Id(t) });
+        Variable::Id(t)
+    }
+
+    /// the address of an array, a struct, or an element of them
+    fn address(&mut self, expr: &Expr<'a>) -> Result<Variable<'static>> {
+        match &expr.kind {
+            ExprKind::Id(id) => {
+                let local = self.lookup(id);
+                Ok(match local.by_ref {
+                    true => Variable::Id(local.name),
+                    false => Variable::Pointer(local.name),
+                })
+            }
+            ExprKind::Index(array, index) => {
+                let size = self.env.size(&self.type_of(expr));
+                let base = self.address(array)?;
+                match self.expr(index)? {
+                    Variable::Number(n) => Ok(self.offset(base, n.wrapping_mul(size))),
+                    index => {
+                        let (t1, t2) = (self.temp(), self.temp());
+                        self.emit(Sentence::Arith { l: index, r: Variable::Number(size), opt: Operator::Mul, target: Variable::Id(t1) });
+                        self.emit(Sentence::Arith { l: base, r: Variable::Id(t1), opt: Operator::Plus, target: Variable::Id(t2) });
+                        Ok(Variable::Id(t2))
+                    }
+                }
+            }
+            ExprKind::Field(s, name) => {
+                let Type::Struct(id) = self.type_of(s) else { unreachable!() };
+                let (offset, _) = self.env.field(id, name).unwrap();
+                let base = self.address(s)?;
+                Ok(self.offset(base, offset))
+            }
+            ExprKind::Assign(l, r) => {
+                self.assign(l, r)?;
+                self.address(l)
+            }
+            _ => unsupported("the value can't be addressed", expr.line),
+        }
+    }
+
+    /// `l = r`, the arrays and structs are copied word by word as long as both have
+    fn assign(&mut self, l: &Expr<'a>, r: &Expr<'a>) -> Result<Variable<'static>> {
+        let ty = self.type_of(l);
+        if ty != Type::Int {
+            let size = self.env.size(&ty).min(self.env.size(&self.type_of(r)));
+            let (dst, src) = (self.address(l)?, self.address(r)?);
+            for offset in (0..size).step_by(4) {
+                let (from, to) = (self.offset(src.clone(), offset), self.offset(dst.clone(), offset));
+                let (from, to) = (self.deref(from), self.deref(to));
+                self.emit(Sentence::Assign { target: to, var: from });
+            }
+            return Ok(dst)
+        }
+
+        let value = self.expr(r)?;
+        let target = match &l.kind {
+            ExprKind::Id(id) => Variable::Id(self.lookup(id).name),
+            _ => {
+                let addr = self.address(l)?;
+                self.deref(addr)
+            }
+        };
+
+        // `t1 := a + b; v1 := t1` becomes `v1 := a + b`
+        if let (Variable::Id(t), Variable::Id(_)) = (&value, &target) {
+            let last = self.codes.last_mut().filter(|code| code.def() == Some(*t) && t.starts_with('t'));
+            if let Some(Sentence::Assign { target: def, .. } | Sentence::Arith { target: def, .. }
+                | Sentence::Call { target: def, .. } | Sentence::Read(def)) = last
+            {
+                *def = target.clone();
+                return Ok(target)
+            }
+        }
+        self.emit(Sentence::Assign { target: target.clone(), var: value });
+        Ok(target)
+    }
+
+    /// the value of expression, it's the address for an array or a struct
+    fn expr(&mut self, expr: &Expr<'a>) -> Result<Variable<'static>> {
+        match &expr.kind {
+            ExprKind::Int(n) => Ok(Variable::Number(*n)),
+            ExprKind::Float(_) => unsupported("float is not supported by IR", expr.line),
+            ExprKind::Id(id) => {
+                let local = self.lookup(id);
+                match local.ty {
+                    Type::Int => Ok(Variable::Id(local.name)),
+                    _ => self.address(expr),
+                }
+            }
+            ExprKind::Assign(l, r) => self.assign(l, r),
+            ExprKind::Arith(opt, l, r) => {
+                let (l, r) = (self.expr(l)?, self.expr(r)?);
+                if let (Variable::Number(a), Variable::Number(b)) = (&l, &r) {
+                    if let Some(n) = opt.try_calculate(*a, *b) {
+                        return Ok(Variable::Number(n))
+                    }
+                }
+                let t = self.temp();
+                self.emit(Sentence::Arith { l, r, opt: *opt, target: Variable::Id(t) });
+                Ok(Variable::Id(t))
+            }
+            ExprKind::Neg(e) => match self.expr(e)? {
+                Variable::Number(n) => Ok(Variable::Number(n.wrapping_neg())),
+                value => {
+                    let t = self.temp();
+                    self.emit(Sentence::Arith { l: Variable::Number(0), r: value, opt: Operator::Sub, target: Variable::Id(t) });
+                    Ok(Variable::Id(t))
+                }
+            },
+            ExprKind::Rel(..) | ExprKind::And(..) | ExprKind::Or(..) | ExprKind::Not(_) => {
+                let (t, l1, l2) = (self.temp(), self.label(), self.label());
+                self.emit(Sentence::Assign { target: Variable::Id(t), var: Variable::Number(0) });
+                self.cond(expr, l1, l2)?;
+                self.emit(Sentence::Label(l1));
+                self.emit(Sentence::Assign { target: Variable::Id(t), var: Variable::Number(1) });
+                self.emit(Sentence::Label(l2));
+                Ok(Variable::Id(t))
+            }
+            ExprKind::Call(name, args) => match (*name, self.funcs.get(name)) {
+                ("read", None) => {
+                    let t = self.temp();
+                    self.emit(Sentence::Read(Variable::Id(t)));
+                    Ok(Variable::Id(t))
+                }
+                ("write", None) => {
+                    let value = self.expr(&args[0])?;
+                    self.emit(Sentence::Write(value));
+                    Ok(Variable::Number(0))
+                }
+                (_, Some(func)) => {
+                    let func = *func;
+                    // the arguments are evaluated before any `ARG`, the last one is pushed first
+                    let values = args.iter().map(|arg| self.expr(arg)).collect::<Result<Vec<_>>>()?;
+                    for value in values.into_iter().rev() {
+                        self.emit(Sentence::Arg(value));
+                    }
+                    let t = self.temp();
+                    self.emit(Sentence::Call { target: Variable::Id(t), func });
+                    Ok(Variable::Id(t))
+                }
+                _ => unreachable!()
+            },
+            ExprKind::Index(..) | ExprKind::Field(..) => {
+                let addr = self.address(expr)?;
+                match self.type_of(expr) {
+                    Type::Int => Ok(self.deref(addr)),
+                    _ => Ok(addr),
+                }
+            }
+        }
+    }
+
+    /// jump to `t` if the condition holds, otherwise to `f`
+    fn cond(&mut self, expr: &Expr<'a>, t: &'static str, f: &'static str) -> Result<()> {
+        match &expr.kind {
+            ExprKind::Rel(opt, l, r) => {
+                let (l, r) = (self.expr(l)?, self.expr(r)?);
+                self.emit(Sentence::IfGoto { l, r, opt: *opt, label: t });
+                self.emit(Sentence::Goto(f));
+            }
+            ExprKind::Not(e) => self.cond(e, f, t)?,
+            ExprKind::And(l, r) => {
+                let label = self.label();
+                self.cond(l, label, f)?;
+                self.emit(Sentence::Label(label));
+                self.cond(r, t, f)?;
+            }
+            ExprKind::Or(l, r) => {
+                let label = self.label();
+                self.cond(l, t, label)?;
+                self.emit(Sentence::Label(label));
+                self.cond(r, t, f)?;
+            }
+            _ => {
+                let value = self.expr(expr)?;
+                self.emit(Sentence::IfGoto { l: value, r: Variable::Number(0), opt: Operator::NotEqual, label: t });
+                self.emit(Sentence::Goto(f));
+            }
+        }
+        Ok(())
+    }
+}
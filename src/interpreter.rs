@@ -5,17 +5,20 @@ use std::str::FromStr;
 use lalrpop_util::lalrpop_mod;
 
 use crate::{
-    ast::{Sentence, Variable},
+    analysis::{Warning, uninitialized_reads},
+    ast::{Operator, Sentence, Variable},
     computer::Computer,
     error::{
         InterpreterError as IError, 
         InterpreterErrorKind::*,
         RuntimeError as RError,
-        RuntimeErrorKind::*
+        RuntimeErrorKind::*,
+        EvalError,
     },
+    utils::io::{ReadFunc, WriteFunc},
 };
 
-lalrpop_mod!(pub parser);
+lalrpop_mod!(#[allow(clippy::all, unused)] pub parser);
 
 struct Symbol {
     addr: i32,
@@ -45,14 +48,37 @@ impl<'a> Call<'a> {
 }
 
 
+/// parse the source into sentences with their line numbers (starting from 1)
+/// 
+/// empty lines and comments are skipped
+pub fn parse_lines<'a>(lines: &[&'a str]) -> Result<(Vec<Sentence<'a>>, Vec<usize>), IError<'a>> {
+    let line_parser = parser::LineParser::new();
+
+    let (mut codes, mut source_lines) = (Vec::with_capacity(lines.len()), Vec::new());
+    for (i, line) in lines.iter().enumerate() {
+        match line_parser.parse(line) {
+            Ok(Some(code)) => {
+                codes.push(code);
+                source_lines.push(i + 1);
+            },
+            Ok(None) => (),
+            Err(err) => IError::new_err(ParseError(err), i + 1)?
+        };
+    }
+
+    Ok((codes, source_lines))
+}
+
 // in the origin project
 // all the symbol are recorded in a same table
 // so the dictionary of function must be recorded
 pub struct Interpreter<'a> {
-    codes: Vec<Sentence<'a>>,
+    codes: RefCell<Vec<Sentence<'a>>>,
+    // the line number in the source file of every sentence
+    source_lines: Vec<usize>,
 
-    read: Box<dyn Fn() -> String>,
-    write: Box<dyn Fn(String) -> ()>,
+    read: ReadFunc,
+    write: WriteFunc,
     
     // resident status
     entrance_ip: RefCell<Option<usize>>,
@@ -62,6 +88,11 @@ pub struct Interpreter<'a> {
     // temporary
     ip: RefCell<usize>,
     count: RefCell<usize>,
+    limit: RefCell<Option<usize>>,
+    // the running count of every sentence
+    profile: RefCell<Vec<usize>>,
+    // the value returned by main
+    return_value: RefCell<Option<i32>>,
     symbol_table_stack: RefCell<Vec<BTreeMap<&'a str, Symbol>>>,
     call_stack: RefCell<Vec<Call<'a>>>,
     argument_stack: RefCell<Vec<i32>>,
@@ -73,24 +104,45 @@ pub struct Interpreter<'a> {
 impl <'a>Interpreter<'a> {
     pub fn from_lines(
         lines: &Vec<&'a str>,
-        read: Box<dyn Fn() -> String>,
-        write: Box<dyn Fn(String) -> ()>) 
+        read: ReadFunc,
+        write: WriteFunc) 
         -> Result<Interpreter<'a>, IError<'a>> 
     {
-        let sent_parser = parser::SentenceParser::new();
+        let (codes, source_lines) = parse_lines(lines)?;
+        
+        let mut interpreter = Self::new(codes, read, write);
+        interpreter.source_lines = source_lines;
+        interpreter.check()
+            .map_err(|err| { let line = interpreter.source_line(err.line()); err.at(line) })?;
 
-        let mut codes = Vec::with_capacity(lines.len());
-        for (i, line) in lines.iter().enumerate() {
-            let code = match sent_parser.parse(line) {
-                Ok(code) => code,
-                Err(err) => IError::new_err::<Sentence>(ParseError(err), i)?
-            };
+        Ok(interpreter)
+    }   
 
-            codes.push(code);
-        }
-        
-        let interpreter = Self { 
-            codes: codes,
+    /// the codes may come from a pass, so the line of error is the index of sentence plus 1
+    pub fn from_codes(
+        codes: Vec<Sentence<'a>>,
+        read: ReadFunc,
+        write: WriteFunc) 
+        -> Result<Interpreter<'a>, IError<'a>> 
+    {
+        let interpreter = Self::new(codes, read, write);
+        interpreter.check().map_err(|err| { let line = err.line() + 1; err.at(line) })?;
+
+        Ok(interpreter)
+    }
+
+    /// an interpreter without any function, the sentences can be evaluated one by one
+    pub fn empty(read: ReadFunc, write: WriteFunc) -> Interpreter<'a> {
+        let interpreter = Self::new(Vec::new(), read, write);
+        interpreter.symbol_table_stack.borrow_mut().push(BTreeMap::new());
+        interpreter
+    }
+
+    fn new(codes: Vec<Sentence<'a>>, read: ReadFunc, write: WriteFunc) -> Interpreter<'a> {
+        Self { 
+            profile: RefCell::new(vec![0; codes.len()]),
+            codes: RefCell::new(codes),
+            source_lines: Vec::new(),
             label_table: RefCell::new(BTreeMap::new()),
             func_table: RefCell::new(BTreeMap::new()),
             
@@ -101,17 +153,32 @@ impl <'a>Interpreter<'a> {
             argument_stack: RefCell::new(Vec::new()),
 
             count: RefCell::new(0),
+            limit: RefCell::new(None),
+            return_value: RefCell::new(None),
             computer: RefCell::new(Computer::new()),
             ip: RefCell::new(0),
             entrance_ip: RefCell::new(None)
-        };
-        interpreter.check()?;
-
-        Ok(interpreter)
-    }   
+        }
+    }
 
     // it is difficult to decouple checking and loading
     fn check(& self) -> Result<(), IError<'a>> {
+        self.check_codes(0)?;
+
+        // main function not found
+        match self.entrance_ip.borrow().as_ref() {
+            None => IError::new_err(IRSyntaxError, self.codes.borrow().len())?,
+            Some(i) => {
+                *self.ip.borrow_mut() = *i;
+                self.symbol_table_stack.borrow_mut().push(BTreeMap::new());
+            },
+        };
+
+        Ok(())
+    }
+
+    /// check the codes from `start` to the end
+    fn check_codes(&self, start: usize) -> Result<(), IError<'a>> {
         let mut cur_func: Option<&str> = None;
         // the symbol_table is different with the one in interpreter
         // it's used to check variable duplicated and undefined
@@ -120,7 +187,8 @@ impl <'a>Interpreter<'a> {
         let (mut goto_labels, mut call_funcs) = (Vec::new(), Vec::new());
         
         // check label and variable
-        for (i, code) in self.codes.iter().enumerate() {
+        let codes = self.codes.borrow();
+        for (i, code) in codes.iter().enumerate().skip(start) {
             // 1. check label
             if let Some(flag) = self.check_label(code, i, &mut cur_func)? {
                 if flag {
@@ -149,18 +217,103 @@ impl <'a>Interpreter<'a> {
             }
         }
 
-        // main function not found
-        match self.entrance_ip.borrow().as_ref() {
-            None => IError::new_err(IRSyntaxError, self.codes.len())?,
-            Some(i) => {
-                *self.ip.borrow_mut() = *i;
-                self.symbol_table_stack.borrow_mut().push(BTreeMap::new());
-            },
-        };
-
         Ok(())
     }
 
+    /// append a function to the program, the codes must start with `FUNCTION`
+    /// and end with `RETURN` or `GOTO`, or the next function is fallen into
+    /// 
+    /// nothing is changed if the function can't pass checking
+    pub fn define(&self, codes: Vec<Sentence<'a>>) -> Result<(), IError<'a>> {
+        let start = self.codes.borrow().len();
+        if !matches!(codes.first(), Some(Sentence::Func(_))) {
+            IError::new_err(CurrentFuncNoneError, start)?
+        }
+        if !matches!(codes.last(), Some(Sentence::Return(_) | Sentence::Goto(_))) {
+            IError::new_err(FuncEndError, start + codes.len() - 1)?
+        }
+
+        self.profile.borrow_mut().resize(start + codes.len(), 0);
+        self.codes.borrow_mut().extend(codes);
+
+        self.check_codes(start).inspect_err(|_| {
+            self.codes.borrow_mut().truncate(start);
+            self.profile.borrow_mut().truncate(start);
+            self.label_table.borrow_mut().retain(|_, i| *i < start);
+            self.func_table.borrow_mut().retain(|_, i| *i < start);
+        })
+    }
+
+    /// check and execute a sentence outside of any function
+    /// 
+    /// `symbol_table` records the variables which have been defined before
+    pub fn eval(
+        &self, 
+        code: Sentence<'a>, 
+        symbol_table: &mut BTreeSet<&'a str>) -> Result<(), EvalError<'a>> 
+    {
+        let i = self.codes.borrow().len();
+        match code {
+            Sentence::Label(_) | Sentence::Func(_) | Sentence::Goto(_) 
+                | Sentence::IfGoto { .. } | Sentence::Return(_) | Sentence::Param(_)
+                => IError::new_err(CurrentFuncNoneError, i)?,
+            _ => ()
+        }
+
+        let (mut goto_labels, mut call_funcs) = (Vec::new(), Vec::new());
+        let mut symbols = symbol_table.clone();
+        self.check_var(&code, i, &mut symbols, &mut goto_labels, &mut call_funcs)?;
+        for (item, i) in call_funcs {
+            if self.func_table.borrow().get(item).is_none() {
+                IError::new_err(UndefinedFuncError, i)?
+            }
+        }
+        *symbol_table = symbols;
+
+        self.codes.borrow_mut().push(code);
+        self.profile.borrow_mut().push(0);
+        *self.ip.borrow_mut() = i;
+
+        // a call is over when the call stack is empty again
+        let result = self.execute().and_then(|_| {
+            while !self.call_stack.borrow().is_empty() {
+                self.execute()?;
+            }
+            Ok(())
+        });
+
+        // go back to the top level if the call is broken
+        if result.is_err() {
+            let depth = self.call_stack.borrow().len();
+            for _ in 0..depth {
+                self.computer.borrow_mut().pop();
+            }
+            self.call_stack.borrow_mut().clear();
+            self.symbol_table_stack.borrow_mut().truncate(1);
+            self.argument_stack.borrow_mut().clear();
+        }
+
+        Ok(result?)
+    }
+
+    /// the variables of the current function with their values
+    pub fn variables(&self) -> Vec<(&'a str, Vec<i32>)> {
+        let binding = self.symbol_table_stack.borrow();
+        let computer = self.computer.borrow();
+        binding.last().unwrap().iter().map(|(id, symbol)| {
+            let values = if symbol.is_array {
+                (0..symbol.size).step_by(4).map(|offset| computer.load(symbol.addr + offset)).collect()
+            } else {
+                vec![computer.load(symbol.addr)]
+            };
+            (*id, values)
+        }).collect()
+    }
+
+    pub fn functions(&self) -> Vec<&'a str> {
+        self.func_table.borrow().keys().copied().collect()
+    }
+
     #[inline]
     fn check_var(
         &self,
@@ -198,14 +351,13 @@ impl <'a>Interpreter<'a> {
 
             // check right value
             match code {
-                Sentence::Assign { var, .. } => if check_var_not_exist(var, &symbol_table) {
-                    IError::new_err(UndefinedVariableError, i)?
-                },
-                Sentence::Arith { l, r, .. } => if check_var_not_exist(l, &symbol_table) {
-                    IError::new_err(UndefinedVariableError, i)?
-                } else if check_var_not_exist(r, &symbol_table) {
+                Sentence::Assign { var, .. } => if check_var_not_exist(var, symbol_table) {
                     IError::new_err(UndefinedVariableError, i)?
                 },
+                Sentence::Arith { l, r, .. } => 
+                    if check_var_not_exist(l, symbol_table) || check_var_not_exist(r, symbol_table) {
+                        IError::new_err(UndefinedVariableError, i)?
+                    },
                 Sentence::Call { func, ..} => call_funcs.push((*func, i)),
                 _ => unreachable!()
             };
@@ -228,7 +380,7 @@ impl <'a>Interpreter<'a> {
                 | Sentence::Arg(var) 
                 | Sentence::Return(var) =>  
             {
-                if check_var_not_exist(var, &symbol_table) {
+                if check_var_not_exist(var, symbol_table) {
                     IError::new_err(UndefinedVariableError, i)?
                 }
             }
@@ -250,7 +402,7 @@ impl <'a>Interpreter<'a> {
 
             },
             Sentence::IfGoto { label, l, r,  .. } => {
-                if check_var_not_exist(l, &symbol_table) || check_var_not_exist(r, &symbol_table) {
+                if check_var_not_exist(l, symbol_table) || check_var_not_exist(r, symbol_table) {
                     IError::new_err(UndefinedVariableError, i)?
                 }
                 goto_labels.push((*label, i))
@@ -290,7 +442,7 @@ impl <'a>Interpreter<'a> {
                 self.entrance_ip.borrow_mut().get_or_insert(i);
             }
             // record function name and line no
-            func_table.insert(&label, i);
+            func_table.insert(label, i);
             // modify the current function
             cur_func.get_or_insert(label);
 
@@ -302,18 +454,44 @@ impl <'a>Interpreter<'a> {
     
     // when program is over, it will return the running count
     pub fn execute(&self) -> Result<Option<usize>, RError> {
+        self.step()
+            .map_err(|err| { let line = self.source_line(err.line()); err.at(line) })
+    }
+
+    /// the line number in the source file of the i-th sentence, it starts from 1
+    pub fn source_line(&self, i: usize) -> usize {
+        match self.source_lines.get(i) {
+            Some(line) => *line,
+            // the sentences evaluated in repl or the end of the file
+            None => self.source_lines.last().copied().unwrap_or(0) + i + 1 - self.source_lines.len(),
+        }
+    }
+
+    fn step(&self) -> Result<Option<usize>, RError> {
         let ip = *self.ip.borrow();
-        let code = match self.codes.get(ip) {
+        let codes = self.codes.borrow();
+        let code = match codes.get(ip) {
             Some(c) => c,
-            None => {println!("{}", ip); todo!()}
+            // the last function runs past the end of file
+            None => return RError::new_err(NoReturnError, ip - 1)
         };
 
         // increment count
         self.count.borrow_mut().add_assign(1);
+        self.profile.borrow_mut()[ip].add_assign(1);
+        if let Some(limit) = *self.limit.borrow() {
+            if *self.count.borrow() > limit {
+                return RError::new_err(StepLimitError, ip)
+            }
+        }
 
         match code {
             Sentence::Read(var) => {
-                let input = match i32::from_str((*self.read)().as_str().trim()) {
+                let input = match (*self.read)() {
+                    Some(input) => input,
+                    None => return RError::new_err::<Option<usize>>(InputExhaustedError, ip)
+                };
+                let input = match i32::from_str(input.trim()) {
                     Ok(i) => i,
                     Err(_) => return RError::new_err::<Option<usize>>(InputError, ip)
                 };
@@ -341,7 +519,7 @@ impl <'a>Interpreter<'a> {
                         // it must to register the id in the symbol label
                         let mut binding = self.symbol_table_stack.borrow_mut();
                         let symbol_table = binding.last_mut().unwrap();
-                        symbol_table.insert(id, Symbol::new_array(addr, *size));
+                        symbol_table.insert(id, Symbol::new_array(addr, *size / 4));
                     }
     
                     self.assign_number(target, addr)
@@ -349,12 +527,16 @@ impl <'a>Interpreter<'a> {
             }
             Sentence::Assign { target, var } => self.assign(target, var),
             Sentence::Arith { l, r, opt, target } => {
-                let result = opt.calculate(self.get_var(l), self.get_var(r));
-                self.assign_number(target, result)
+                let (l, r) = (self.get_var(l), self.get_var(r));
+                if *opt == Operator::Div && r == 0 {
+                    return RError::new_err(DivideByZeroError, ip)
+                }
+                self.assign_number(target, opt.calculate(l, r))
             },
             Sentence::Return(var) => {
                 // 1. if the stack is empty, the program over
                 if self.symbol_table_stack.borrow().len() == 1 {
+                    *self.return_value.borrow_mut() = Some(self.get_var(var));
                     return Ok(Some(*self.count.borrow()))
                 }    
 
@@ -387,16 +569,49 @@ impl <'a>Interpreter<'a> {
                 self.argument_stack.borrow_mut().push(self.get_var(var));
             },
             Sentence::Param(var) => {
-                let value = self.argument_stack.borrow_mut().pop().unwrap();
+                let Some(value) = self.argument_stack.borrow_mut().pop() else {
+                    return RError::new_err(ArgumentError, ip)
+                };
                 self.assign_number(var, value);
             }
             _ => ()
         };
+        if self.computer.borrow().fault() {
+            return RError::new_err(MemoryError, ip)
+        }
 
         self.ip.borrow_mut().add_assign(1);
         Ok(None)
     }
 
+    /// execute until the program is over and return the running count
+    pub fn run(&self) -> Result<usize, RError> {
+        loop {
+            if let Some(count) = self.execute()? {
+                break Ok(count)
+            }
+        }
+    }
+
+    /// the running count of every sentence with its line number
+    pub fn profile(&self) -> Vec<(usize, usize)> {
+        self.profile.borrow().iter().enumerate()
+            .map(|(i, count)| (self.source_line(i), *count))
+            .collect()
+    }
+
+    /// the warnings found by analysing the program, with the line of sentence
+    pub fn warnings(&self) -> Vec<(usize, Warning)> {
+        uninitialized_reads(&self.codes.borrow()).into_iter()
+            .map(|warning| (self.source_line(warning.i), warning))
+            .collect()
+    }
+
+    /// the value returned by main, it's `None` before the program is over
+    pub fn return_value(&self) -> Option<i32> {
+        *self.return_value.borrow()
+    }
+
     fn goto(&self, label: &str) {
         let binding = self.label_table.borrow();
         let new_ip = binding.get(label).unwrap();
@@ -469,12 +684,19 @@ impl <'a>Interpreter<'a> {
     fn get_addr(&self, id: &str) -> Option<i32> {
         let binding = self.symbol_table_stack.borrow();
         let symbol_table = binding.last().unwrap();
-        symbol_table.get(id).and_then(|symbol| Some(symbol.addr))
+        symbol_table.get(id).map(|symbol| symbol.addr)
+    }
+
+    /// stop the program with an error once it runs more than `limit` sentences
+    pub fn set_limit(&self, limit: Option<usize>) {
+        *self.limit.borrow_mut() = limit;
     }
 
     pub fn clear(&self) {
         *self.ip.borrow_mut() = self.entrance_ip.borrow().unwrap();  
         *self.count.borrow_mut() = 0;
+        *self.return_value.borrow_mut() = None;
+        self.profile.borrow_mut().iter_mut().for_each(|c| *c = 0);
 
         self.symbol_table_stack.borrow_mut().clear();
         self.symbol_table_stack.borrow_mut().push(BTreeMap::new());
@@ -1,16 +1,50 @@
 pub mod ast; 
 pub mod interpreter;
 pub mod error;
+#[allow(clippy::bind_instead_of_map, clippy::needless_question_mark)]
 pub mod debugger;
+pub mod cfg;
+pub mod analysis;
+pub mod lint;
+pub mod format;
+pub mod opt;
+pub mod diff;
+pub mod frontend;
+#[allow(clippy::unnecessary_cast)]
 mod computer;
 
+pub mod backend {
+    pub mod c;
+    pub mod llvm;
+    pub mod mips;
+    pub mod sim;
+    pub mod wasm;
+}
+
 pub mod utils {
     pub mod io;
+    pub mod json;
+    pub mod names;
+    pub mod rng;
 }
 
 #[cfg(test)]
 mod test {
+    #[allow(clippy::useless_vec, clippy::unused_enumerate_index, mismatched_lifetime_syntaxes)]
     mod lexer;
+    #[allow(clippy::useless_vec, mismatched_lifetime_syntaxes)]
     mod parser;
+    mod interpreter;
+    mod opt;
+    mod cfg;
+    mod analysis;
+    mod lint;
+    mod format;
+    mod roundtrip;
+    mod backend;
+    mod frontend;
+    mod diff;
+    mod json;
+    mod utils;
 }
 
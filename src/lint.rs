@@ -0,0 +1,175 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    analysis::{Warning, UNINITIALIZED, uninitialized_reads},
+    ast::{Sentence, Variable},
+    cfg::{Cfg, build},
+};
+
+pub const NO_RETURN: &str = "W001";
+pub const UNUSED_LABEL: &str = "W002";
+pub const REDUNDANT_GOTO: &str = "W003";
+pub const UNUSED_VARIABLE: &str = "W004";
+pub const UNUSED_ARRAY: &str = "W005";
+pub const DANGLING_ARG: &str = "W006";
+pub const UNREACHABLE_CODE: &str = "W007";
+pub const OVERWRITTEN_READ: &str = "W008";
+
+/// the code, name and description of every lint,
+/// a lint can be suppressed by either its code or name
+pub const LINTS: &[(&str, &str, &str)] = &[
+    (NO_RETURN, "no-return", "function without a reachable RETURN"),
+    (UNUSED_LABEL, "unused-label", "label which is never the target of GOTO"),
+    (REDUNDANT_GOTO, "redundant-goto", "GOTO to the label just after it"),
+    (UNUSED_VARIABLE, "unused-variable", "variable assigned but never read"),
+    (UNUSED_ARRAY, "unused-array", "array declared by DEC but never used"),
+    (DANGLING_ARG, "dangling-arg", "ARG which isn't followed by CALL"),
+    (UNREACHABLE_CODE, "unreachable-code", "code after GOTO or RETURN before the next label"),
+    (OVERWRITTEN_READ, "overwritten-read", "the value of READ is overwritten before it's used"),
+    (UNINITIALIZED, "uninitialized", "variable may be read before it's assigned"),
+];
+
+/// find the code of a lint by its code or name
+pub fn find_code(lint: &str) -> Option<&'static str> {
+    LINTS.iter()
+        .find(|(code, name, _)| code.eq_ignore_ascii_case(lint) || *name == lint)
+        .map(|(code, _, _)| *code)
+}
+
+/// check the suspicious patterns, the warnings whose code is in `allowed` are dropped
+pub fn lint(codes: &[Sentence], allowed: &[&str]) -> Vec<Warning> {
+    let mut warnings = uninitialized_reads(codes);
+
+    for cfg in build(codes) {
+        no_return(codes, &cfg, &mut warnings);
+        unused_variables(codes, &cfg, &mut warnings);
+    }
+    unused_labels(codes, &mut warnings);
+    sequences(codes, &mut warnings);
+
+    warnings.retain(|w| !allowed.contains(&w.code));
+    warnings.sort_by_key(|w| w.i);
+    warnings
+}
+
+fn no_return(codes: &[Sentence], cfg: &Cfg, warnings: &mut Vec<Warning>) {
+    let reachable = cfg.reachable();
+    let blocks = cfg.blocks.iter().zip(reachable).filter(|(_, r)| *r).map(|(b, _)| b);
+
+    let mut has_return = false;
+    for block in blocks {
+        match &codes[block.end - 1] {
+            Sentence::Return(_) => has_return = true,
+            Sentence::Goto(_) => (),
+            // the last block runs past the end of function
+            _ if block.succs.is_empty() => warnings.push(Warning::new(
+                block.end - 1, NO_RETURN, format!("function `{}` may run past its end", cfg.name)
+            )),
+            _ => ()
+        }
+    }
+    if !has_return {
+        warnings.push(Warning::new(
+            cfg.start, NO_RETURN, format!("function `{}` has no reachable RETURN", cfg.name)
+        ));
+    }
+}
+
+fn unused_variables(codes: &[Sentence], cfg: &Cfg, warnings: &mut Vec<Warning>) {
+    let body = &codes[cfg.start..cfg.end];
+    // an id is used if its value or address is read
+    let used: BTreeSet<&str> = body.iter()
+        .flat_map(|code| {
+            let mut ids = code.uses();
+            ids.extend(code.operands().into_iter().filter_map(|var| match var {
+                Variable::Pointer(id) => Some(*id),
+                _ => None
+            }));
+            ids
+        })
+        .collect();
+
+    let mut reported = BTreeSet::new();
+    for (i, code) in body.iter().enumerate() {
+        let Some(id) = code.def() else { continue };
+        if used.contains(id) || !reported.insert(id) {
+            continue
+        }
+        let (code, msg) = match code {
+            Sentence::Dec { .. } => (UNUSED_ARRAY, format!("array `{}` is never used", id)),
+            _ => (UNUSED_VARIABLE, format!("`{}` is assigned but never read", id)),
+        };
+        warnings.push(Warning::new(cfg.start + i, code, msg));
+    }
+}
+
+fn unused_labels(codes: &[Sentence], warnings: &mut Vec<Warning>) {
+    let targets: BTreeSet<&str> = codes.iter()
+        .filter_map(|code| match code {
+            Sentence::Goto(label) | Sentence::IfGoto { label, .. } => Some(*label),
+            _ => None
+        })
+        .collect();
+
+    for (i, code) in codes.iter().enumerate() {
+        if let Sentence::Label(label) = code {
+            if !targets.contains(label) {
+                warnings.push(Warning::new(i, UNUSED_LABEL, format!("label `{}` is never used", label)));
+            }
+        }
+    }
+}
+
+/// the lints about the adjacent sentences
+fn sequences(codes: &[Sentence], warnings: &mut Vec<Warning>) {
+    let is_header = |code: &Sentence| matches!(code, Sentence::Label(_) | Sentence::Func(_));
+
+    for (i, code) in codes.iter().enumerate() {
+        match code {
+            // the labels just after GOTO
+            Sentence::Goto(label) => {
+                let mut next_labels = codes[i + 1..].iter().map_while(|code| match code {
+                    Sentence::Label(label) => Some(*label),
+                    _ => None
+                });
+                if next_labels.any(|l| l == *label) {
+                    warnings.push(Warning::new(
+                        i, REDUNDANT_GOTO, format!("`GOTO {}` jumps to the next sentence", label)
+                    ));
+                }
+            }
+            // the first ARG of a sequence
+            Sentence::Arg(_) if i == 0 || !matches!(codes[i - 1], Sentence::Arg(_)) => {
+                let next = codes[i..].iter().find(|code| !matches!(code, Sentence::Arg(_)));
+                if !matches!(next, Some(Sentence::Call { .. })) {
+                    warnings.push(Warning::new(i, DANGLING_ARG, "ARG isn't followed by CALL".into()));
+                }
+            }
+            Sentence::Read(Variable::Id(id)) => {
+                for next in codes[i + 1..].iter().take_while(|code| !is_header(code)) {
+                    if next.uses().contains(id) {
+                        break
+                    }
+                    if next.def() == Some(*id) {
+                        warnings.push(Warning::new(
+                            i, OVERWRITTEN_READ, format!("`{}` is overwritten before it's used", id)
+                        ));
+                        break
+                    }
+                    if matches!(next, Sentence::Goto(_) | Sentence::IfGoto { .. } | Sentence::Return(_)) {
+                        break
+                    }
+                }
+            }
+            _ => ()
+        }
+
+        // the first sentence after GOTO or RETURN
+        let after_jump = i > 0 && matches!(codes[i - 1], Sentence::Goto(_) | Sentence::Return(_));
+        if after_jump && !is_header(code) {
+            warnings.push(Warning::new(
+                i, UNREACHABLE_CODE, format!("`{}` is unreachable", code)
+            ));
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    ast::{Sentence, Variable},
+    cfg::{Cfg, build},
+};
+use super::{Change, address_taken, rename_uses};
+
+/// `target := source`, both are ids not in memory
+type Copy<'a> = (&'a str, &'a str);
+
+/// replace the ids by the ones they are copied from
+///
+/// the copies available at the beginning of every block are found by the data flow,
+/// so `t1 := v; t2 := t1; t3 := t2 + #1` becomes `t1 := v; t2 := v; t3 := v + #1`
+/// even if the sentences are in different blocks, and the dead copies are left to `dce`
+pub fn propagate<'a>(codes: &[Sentence<'a>]) -> (Vec<Sentence<'a>>, Vec<Change>) {
+    let (mut result, mut changes) = (codes.to_vec(), Vec::new());
+    for cfg in build(codes) {
+        let taken = address_taken(&codes[cfg.start..cfg.end]);
+        let available = available_copies(codes, &cfg, &taken);
+
+        // the index of the first assignment of every id, an id can't be read before it in the source
+        let mut first_def: BTreeMap<&str, usize> = BTreeMap::new();
+        for (i, code) in codes.iter().enumerate().take(cfg.end).skip(cfg.start) {
+            if let Some(id) = code.def() {
+                first_def.entry(id).or_insert(i);
+            }
+        }
+
+        for (k, block) in cfg.blocks.iter().enumerate() {
+            let Some(mut copies) = available[k].clone() else { continue };
+            for i in block.start..block.end {
+                let source = |id: &'a str| match copies.iter().find(|(target, _)| *target == id) {
+                    Some((_, source)) if first_def.get(source).is_none_or(|j| *j < i) => *source,
+                    _ => id
+                };
+                let code = rename_uses(&codes[i], source);
+                if code != codes[i] {
+                    changes.push(Change::new(i, format!("`{}` becomes `{}`", codes[i], code)));
+                    result[i] = code;
+                }
+                transfer(&mut copies, &codes[i], &taken);
+            }
+        }
+    }
+    (result, changes)
+}
+
+/// the copies which hold after the sentence
+///
+/// the source is followed first, `t2 := t1` after `t1 := v` is recorded as `t2 := v`,
+/// so it's still available after `t1` is changed
+fn transfer<'a>(copies: &mut BTreeSet<Copy<'a>>, code: &Sentence<'a>, taken: &[&'a str]) {
+    let copy = match code {
+        Sentence::Assign { target: Variable::Id(target), var: Variable::Id(source) }
+            if !taken.contains(target) && !taken.contains(source) => Some((
+                *target,
+                copies.iter().find(|(t, _)| t == source).map_or(*source, |(_, s)| *s)
+            )),
+        _ => None
+    };
+    if let Some(id) = code.def() {
+        copies.retain(|(target, source)| *target != id && *source != id);
+    }
+    if let Some((target, source)) = copy.filter(|(target, source)| target != source) {
+        copies.insert((target, source));
+    }
+}
+
+/// the copies available at the beginning of every block, `None` means the block is unreachable
+fn available_copies<'a>(codes: &[Sentence<'a>], cfg: &Cfg, taken: &[&'a str]) -> Vec<Option<BTreeSet<Copy<'a>>>> {
+    let blocks = &cfg.blocks;
+    let reachable = cfg.reachable();
+    // the resolved sources are the ids copied from
+    let ids: BTreeSet<&str> = codes[cfg.start..cfg.end].iter()
+        .filter_map(|code| match code {
+            Sentence::Assign { var: Variable::Id(source), .. } => Some(*source),
+            _ => None
+        })
+        .collect();
+    let all: BTreeSet<Copy> = codes[cfg.start..cfg.end].iter()
+        .filter_map(|code| match code {
+            Sentence::Assign { target: Variable::Id(target), var: Variable::Id(_) } => Some(*target),
+            _ => None
+        })
+        .flat_map(|target| ids.iter().map(move |source| (target, *source)))
+        .collect();
+
+    // a copy is available if it's available at the end of every predecessor
+    let mut out: Vec<BTreeSet<Copy>> = vec![all; blocks.len()];
+    let mut available_in: Vec<Option<BTreeSet<Copy>>> = vec![None; blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for k in cfg.reverse_postorder() {
+            // nothing is known when the control comes from another function
+            let mut copies = match cfg.roots().any(|root| root == k) {
+                true => BTreeSet::new(),
+                false => blocks[k].preds.iter()
+                    .filter(|p| reachable[**p])
+                    .map(|p| out[*p].clone())
+                    .reduce(|a, b| a.intersection(&b).copied().collect())
+                    .unwrap_or_default(),
+            };
+            available_in[k] = Some(copies.clone());
+            for code in &codes[blocks[k].start..blocks[k].end] {
+                transfer(&mut copies, code, taken);
+            }
+            if copies != out[k] {
+                out[k] = copies;
+                changed = true;
+            }
+        }
+    }
+    available_in
+}
@@ -0,0 +1,85 @@
+use crate::ast::{Operator, Sentence, Variable};
+use crate::cfg::functions;
+use super::{Change, address_taken};
+
+/// `holder := l opt r` computed before in the block
+struct Expr<'a> {
+    opt: Operator,
+    l: Variable<'a>,
+    r: Variable<'a>,
+    holder: &'a str,
+}
+
+/// eliminate the common subexpressions in every basic block
+///
+/// `t2 := a + b` becomes `t2 := t1` if `t1 := a + b` is computed before and neither `t1`
+/// nor the operands are modified between them, `+` and `*` match the swapped operands.
+/// the expressions reading memory, which are `*p` and the ids whose address is taken,
+/// are forgot at `*p := ...`, `CALL`, `READ` and the assignments to the ids whose address is taken
+pub fn eliminate<'a>(codes: &[Sentence<'a>]) -> (Vec<Sentence<'a>>, Vec<Change>) {
+    let (mut result, mut changes) = (Vec::with_capacity(codes.len()), Vec::new());
+
+    let mut taken = Vec::new();
+    let mut exprs: Vec<Expr<'a>> = Vec::new();
+    let ranges = functions(codes);
+
+    for (i, code) in codes.iter().enumerate() {
+        match code {
+            Sentence::Func(_) => {
+                let (start, end) = ranges.iter().find(|(start, _)| *start == i).unwrap();
+                taken = address_taken(&codes[*start..*end]);
+                exprs.clear();
+            }
+            Sentence::Label(_) => exprs.clear(),
+            _ => ()
+        }
+
+        let in_memory = |var: &Variable| match var {
+            Variable::Deref(_) => true,
+            Variable::Id(id) => taken.contains(id),
+            _ => false
+        };
+
+        let mut new_code = Some(code.clone());
+        if let Sentence::Arith { l, r, opt, target: Variable::Id(target) } = code {
+            let found = exprs.iter().find(|expr| expr.opt == *opt && (
+                (expr.l == *l && expr.r == *r)
+                    || (matches!(opt, Operator::Plus | Operator::Mul) && expr.l == *r && expr.r == *l)
+            ));
+            if let Some(expr) = found {
+                if expr.holder == *target {
+                    changes.push(Change::new(i, format!("`{}` is removed, `{}` holds the value", code, target)));
+                    new_code = None;
+                } else {
+                    let assign = Sentence::Assign { target: Variable::Id(target), var: Variable::Id(expr.holder) };
+                    changes.push(Change::new(i, format!("`{}` becomes `{}`", code, assign)));
+                    new_code = Some(assign);
+                }
+            }
+        }
+
+        // the memory may be written, `v := #5` changes `*p` too if `p := &v`
+        if matches!(code, Sentence::Assign { target: Variable::Deref(_), .. }
+            | Sentence::Arith { target: Variable::Deref(_), .. } | Sentence::Call { .. } | Sentence::Read(_))
+            || code.def().is_some_and(|id| taken.contains(&id))
+        {
+            exprs.retain(|expr| !in_memory(&expr.l) && !in_memory(&expr.r));
+        }
+        if let Some(id) = code.def() {
+            let reads = |var: &Variable| matches!(var, Variable::Id(x) | Variable::Deref(x) if *x == id);
+            exprs.retain(|expr| expr.holder != id && !reads(&expr.l) && !reads(&expr.r));
+        }
+
+        // record the expression, `x := x + #1` changes its own operand
+        if let Sentence::Arith { l, r, opt, target: Variable::Id(target) } = code {
+            let reads = |var: &Variable| matches!(var, Variable::Id(x) | Variable::Deref(x) if x == target);
+            if !taken.contains(target) && !reads(l) && !reads(r) {
+                exprs.push(Expr { opt: *opt, l: l.clone(), r: r.clone(), holder: target });
+            }
+        }
+
+        result.extend(new_code);
+    }
+
+    (result, changes)
+}
@@ -0,0 +1,159 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{ast::{Sentence, Variable}, cfg::{Cfg, build}};
+use super::{Change, address_taken};
+
+/// remove the dead codes, there are three kinds of them
+///
+/// * the blocks can't be reached from the entrance of function or by a jump of another function
+/// * the assignments whose target is never read, `CALL` and `READ` are kept for their side effects
+/// * the labels which are never the target of `GOTO` or `IF`
+///
+/// if the first assignment of an id in the source is unreachable but the id is read later,
+/// `x := #0` is added at the entrance of function to keep the program passing the checking
+pub fn eliminate<'a>(codes: &[Sentence<'a>]) -> (Vec<Sentence<'a>>, Vec<Change>) {
+    let mut removed = vec![false; codes.len()];
+    let mut changes = Vec::new();
+    // the ids to assign `#0` after the `PARAM`s of function
+    let mut inits: BTreeMap<usize, Vec<&'a str>> = BTreeMap::new();
+
+    for cfg in build(codes) {
+        let (start, end) = (cfg.start, cfg.end);
+        for (k, reachable) in cfg.reachable().into_iter().enumerate() {
+            if reachable {
+                continue
+            }
+            for i in cfg.blocks[k].start..cfg.blocks[k].end {
+                removed[i] = true;
+                changes.push(Change::new(i, format!("unreachable `{}` is removed", codes[i])));
+            }
+        }
+
+        let taken = address_taken(&codes[start..end]);
+        // an id read before its first assignment, whose original first assignment is unreachable
+        let entry = start + 1 + codes[start + 1..end].iter().take_while(|code| matches!(code, Sentence::Param(_))).count();
+        let mut defined = BTreeSet::new();
+        for i in (start..end).filter(|i| !removed[*i]) {
+            for id in codes[i].uses() {
+                if !taken.contains(&id) && defined.insert(id) {
+                    changes.push(Change::new(entry, format!("`{} := #0` is added, its first assignment is unreachable", id)));
+                    inits.entry(entry).or_default().push(id);
+                }
+            }
+            defined.extend(codes[i].def());
+        }
+
+        // removing an assignment may make another one dead
+        loop {
+            let dead = dead_assignments(codes, &cfg, &removed, &taken);
+            if dead.is_empty() {
+                break
+            }
+            for i in dead {
+                removed[i] = true;
+                changes.push(Change::new(i, format!("dead `{}` is removed", codes[i])));
+            }
+        }
+    }
+
+    let targets: BTreeSet<&str> = codes.iter().enumerate()
+        .filter(|(i, _)| !removed[*i])
+        .filter_map(|(_, code)| match code {
+            Sentence::Goto(label) | Sentence::IfGoto { label, .. } => Some(*label),
+            _ => None
+        })
+        .collect();
+    for (i, code) in codes.iter().enumerate() {
+        if let Sentence::Label(label) = code {
+            if !removed[i] && !targets.contains(label) {
+                removed[i] = true;
+                changes.push(Change::new(i, format!("unused `{}` is removed", code)));
+            }
+        }
+    }
+
+    changes.sort_by_key(|change| change.i);
+    let mut result = Vec::with_capacity(codes.len());
+    for (i, code) in codes.iter().enumerate() {
+        let init = inits.get(&i).into_iter().flatten()
+            .map(|id| Sentence::Assign { target: Variable::Id(id), var: Variable::Number(0) });
+        result.extend(init);
+        if !removed[i] {
+            result.push(code.clone());
+        }
+    }
+
+    (result, changes)
+}
+
+/// the assignments whose target isn't live after them
+fn dead_assignments(codes: &[Sentence], cfg: &Cfg, removed: &[bool], taken: &[&str]) -> Vec<usize> {
+    let blocks = &cfg.blocks;
+    let alive = |i: &usize| !removed[*i];
+
+    // 1. the live variables at the end of every block
+    let mut live_out: Vec<BTreeSet<&str>> = vec![BTreeSet::new(); blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for k in (0..blocks.len()).rev() {
+            let out: BTreeSet<&str> = blocks[k].succs.iter()
+                .flat_map(|s| live_in(codes, blocks[*s].start..blocks[*s].end, &live_out[*s], removed))
+                .collect();
+            if out != live_out[k] {
+                live_out[k] = out;
+                changed = true;
+            }
+        }
+    }
+
+    // 2. walk back every block to find the dead assignments
+    let mut dead = Vec::new();
+    for (k, block) in blocks.iter().enumerate() {
+        let mut live = live_out[k].clone();
+        for i in (block.start..block.end).rev().filter(alive) {
+            let code = &codes[i];
+            if let (Sentence::Assign { .. } | Sentence::Arith { .. }, Some(id)) = (code, code.def()) {
+                if !live.contains(id) && !taken.contains(&id) {
+                    dead.push(i);
+                    continue
+                }
+            }
+            if let Some(id) = code.def() {
+                live.remove(id);
+            }
+            live.extend(code.uses());
+        }
+    }
+
+    // the interpreter requires a variable is assigned before it's read in the source order
+    // so the first assignment of a variable can't be removed if it's read later
+    dead.retain(|i| {
+        let id = codes[*i].def().unwrap();
+        let assigned_before = (cfg.start..*i)
+            .filter(alive)
+            .any(|j| codes[j].def() == Some(id));
+        let read_after = (*i + 1..cfg.end)
+            .filter(alive)
+            .any(|j| codes[j].uses().contains(&id));
+        assigned_before || !read_after
+    });
+
+    dead
+}
+
+fn live_in<'a>(
+    codes: &[Sentence<'a>],
+    range: std::ops::Range<usize>,
+    out: &BTreeSet<&'a str>,
+    removed: &[bool]) -> BTreeSet<&'a str>
+{
+    let mut live = out.clone();
+    for i in range.rev().filter(|i| !removed[*i]) {
+        if let Some(id) = codes[i].def() {
+            live.remove(id);
+        }
+        live.extend(codes[i].uses());
+    }
+    live
+}
@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+
+use crate::ast::{Operator, Sentence, Variable};
+use crate::cfg::functions;
+use super::{Change, address_taken};
+
+/// fold the constant expressions in every basic block
+/// 
+/// * `t1 := #2 * #4` becomes `t1 := #8`, and `t1` is replaced by `#8` until the block is over
+/// * the algebraic identities like `t2 := t1 + #0` become assignments
+/// * `IF` with known condition becomes `GOTO` or is removed
+pub fn fold<'a>(codes: &[Sentence<'a>]) -> (Vec<Sentence<'a>>, Vec<Change>) {
+    let (mut result, mut changes) = (Vec::with_capacity(codes.len()), Vec::new());
+
+    let mut taken = Vec::new();
+    let mut consts: BTreeMap<&'a str, i32> = BTreeMap::new();
+    let ranges = functions(codes);
+
+    for (i, code) in codes.iter().enumerate() {
+        // the value of a label is unknown, it can be reached from anywhere
+        match code {
+            Sentence::Func(_) => {
+                let (start, end) = ranges.iter().find(|(start, _)| *start == i).unwrap();
+                taken = address_taken(&codes[*start..*end]);
+                consts.clear();
+            }
+            Sentence::Label(_) => consts.clear(),
+            _ => ()
+        }
+
+        let subst = |var: &Variable<'a>| match var {
+            Variable::Id(id) => consts.get(id).map_or(var.clone(), |n| Variable::Number(*n)),
+            _ => var.clone()
+        };
+
+        let new_code = match code {
+            Sentence::Assign { target, var } 
+                => Some(Sentence::Assign { target: target.clone(), var: subst(var) }),
+            Sentence::Arith { l, r, opt, target } 
+                => Some(simplify(target, subst(l), subst(r), *opt)),
+            Sentence::IfGoto { l, r, opt, label } => match (subst(l), subst(r)) {
+                (Variable::Number(l), Variable::Number(r)) => if opt.calculate(l, r) >= 1 {
+                    Some(Sentence::Goto(label))
+                } else {
+                    None
+                },
+                (l, r) => Some(Sentence::IfGoto { l, r, opt: *opt, label }),
+            },
+            Sentence::Return(var) => Some(Sentence::Return(subst(var))),
+            Sentence::Arg(var) => Some(Sentence::Arg(subst(var))),
+            Sentence::Write(var) => Some(Sentence::Write(subst(var))),
+            _ => Some(code.clone())
+        };
+
+        // record the value of target
+        if let Some(id) = new_code.as_ref().and_then(|code| code.def()) {
+            match &new_code {
+                Some(Sentence::Assign { var: Variable::Number(n), .. }) 
+                    if !taken.contains(&id) => { consts.insert(id, *n); },
+                _ => { consts.remove(id); }
+            }
+        }
+
+        match new_code {
+            Some(new_code) => {
+                if new_code != *code {
+                    changes.push(Change::new(i, format!("`{}` => `{}`", code, new_code)));
+                }
+                result.push(new_code);
+            }
+            None => changes.push(Change::new(i, format!("`{}` is removed", code))),
+        }
+    }
+
+    (result, changes)
+}
+
+fn simplify<'a>(target: &Variable<'a>, l: Variable<'a>, r: Variable<'a>, opt: Operator) -> Sentence<'a> {
+    use Variable::Number;
+
+    let var = match (opt, &l, &r) {
+        (_, Number(l), Number(r)) => opt.try_calculate(*l, *r).map(Number),
+        (Operator::Plus, x, Number(0)) | (Operator::Plus, Number(0), x)
+            | (Operator::Sub, x, Number(0))
+            | (Operator::Mul, x, Number(1)) | (Operator::Mul, Number(1), x)
+            | (Operator::Div, x, Number(1)) => Some((*x).clone()),
+        (Operator::Mul, _, Number(0)) | (Operator::Mul, Number(0), _) => Some(Number(0)),
+        (Operator::Sub, x, y) if x == y => Some(Number(0)),
+        _ => None
+    };
+
+    match var {
+        Some(var) => Sentence::Assign { target: target.clone(), var },
+        None => Sentence::Arith { l, r, opt, target: target.clone() },
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    ast::{Sentence, Variable},
+    cfg::functions,
+    utils::names::Fresh,
+};
+use super::Change;
+
+/// the largest callee to inline by default, `FUNCTION` isn't counted
+pub const THRESHOLD: usize = 16;
+
+/// a function which can be inlined
+struct Callee<'a, 'c> {
+    params: Vec<&'a str>,
+    body: &'c [Sentence<'a>],
+}
+
+/// replace the calls to the small functions by their bodies
+///
+/// the callee must have at most `threshold` sentences, end with `RETURN`, not call itself
+/// in any way and have no `DEC`, which would allocate again at every inlined run in a loop
+/// without the frame freed by `RETURN`. the `ARG`s must be right before the `CALL`. the ids and labels of callee
+/// are renamed, the `PARAM`s are assigned the values of `ARG`s and `RETURN x` becomes
+/// `target := x` followed by `GOTO` the end of body. the callee itself is kept
+pub fn inline<'a>(codes: &[Sentence<'a>], threshold: usize) -> (Vec<Sentence<'a>>, Vec<Change>) {
+    let callees = callees(codes, threshold);
+
+    let mut ids = BTreeSet::new();
+    let mut labels = BTreeSet::new();
+    for code in codes {
+        match code {
+            Sentence::Label(label) => { labels.insert(*label); }
+            _ => {
+                let mut vars = code.operands();
+                vars.extend(code.target());
+                ids.extend(vars.into_iter().filter_map(|var| var.get_id()));
+            }
+        }
+    }
+    let (mut fresh_ids, mut fresh_labels) = (Fresh::new(ids), Fresh::new(labels));
+
+    // the `ARG`s passed to an inlined call are removed
+    let mut skipped = BTreeSet::new();
+    let mut inlined = BTreeMap::new();
+    for (i, code) in codes.iter().enumerate() {
+        let Sentence::Call { func, .. } = code else { continue };
+        let Some(callee) = callees.get(func) else { continue };
+        let n = callee.params.len();
+        let args = codes[..i].iter().rev().take_while(|code| matches!(code, Sentence::Arg(_))).count();
+        if args >= n {
+            skipped.extend(i - n..i);
+            inlined.insert(i, callee);
+        }
+    }
+
+    let (mut result, mut changes) = (Vec::with_capacity(codes.len()), Vec::new());
+    for (i, code) in codes.iter().enumerate() {
+        if skipped.contains(&i) {
+            continue
+        }
+        let (Some(callee), Sentence::Call { target, func }) = (inlined.get(&i), code) else {
+            result.push(code.clone());
+            continue
+        };
+
+        // the ids and labels of callee are renamed for every call
+        let mut rename: BTreeMap<&'a str, &'a str> = BTreeMap::new();
+        let mut relabel: BTreeMap<&'a str, &'a str> = BTreeMap::new();
+        for code in callee.body {
+            match code {
+                Sentence::Label(label) => { relabel.insert(label, fresh_labels.name(label)); }
+                _ => {
+                    let mut vars = code.operands();
+                    vars.extend(code.target());
+                    for id in vars.into_iter().filter_map(|var| var.get_id()).chain(callee.params.iter().copied()) {
+                        rename.entry(id).or_insert_with(|| fresh_ids.name(id));
+                    }
+                }
+            }
+        }
+        let id = |id: &'a str| rename[id];
+        // a label out of callee is a label of another function, it isn't renamed
+        let label = |label: &'a str| relabel.get(label).copied().unwrap_or(label);
+
+        // the first `PARAM` gets the last `ARG`
+        let n = callee.params.len();
+        for (k, param) in callee.params.iter().enumerate() {
+            let Sentence::Arg(value) = &codes[i - 1 - k] else { unreachable!() };
+            result.push(Sentence::Assign { target: Variable::Id(id(param)), var: value.clone() });
+        }
+
+        let end = fresh_labels.name(&format!("{}_end", func));
+        let mut jumps = false;
+        for (k, code) in callee.body.iter().enumerate() {
+            let code = map_vars(code, |var| match var {
+                Variable::Id(x) => Variable::Id(id(x)),
+                Variable::Deref(x) => Variable::Deref(id(x)),
+                Variable::Pointer(x) => Variable::Pointer(id(x)),
+                Variable::Number(_) => var.clone(),
+            });
+            match code {
+                Sentence::Label(l) => result.push(Sentence::Label(label(l))),
+                Sentence::Goto(l) => result.push(Sentence::Goto(label(l))),
+                Sentence::IfGoto { l, r, opt, label: to } => result.push(Sentence::IfGoto { l, r, opt, label: label(to) }),
+                Sentence::Return(var) => {
+                    result.push(Sentence::Assign { target: target.clone(), var });
+                    if k + 1 < callee.body.len() {
+                        result.push(Sentence::Goto(end));
+                        jumps = true;
+                    }
+                }
+                code => result.push(code),
+            }
+        }
+        if jumps {
+            result.push(Sentence::Label(end));
+        }
+        changes.push(Change::new(i, format!("`{}` is inlined with {} `ARG`s", code, n)));
+    }
+
+    (result, changes)
+}
+
+/// the functions small enough to inline, which never call themselves
+fn callees<'a, 'c>(codes: &'c [Sentence<'a>], threshold: usize) -> BTreeMap<&'a str, Callee<'a, 'c>> {
+    let mut graph: BTreeMap<&'a str, Vec<&'a str>> = BTreeMap::new();
+    let mut ranges = Vec::new();
+    for (start, end) in functions(codes) {
+        let Sentence::Func(name) = codes[start] else { unreachable!() };
+        let calls = codes[start..end].iter().filter_map(|code| match code {
+            Sentence::Call { func, .. } => Some(*func),
+            _ => None
+        });
+        graph.insert(name, calls.collect());
+        ranges.push((name, start, end));
+    }
+
+    // the function is recursive if it can be reached from its callees
+    let recursive = |name: &'a str| {
+        let (mut stack, mut visited) = (graph[name].clone(), BTreeSet::new());
+        while let Some(func) = stack.pop() {
+            if func == name {
+                return true
+            }
+            if visited.insert(func) {
+                stack.extend(graph.get(func).into_iter().flatten());
+            }
+        }
+        false
+    };
+
+    let mut callees = BTreeMap::new();
+    for (name, start, end) in ranges {
+        let codes = &codes[start + 1..end];
+        let params: Vec<&'a str> = codes.iter()
+            .map_while(|code| match code {
+                Sentence::Param(Variable::Id(id)) => Some(*id),
+                _ => None
+            })
+            .collect();
+        let body = &codes[params.len()..];
+        if codes.len() <= threshold && matches!(body.last(), Some(Sentence::Return(_)))
+            && !body.iter().any(|code| matches!(code, Sentence::Param(_) | Sentence::Dec { .. }))
+            && !recursive(name)
+        {
+            callees.insert(name, Callee { params, body });
+        }
+    }
+    callees
+}
+
+/// the sentence with every variable mapped, including the targets
+fn map_vars<'a>(code: &Sentence<'a>, f: impl Fn(&Variable<'a>) -> Variable<'a>) -> Sentence<'a> {
+    match code {
+        Sentence::Assign { target, var } => Sentence::Assign { target: f(target), var: f(var) },
+        Sentence::Arith { l, r, opt, target } => Sentence::Arith { l: f(l), r: f(r), opt: *opt, target: f(target) },
+        Sentence::IfGoto { l, r, opt, label } => Sentence::IfGoto { l: f(l), r: f(r), opt: *opt, label },
+        Sentence::Return(var) => Sentence::Return(f(var)),
+        Sentence::Dec { target, size } => Sentence::Dec { target: f(target), size: *size },
+        Sentence::Arg(var) => Sentence::Arg(f(var)),
+        Sentence::Call { target, func } => Sentence::Call { target: f(target), func },
+        Sentence::Param(var) => Sentence::Param(f(var)),
+        Sentence::Read(var) => Sentence::Read(f(var)),
+        Sentence::Write(var) => Sentence::Write(f(var)),
+        Sentence::Label(_) | Sentence::Func(_) | Sentence::Goto(_) => code.clone(),
+    }
+}
@@ -0,0 +1,166 @@
+use std::fmt::{Display, self};
+
+use crate::ast::{Sentence, Variable};
+
+pub mod copy;
+pub mod cse;
+pub mod dce;
+pub mod fold;
+pub mod inline;
+pub mod peephole;
+pub mod ssa;
+
+/// a change made by a pass, `i` is the index of the sentence in the input codes
+#[derive(Debug)]
+pub struct Change {
+    pub i: usize,
+    pub msg: String,
+}
+
+impl Change {
+    fn new(i: usize, msg: String) -> Self {
+        Change { i, msg }
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.i + 1, self.msg)
+    }
+}
+
+/// the codes as IR source, one sentence a line
+pub fn to_source(codes: &[Sentence]) -> String {
+    codes.iter().map(|code| format!("{}\n", code)).collect()
+}
+
+/// a pass of the pipeline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pass {
+    Fold,
+    Copy,
+    Cse,
+    Dce,
+    Inline,
+    Peephole,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 6] = [Pass::Fold, Pass::Copy, Pass::Cse, Pass::Dce, Pass::Inline, Pass::Peephole];
+
+    /// the passes of `-O<level>`, the level above 2 is the same as 2
+    ///
+    /// `peephole` goes first in `-O2` to remove the labels splitting the blocks for `cse`
+    pub fn level(level: u8) -> &'static [Pass] {
+        match level {
+            0 => &[],
+            1 => &[Pass::Fold, Pass::Copy, Pass::Dce, Pass::Peephole],
+            _ => &[
+                Pass::Inline, Pass::Peephole, Pass::Fold, Pass::Copy, Pass::Cse,
+                Pass::Copy, Pass::Fold, Pass::Dce, Pass::Peephole, Pass::Dce,
+            ],
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::Fold => "fold",
+            Pass::Copy => "copy",
+            Pass::Cse => "cse",
+            Pass::Dce => "dce",
+            Pass::Inline => "inline",
+            Pass::Peephole => "peephole",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Pass> {
+        Pass::ALL.into_iter().find(|pass| pass.name() == name)
+    }
+}
+
+/// run the passes in order, the changes are reported with the pass making them,
+/// and their lines are the ones in the input of that pass
+pub fn optimize<'a>(codes: &[Sentence<'a>], passes: &[Pass], rules: &[peephole::Rule]) -> (Vec<Sentence<'a>>, Vec<(Pass, Change)>) {
+    let (mut codes, mut changes) = (codes.to_vec(), Vec::new());
+    for pass in passes {
+        let (result, pass_changes) = match pass {
+            Pass::Fold => fold::fold(&codes),
+            Pass::Copy => copy::propagate(&codes),
+            Pass::Cse => cse::eliminate(&codes),
+            Pass::Dce => dce::eliminate(&codes),
+            Pass::Inline => inline::inline(&codes, inline::THRESHOLD),
+            Pass::Peephole => peephole::peephole(&codes, rules),
+        };
+        codes = result;
+        changes.extend(pass_changes.into_iter().map(|change| (*pass, change)));
+    }
+    (codes, changes)
+}
+
+/// the running counts of a program before and after the passes with the same input
+#[derive(Debug, PartialEq)]
+pub struct Report {
+    pub before: usize,
+    pub after: usize,
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let delta = self.after as f64 / self.before.max(1) as f64 * 100.0 - 100.0;
+        write!(f, "instructions: {} -> {} ({:+.1}%)", self.before, self.after, delta)
+    }
+}
+
+/// the ids whose address may be taken in the function,
+/// they can be modified by `*p := ...` or the callee
+pub(crate) fn address_taken<'a>(codes: &[Sentence<'a>]) -> Vec<&'a str> {
+    let mut ids = Vec::new();
+    for code in codes {
+        if let Sentence::Dec { target: Variable::Id(id), .. } = code {
+            ids.push(*id);
+        }
+        for var in code.operands() {
+            if let Variable::Pointer(id) = var {
+                ids.push(*id);
+            }
+        }
+    }
+    ids
+}
+
+/// the sentence with every read id replaced, including the `p` of `*p := x`,
+/// the taken address `&x` and the assigned id are kept
+pub(crate) fn rename_uses<'a>(code: &Sentence<'a>, f: impl Fn(&'a str) -> &'a str) -> Sentence<'a> {
+    let var = |var: &Variable<'a>| match var {
+        Variable::Id(id) => Variable::Id(f(id)),
+        Variable::Deref(id) => Variable::Deref(f(id)),
+        _ => var.clone()
+    };
+    // only `*p` is a read in the place of target
+    let target = |target: &Variable<'a>| match target {
+        Variable::Deref(id) => Variable::Deref(f(id)),
+        _ => target.clone()
+    };
+    match code {
+        Sentence::Assign { target: t, var: v } => Sentence::Assign { target: target(t), var: var(v) },
+        Sentence::Arith { l, r, opt, target: t } 
+            => Sentence::Arith { l: var(l), r: var(r), opt: *opt, target: target(t) },
+        Sentence::IfGoto { l, r, opt, label } => Sentence::IfGoto { l: var(l), r: var(r), opt: *opt, label },
+        Sentence::Call { target: t, func } => Sentence::Call { target: target(t), func },
+        Sentence::Return(v) => Sentence::Return(var(v)),
+        Sentence::Arg(v) => Sentence::Arg(var(v)),
+        Sentence::Write(v) => Sentence::Write(var(v)),
+        _ => code.clone()
+    }
+}
+
+/// the sentence assigning `id` instead, it must have `def()`
+pub(crate) fn rename_def<'a>(code: &Sentence<'a>, id: &'a str) -> Sentence<'a> {
+    let mut code = code.clone();
+    match &mut code {
+        Sentence::Assign { target, .. } | Sentence::Arith { target, .. } | Sentence::Call { target, .. }
+            | Sentence::Read(target) | Sentence::Param(target) => *target = Variable::Id(id),
+        _ => unreachable!()
+    }
+    code
+}
@@ -0,0 +1,393 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    ast::{Sentence, Variable},
+    cfg::functions,
+    interpreter::parse_lines,
+};
+use super::{Change, address_taken};
+
+/// the rules used by default, the format is the same as the file given by users
+///
+/// every rule starts with `rule <name>`, the pattern and the replacement are sentences
+/// separated by `=>`, and `where dead t` means the id `t` isn't read anywhere else in the function,
+/// or the label `t` isn't the target of any other jump in the program.
+/// an id in the pattern matches any variable, `*p` and `&p` bind `p` to an id,
+/// the labels and functions match any name, and the same name must match the same thing
+pub const DEFAULT_RULES: &str = "
+; the label only costs a step when it's fallen into
+rule label-unused
+LABEL l :
+=>
+where dead l
+
+; the jump to the next sentence
+rule goto-next
+GOTO l
+LABEL l :
+=>
+LABEL l :
+
+; jump over a `GOTO` by the inverted condition
+rule invert-lt
+IF a < b GOTO l1
+GOTO l2
+LABEL l1 :
+=>
+IF a >= b GOTO l2
+LABEL l1 :
+
+rule invert-le
+IF a <= b GOTO l1
+GOTO l2
+LABEL l1 :
+=>
+IF a > b GOTO l2
+LABEL l1 :
+
+rule invert-gt
+IF a > b GOTO l1
+GOTO l2
+LABEL l1 :
+=>
+IF a <= b GOTO l2
+LABEL l1 :
+
+rule invert-ge
+IF a >= b GOTO l1
+GOTO l2
+LABEL l1 :
+=>
+IF a < b GOTO l2
+LABEL l1 :
+
+rule invert-eq
+IF a == b GOTO l1
+GOTO l2
+LABEL l1 :
+=>
+IF a != b GOTO l2
+LABEL l1 :
+
+rule invert-ne
+IF a != b GOTO l1
+GOTO l2
+LABEL l1 :
+=>
+IF a == b GOTO l2
+LABEL l1 :
+
+; the temporary only passes the value
+rule copy-dead
+t := x
+y := t
+=>
+y := x
+where dead t
+
+rule add-dead
+t := a + b
+y := t
+=>
+y := a + b
+where dead t
+
+rule sub-dead
+t := a - b
+y := t
+=>
+y := a - b
+where dead t
+
+rule mul-dead
+t := a * b
+y := t
+=>
+y := a * b
+where dead t
+
+rule div-dead
+t := a / b
+y := t
+=>
+y := a / b
+where dead t
+
+rule self-assign
+x := x
+=>
+
+; `y` already has the value
+rule copy-back
+x := y
+y := x
+=>
+x := y
+";
+
+/// the passes over the codes at most, a set of rules may rewrite the codes back and forth
+const MAX_PASSES: usize = 16;
+
+/// a rewrite of the consecutive sentences
+#[derive(Debug)]
+pub struct Rule<'r> {
+    pub name: &'r str,
+    pattern: Vec<Sentence<'r>>,
+    replacement: Vec<Sentence<'r>>,
+    dead: Vec<&'r str>,
+}
+
+/// parse the rules, the error is the line number (starting from 1) and the message
+pub fn parse_rules(text: &str) -> Result<Vec<Rule<'_>>, (usize, String)> {
+    // the sentences are parsed at once, the other lines are left empty
+    let lines: Vec<&str> = text.lines().map(|line| line.trim()).collect();
+    let is_directive = |line: &str| line.starts_with("rule ") || line == "=>" || line.starts_with("where dead ");
+    let sentences: Vec<&str> = lines.iter().map(|line| if is_directive(line) { "" } else { line }).collect();
+    let (codes, source_lines) = parse_lines(&sentences)
+        .map_err(|err| (err.line(), format!("`{}` can't be parsed", lines[err.line() - 1])))?;
+    let mut codes: BTreeMap<usize, Sentence> = source_lines.into_iter().zip(codes).collect();
+
+    let mut rules: Vec<Rule> = Vec::new();
+    // whether the `=>` of the last rule is found
+    let mut replacing = false;
+    for (i, line) in lines.iter().enumerate() {
+        let err = |msg: String| Err((i + 1, msg));
+        if let Some(name) = line.strip_prefix("rule ") {
+            rules.push(Rule { name: name.trim(), pattern: Vec::new(), replacement: Vec::new(), dead: Vec::new() });
+            replacing = false;
+            continue
+        }
+        let code = codes.remove(&(i + 1));
+        let Some(rule) = rules.last_mut() else {
+            match (code, is_directive(line)) {
+                (None, false) => continue,
+                _ => return err("expect `rule <name>`".into())
+            }
+        };
+
+        if *line == "=>" {
+            if replacing || rule.pattern.is_empty() {
+                return err(format!("the pattern of `{}` is empty or `=>` is repeated", rule.name))
+            }
+            replacing = true;
+        } else if let Some(id) = line.strip_prefix("where dead ") {
+            let id = id.trim();
+            if !replacing || !binds(&rule.pattern, id) {
+                return err(format!("`{}` isn't bound by the pattern", id))
+            }
+            rule.dead.push(id);
+        } else if let Some(code) = code {
+            if !replacing {
+                if matches!(code, Sentence::Func(_)) {
+                    return err("the pattern can't match `FUNCTION`".into())
+                }
+                rule.pattern.push(code);
+                continue
+            }
+            // the replacement only uses the names bound by pattern
+            let mut vars = code.operands();
+            vars.extend(code.target());
+            let unbound = vars.into_iter().filter_map(|var| var.get_id())
+                .chain(label(&code))
+                .find(|name| !binds(&rule.pattern, name));
+            if let Some(name) = unbound {
+                return err(format!("`{}` isn't bound by the pattern", name))
+            }
+            rule.replacement.push(code);
+        }
+    }
+
+    match rules.iter().find(|rule| rule.pattern.is_empty()) {
+        Some(rule) => Err((text.lines().count(), format!("the rule `{}` has no pattern", rule.name))),
+        None => Ok(rules),
+    }
+}
+
+/// the default rules, which are always valid
+pub fn default_rules() -> Vec<Rule<'static>> {
+    parse_rules(DEFAULT_RULES).unwrap()
+}
+
+/// rewrite the codes by the rules until nothing changes, the rules are tried by order
+pub fn peephole<'a>(codes: &[Sentence<'a>], rules: &[Rule]) -> (Vec<Sentence<'a>>, Vec<Change>) {
+    // every sentence keeps the index in the input, the replacement has the one of the first matched
+    let mut codes: Vec<(usize, Sentence<'a>)> = codes.iter().cloned().enumerate().collect();
+    let mut changes = Vec::new();
+
+    for _ in 0..MAX_PASSES {
+        let mut changed = false;
+        let mut result = Vec::with_capacity(codes.len());
+        let plain: Vec<Sentence<'a>> = codes.iter().map(|(_, code)| code.clone()).collect();
+        let ranges = functions(&plain);
+
+        let mut i = 0;
+        while i < codes.len() {
+            let range = ranges.iter().copied().find(|(start, end)| (*start..*end).contains(&i)).unwrap_or((0, codes.len()));
+            let rewrite = rules.iter().find_map(|rule| {
+                let window = plain.get(i..i + rule.pattern.len()).filter(|_| i + rule.pattern.len() <= range.1)?;
+                let (replacement, binding) = rule.apply(window)?;
+                rule.dead.iter().all(|t| match (binding.vars.get(t), binding.names.get(t)) {
+                    (Some(Variable::Id(id)), _) => is_dead(id, &plain[range.0..range.1], i - range.0, window.len(), &replacement),
+                    (_, Some(label)) => is_unused(label, &plain, i, window.len(), &replacement),
+                    _ => false
+                }).then_some((rule, replacement))
+            });
+
+            match rewrite {
+                Some((rule, replacement)) => {
+                    let window = &plain[i..i + rule.pattern.len()];
+                    let show = |codes: &[Sentence]| codes.iter().map(|code| format!("`{}`", code)).collect::<Vec<_>>().join(", ");
+                    let msg = match replacement.len() {
+                        0 => format!("{} is removed by `{}`", show(window), rule.name),
+                        _ => format!("{} becomes {} by `{}`", show(window), show(&replacement), rule.name),
+                    };
+                    let origin = codes[i].0;
+                    changes.push(Change::new(origin, msg));
+                    result.extend(replacement.into_iter().map(|code| (origin, code)));
+                    i += window.len();
+                    changed = true;
+                }
+                None => {
+                    result.push(codes[i].clone());
+                    i += 1;
+                }
+            }
+        }
+
+        codes = result;
+        if !changed {
+            break
+        }
+    }
+
+    (codes.into_iter().map(|(_, code)| code).collect(), changes)
+}
+
+/// whether `id` isn't read in the function once the window at `start` is replaced
+fn is_dead(id: &str, func: &[Sentence], start: usize, len: usize, replacement: &[Sentence]) -> bool {
+    !address_taken(func).contains(&id)
+        && func[..start].iter().chain(&func[start + len..]).chain(replacement).all(|code| !code.uses().contains(&id))
+}
+
+/// whether no jump goes to `label` once the window at `start` is replaced
+fn is_unused(label: &str, codes: &[Sentence], start: usize, len: usize, replacement: &[Sentence]) -> bool {
+    codes[..start].iter().chain(&codes[start + len..]).chain(replacement).all(|code| match code {
+        Sentence::Goto(l) | Sentence::IfGoto { label: l, .. } => *l != label,
+        _ => true
+    })
+}
+
+/// the names bound by the pattern to the variables and the names of labels and functions
+#[derive(Default)]
+struct Binding<'r, 'a> {
+    vars: BTreeMap<&'r str, Variable<'a>>,
+    names: BTreeMap<&'r str, &'a str>,
+}
+
+impl<'r, 'a> Binding<'r, 'a> {
+    fn var(&mut self, pattern: &Variable<'r>, var: &Variable<'a>) -> bool {
+        let (name, var) = match (pattern, var) {
+            (Variable::Number(n), Variable::Number(m)) => return n == m,
+            (Variable::Id(name), _) => (name, var.clone()),
+            (Variable::Deref(name), Variable::Deref(id)) | (Variable::Pointer(name), Variable::Pointer(id))
+                => (name, Variable::Id(id)),
+            _ => return false
+        };
+        self.vars.entry(name).or_insert_with(|| var.clone()) == &var
+    }
+
+    fn name(&mut self, pattern: &'r str, name: &'a str) -> bool {
+        *self.names.entry(pattern).or_insert(name) == name
+    }
+
+    fn code(&mut self, pattern: &Sentence<'r>, code: &Sentence<'a>) -> bool {
+        match (pattern, code) {
+            (Sentence::Label(p), Sentence::Label(l)) | (Sentence::Goto(p), Sentence::Goto(l)) => self.name(p, l),
+            (Sentence::Assign { target: pt, var: pv }, Sentence::Assign { target, var })
+                => self.var(pt, target) && self.var(pv, var),
+            (Sentence::Arith { l: pl, r: pr, opt: po, target: pt }, Sentence::Arith { l, r, opt, target })
+                => po == opt && self.var(pt, target) && self.var(pl, l) && self.var(pr, r),
+            (Sentence::IfGoto { l: pl, r: pr, opt: po, label: pb }, Sentence::IfGoto { l, r, opt, label })
+                => po == opt && self.var(pl, l) && self.var(pr, r) && self.name(pb, label),
+            (Sentence::Call { target: pt, func: pf }, Sentence::Call { target, func })
+                => self.var(pt, target) && self.name(pf, func),
+            (Sentence::Dec { target: pt, size: ps }, Sentence::Dec { target, size }) => ps == size && self.var(pt, target),
+            (Sentence::Return(p), Sentence::Return(v)) | (Sentence::Arg(p), Sentence::Arg(v))
+                | (Sentence::Param(p), Sentence::Param(v)) | (Sentence::Read(p), Sentence::Read(v))
+                | (Sentence::Write(p), Sentence::Write(v)) => self.var(p, v),
+            _ => false
+        }
+    }
+
+    /// the variable replacing the one in the replacement, `*p` needs `p` bound to an id
+    fn subst(&self, pattern: &Variable<'r>) -> Option<Variable<'a>> {
+        match pattern {
+            Variable::Number(n) => Some(Variable::Number(*n)),
+            Variable::Id(name) => self.vars.get(name).cloned(),
+            Variable::Deref(name) => match self.vars.get(name)? {
+                Variable::Id(id) => Some(Variable::Deref(id)),
+                _ => None
+            },
+            Variable::Pointer(name) => match self.vars.get(name)? {
+                Variable::Id(id) => Some(Variable::Pointer(id)),
+                _ => None
+            },
+        }
+    }
+
+    /// the target must be a left value
+    fn target(&self, pattern: &Variable<'r>) -> Option<Variable<'a>> {
+        self.subst(pattern).filter(|var| matches!(var, Variable::Id(_) | Variable::Deref(_)))
+    }
+
+    fn sentence(&self, pattern: &Sentence<'r>) -> Option<Sentence<'a>> {
+        let name = |name: &'r str| self.names.get(name).copied();
+        Some(match pattern {
+            Sentence::Label(l) => Sentence::Label(name(l)?),
+            Sentence::Func(f) => Sentence::Func(name(f)?),
+            Sentence::Goto(l) => Sentence::Goto(name(l)?),
+            Sentence::Assign { target, var } => Sentence::Assign { target: self.target(target)?, var: self.subst(var)? },
+            Sentence::Arith { l, r, opt, target }
+                => Sentence::Arith { l: self.subst(l)?, r: self.subst(r)?, opt: *opt, target: self.target(target)? },
+            Sentence::IfGoto { l, r, opt, label }
+                => Sentence::IfGoto { l: self.subst(l)?, r: self.subst(r)?, opt: *opt, label: name(label)? },
+            Sentence::Call { target, func } => Sentence::Call { target: self.target(target)?, func: name(func)? },
+            Sentence::Dec { target, size } => Sentence::Dec { target: self.target(target)?, size: *size },
+            Sentence::Return(var) => Sentence::Return(self.subst(var)?),
+            Sentence::Arg(var) => Sentence::Arg(self.subst(var)?),
+            Sentence::Param(var) => Sentence::Param(self.target(var)?),
+            Sentence::Read(var) => Sentence::Read(self.target(var)?),
+            Sentence::Write(var) => Sentence::Write(self.subst(var)?),
+        })
+    }
+}
+
+impl<'r> Rule<'r> {
+    /// the replacement and the binding if the window matches the pattern
+    fn apply<'a>(&self, window: &[Sentence<'a>]) -> Option<(Vec<Sentence<'a>>, Binding<'r, 'a>)> {
+        let mut binding = Binding::default();
+        if !self.pattern.iter().zip(window).all(|(pattern, code)| binding.code(pattern, code)) {
+            return None
+        }
+        let replacement = self.replacement.iter().map(|code| binding.sentence(code)).collect::<Option<_>>()?;
+        Some((replacement, binding))
+    }
+}
+
+/// whether the name is bound by the pattern, as an id or a label
+fn binds(pattern: &[Sentence], name: &str) -> bool {
+    pattern.iter().any(|code| {
+        let mut vars = code.operands();
+        vars.extend(code.target());
+        vars.into_iter().any(|var| var.get_id() == Some(name)) || label(code) == Some(name)
+    })
+}
+
+/// the label or function name in the sentence
+fn label<'r>(code: &Sentence<'r>) -> Option<&'r str> {
+    match code {
+        Sentence::Label(name) | Sentence::Func(name) | Sentence::Goto(name)
+            | Sentence::IfGoto { label: name, .. } | Sentence::Call { func: name, .. } => Some(name),
+        _ => None
+    }
+}
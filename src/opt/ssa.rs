@@ -0,0 +1,401 @@
+use std::{collections::{BTreeMap, BTreeSet}, fmt::{Display, self}};
+
+use crate::{
+    ast::{Sentence, Variable},
+    cfg::{Cfg, build, foreign_jump},
+    utils::names::Fresh,
+};
+use super::{address_taken, rename_def, rename_uses};
+
+/// `target := PHI(...)`, the value comes from the predecessor which the control comes from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phi<'a> {
+    // the id before renaming
+    pub var: &'a str,
+    pub target: &'a str,
+    // the predecessor and the value from it, `#0` if the id isn't assigned on that path
+    pub args: Vec<(usize, Variable<'a>)>,
+}
+
+#[derive(Debug)]
+pub struct SsaBlock<'a> {
+    pub phis: Vec<Phi<'a>>,
+    // the first one is `FUNCTION` or `LABEL` if the block has it
+    pub codes: Vec<Sentence<'a>>,
+    pub preds: Vec<usize>,
+    pub succs: Vec<usize>,
+}
+
+/// a function in SSA form, every id is assigned once except the ones in memory
+///
+/// the blocks are the reachable ones of CFG in the same order,
+/// so a block without jump at the end still falls through to the next one
+#[derive(Debug)]
+pub struct SsaFunction<'a> {
+    pub name: &'a str,
+    pub blocks: Vec<SsaBlock<'a>>,
+}
+
+impl Display for SsaFunction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (k, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "; block {}", k)?;
+            let mut codes = block.codes.iter().peekable();
+            // the phis come after the label
+            if let Some(code) = codes.next_if(|code| matches!(code, Sentence::Func(_) | Sentence::Label(_))) {
+                writeln!(f, "{}", code)?;
+            }
+            for phi in &block.phis {
+                let args: Vec<String> = phi.args.iter().map(|(p, var)| format!("{} [{}]", var, p)).collect();
+                writeln!(f, "{} := PHI {}", phi.target, args.join(", "))?;
+            }
+            for code in codes {
+                writeln!(f, "{}", code)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// convert every function into SSA form
+///
+/// the phis are placed on the iterated dominance frontiers where the id is live,
+/// and the ids whose address is taken or allocated by `DEC` are left as they are,
+/// since `*p := x` or the callee can assign them. the unreachable blocks are removed,
+/// but a block jumped to from another function is reachable, and no id of such a program
+/// is renamed since the jump carries the variables of the function it comes from
+pub fn construct<'a>(codes: &[Sentence<'a>]) -> Vec<SsaFunction<'a>> {
+    let frozen = foreign_jump(codes).is_some();
+    build(codes).iter().map(|cfg| Builder::new(codes, cfg, frozen).build()).collect()
+}
+
+struct Builder<'a, 'c> {
+    codes: &'c [Sentence<'a>],
+    cfg: &'c Cfg<'a>,
+    // the ids in memory aren't renamed
+    taken: Vec<&'a str>,
+    // the index of every reachable block in the result
+    index: Vec<Option<usize>>,
+    idom: Vec<Option<usize>>,
+}
+
+impl<'a, 'c> Builder<'a, 'c> {
+    fn new(codes: &'c [Sentence<'a>], cfg: &'c Cfg<'a>, frozen: bool) -> Self {
+        let taken = match frozen {
+            true => codes[cfg.start..cfg.end].iter().flat_map(|code| {
+                let mut vars = code.operands();
+                vars.extend(code.target());
+                vars.into_iter().filter_map(|var| var.get_id()).collect::<Vec<_>>()
+            }).collect(),
+            false => address_taken(&codes[cfg.start..cfg.end]),
+        };
+        let mut index = vec![None; cfg.blocks.len()];
+        let mut count = 0;
+        for (k, reachable) in cfg.reachable().into_iter().enumerate() {
+            if reachable {
+                index[k] = Some(count);
+                count += 1;
+            }
+        }
+        Builder { codes, cfg, taken, index, idom: cfg.dominators() }
+    }
+
+    fn renamed(&self, id: &str) -> bool {
+        !self.taken.contains(&id)
+    }
+
+    fn block_codes(&self, k: usize) -> &'c [Sentence<'a>] {
+        &self.codes[self.cfg.blocks[k].start..self.cfg.blocks[k].end]
+    }
+
+    /// the ids live at the beginning of every block
+    fn live_in(&self) -> Vec<BTreeSet<&'a str>> {
+        let blocks = &self.cfg.blocks;
+        // the ids read before assigned in the block, and the ids assigned
+        let (mut uses, mut defs) = (Vec::new(), Vec::new());
+        for k in 0..blocks.len() {
+            let (mut used, mut defined) = (BTreeSet::new(), BTreeSet::new());
+            for code in self.block_codes(k) {
+                used.extend(code.uses().into_iter().filter(|id| self.renamed(id) && !defined.contains(id)));
+                defined.extend(code.def().filter(|id| self.renamed(id)));
+            }
+            uses.push(used);
+            defs.push(defined);
+        }
+
+        let mut live_in: Vec<BTreeSet<&str>> = uses.clone();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for k in (0..blocks.len()).rev() {
+                let mut live: BTreeSet<&str> = blocks[k].succs.iter()
+                    .flat_map(|s| live_in[*s].iter().copied())
+                    .filter(|id| !defs[k].contains(id))
+                    .collect();
+                live.extend(uses[k].iter().copied());
+                if live != live_in[k] {
+                    live_in[k] = live;
+                    changed = true;
+                }
+            }
+        }
+        live_in
+    }
+
+    /// the dominance frontier of every block
+    fn frontiers(&self) -> Vec<BTreeSet<usize>> {
+        let mut frontiers = vec![BTreeSet::new(); self.cfg.blocks.len()];
+        for (k, block) in self.cfg.blocks.iter().enumerate() {
+            if block.preds.len() < 2 || self.index[k].is_none() {
+                continue
+            }
+            for pred in block.preds.iter().filter(|p| self.index[**p].is_some()) {
+                let mut runner = Some(*pred);
+                while let Some(r) = runner.filter(|r| Some(*r) != self.idom[k]) {
+                    frontiers[r].insert(k);
+                    runner = self.idom[r];
+                }
+            }
+        }
+        frontiers
+    }
+
+    /// the ids which need a phi at the beginning of every block
+    fn place_phis(&self) -> Vec<Vec<&'a str>> {
+        let (live_in, frontiers) = (self.live_in(), self.frontiers());
+        let mut def_blocks: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for k in (0..self.cfg.blocks.len()).filter(|k| self.index[*k].is_some()) {
+            for id in self.block_codes(k).iter().filter_map(|code| code.def()).filter(|id| self.renamed(id)) {
+                let blocks = def_blocks.entry(id).or_default();
+                if !blocks.contains(&k) {
+                    blocks.push(k);
+                }
+            }
+        }
+
+        let mut phis = vec![Vec::new(); self.cfg.blocks.len()];
+        for (id, mut work) in def_blocks {
+            let mut placed = BTreeSet::new();
+            while let Some(k) = work.pop() {
+                for f in &frontiers[k] {
+                    if live_in[*f].contains(id) && placed.insert(*f) {
+                        phis[*f].push(id);
+                        work.push(*f);
+                    }
+                }
+            }
+        }
+        phis
+    }
+
+    fn build(self) -> SsaFunction<'a> {
+        let cfg = self.cfg;
+        let phis = self.place_phis();
+        let mut fresh = Fresh::new(self.codes[cfg.start..cfg.end].iter().flat_map(|code| {
+            let mut vars = code.operands();
+            vars.extend(code.target());
+            vars.into_iter().filter_map(|var| var.get_id()).collect::<Vec<_>>()
+        }));
+
+        let index = |k: usize| self.index[k].unwrap();
+        let mut blocks: Vec<SsaBlock> = (0..cfg.blocks.len()).filter(|k| self.index[*k].is_some())
+            .map(|k| {
+                let block = &cfg.blocks[k];
+                let phis = phis[k].iter().map(|var| Phi { var, target: var, args: Vec::new() }).collect();
+                // an unreachable predecessor is removed
+                let preds = block.preds.iter().filter(|p| self.index[**p].is_some()).map(|p| index(*p)).collect();
+                let succs = block.succs.iter().map(|s| index(*s)).collect();
+                SsaBlock { phis, codes: Vec::new(), preds, succs }
+            })
+            .collect();
+
+        // rename along the dominator tree, the stack of an id has its current name on the top
+        let mut children = vec![Vec::new(); cfg.blocks.len()];
+        for (k, idom) in self.idom.iter().enumerate() {
+            if let Some(d) = idom {
+                children[*d].push(k);
+            }
+        }
+        let mut stacks: BTreeMap<&'a str, Vec<&'a str>> = BTreeMap::new();
+        // the block to enter, or the ids to pop when leaving a block
+        let mut work: Vec<Result<usize, Vec<&'a str>>> = cfg.roots().map(Ok).collect();
+        while let Some(item) = work.pop() {
+            let k = match item {
+                Ok(k) => k,
+                Err(pushed) => {
+                    pushed.iter().for_each(|id| { stacks.get_mut(id).unwrap().pop(); });
+                    continue
+                }
+            };
+            let b = index(k);
+            let mut pushed = Vec::new();
+
+            for phi in blocks[b].phis.iter_mut() {
+                phi.target = fresh.name(phi.var);
+                stacks.entry(phi.var).or_default().push(phi.target);
+                pushed.push(phi.var);
+            }
+            for code in self.block_codes(k) {
+                // an id never assigned before keeps its name
+                let mut code = rename_uses(code, |id| {
+                    stacks.get(id).and_then(|stack| stack.last().copied()).unwrap_or(id)
+                });
+                if let Some(id) = code.def().filter(|id| self.renamed(id)) {
+                    let name = fresh.name(id);
+                    code = rename_def(&code, name);
+                    stacks.entry(id).or_default().push(name);
+                    pushed.push(id);
+                }
+                blocks[b].codes.push(code);
+            }
+
+            for s in cfg.blocks[k].succs.clone() {
+                for phi in blocks[index(s)].phis.iter_mut() {
+                    let value = match stacks.get(phi.var).and_then(|stack| stack.last()) {
+                        Some(name) => Variable::Id(name),
+                        None => Variable::Number(0),
+                    };
+                    phi.args.push((b, value));
+                }
+            }
+
+            work.push(Err(pushed));
+            work.extend(children[k].iter().rev().map(|c| Ok(*c)));
+        }
+
+        SsaFunction { name: cfg.name, blocks }
+    }
+}
+
+/// convert the functions back into IR by copying the value of phi at the end of predecessors
+///
+/// a taken `IF` to a block with phis gets a new block for the copies,
+/// and the copies of a phi group are done by temporaries if one reads another's target
+pub fn destruct<'a>(funcs: &[SsaFunction<'a>]) -> Vec<Sentence<'a>> {
+    let labels = funcs.iter()
+        .flat_map(|func| func.blocks.iter().flat_map(|block| block.codes.iter()))
+        .filter_map(|code| match code {
+            Sentence::Label(label) => Some(*label),
+            _ => None
+        });
+    // the labels are global in the program
+    let mut fresh_labels = Fresh::new(labels);
+    funcs.iter().flat_map(|func| destruct_function(func, &mut fresh_labels)).collect()
+}
+
+fn destruct_function<'a>(func: &SsaFunction<'a>, fresh_labels: &mut Fresh) -> Vec<Sentence<'a>> {
+    let blocks = &func.blocks;
+    let mut ids = Vec::new();
+    for code in blocks.iter().flat_map(|block| block.codes.iter()) {
+        let mut vars = code.operands();
+        vars.extend(code.target());
+        ids.extend(vars.into_iter().filter_map(|var| var.get_id()));
+    }
+    ids.extend(blocks.iter().flat_map(|block| block.phis.iter().map(|phi| phi.target)));
+    let mut fresh = Fresh::new(ids);
+
+    let label_of = |k: usize| match blocks[k].codes.first() {
+        Some(Sentence::Label(label)) => Some(*label),
+        _ => None
+    };
+    // the copies on the edge from `p` to `s`
+    let mut copies = |p: usize, s: usize| {
+        let pairs: Vec<(&'a str, Variable<'a>)> = blocks[s].phis.iter()
+            .filter_map(|phi| phi.args.iter().find(|(q, _)| *q == p).map(|(_, var)| (phi.target, var.clone())))
+            .filter(|(target, var)| *var != Variable::Id(target))
+            .collect();
+        let clash = pairs.iter().any(|(_, var)| pairs.iter().any(|(target, _)| *var == Variable::Id(target)));
+        if !clash {
+            return pairs.into_iter().map(|(target, var)| Sentence::Assign { target: Variable::Id(target), var }).collect()
+        }
+        let temps: Vec<&str> = pairs.iter().map(|(target, _)| fresh.name(target)).collect();
+        let mut codes: Vec<Sentence> = pairs.iter().zip(&temps)
+            .map(|((_, var), temp)| Sentence::Assign { target: Variable::Id(temp), var: var.clone() })
+            .collect();
+        codes.extend(pairs.iter().zip(&temps)
+            .map(|((target, _), temp)| Sentence::Assign { target: Variable::Id(target), var: Variable::Id(temp) }));
+        codes
+    };
+
+    // 1. the blocks on the taken edges of `IF`, they are placed before the target
+    let mut splits: Vec<Vec<Sentence<'a>>> = vec![Vec::new(); blocks.len()];
+    let mut branches: Vec<Option<Sentence<'a>>> = vec![None; blocks.len()];
+    for (k, block) in blocks.iter().enumerate() {
+        if let Some(Sentence::IfGoto { l, r, opt, label }) = block.codes.last() {
+            // the label may be out of this function
+            let Some(target) = block.succs.iter().copied().find(|s| label_of(*s) == Some(*label)) else { continue };
+            let edge = copies(k, target);
+            if edge.is_empty() {
+                continue
+            }
+            let split = fresh_labels.name(label);
+            splits[target].push(Sentence::Label(split));
+            splits[target].extend(edge);
+            splits[target].push(Sentence::Goto(label));
+            branches[k] = Some(Sentence::IfGoto { l: l.clone(), r: r.clone(), opt: *opt, label: split });
+        }
+    }
+
+    // 2. the blocks in order with the copies at the end
+    let mut result: Vec<Sentence> = Vec::new();
+    for (k, block) in blocks.iter().enumerate() {
+        if !splits[k].is_empty() {
+            // the code before doesn't run into the split blocks
+            if !matches!(result.last(), Some(Sentence::Goto(_) | Sentence::Return(_))) {
+                result.push(Sentence::Goto(label_of(k).unwrap()));
+            }
+            let mut split = std::mem::take(&mut splits[k]);
+            // the last split block falls through
+            split.pop();
+            result.extend(split);
+        }
+
+        let (last, body) = block.codes.split_last().unwrap();
+        result.extend(body.iter().cloned());
+        let next = (k + 1 < blocks.len()).then_some(k + 1);
+        match last {
+            Sentence::Return(_) => result.push(last.clone()),
+            Sentence::Goto(_) => {
+                // the label may be out of this function
+                if let Some(succ) = block.succs.first() {
+                    result.extend(copies(k, *succ));
+                }
+                result.push(last.clone());
+            }
+            Sentence::IfGoto { .. } => {
+                result.push(branches[k].take().unwrap_or_else(|| last.clone()));
+                if let Some(next) = next.filter(|next| block.succs.contains(next)) {
+                    result.extend(copies(k, next));
+                }
+            }
+            _ => {
+                result.push(last.clone());
+                if let Some(next) = next {
+                    result.extend(copies(k, next));
+                }
+            }
+        }
+    }
+
+    declare_before_use(result)
+}
+
+/// the interpreter checks the ids in the order of source, but a dominating assignment
+/// may come later in the source, so these ids are assigned `#0` at the beginning
+fn declare_before_use(mut codes: Vec<Sentence>) -> Vec<Sentence> {
+    let taken = address_taken(&codes);
+    let (mut defined, mut undeclared) = (BTreeSet::new(), Vec::new());
+    for code in &codes {
+        for id in code.uses() {
+            if !defined.contains(id) && !taken.contains(&id) && !undeclared.contains(&id) {
+                undeclared.push(id);
+            }
+        }
+        defined.extend(code.def());
+    }
+
+    let start = 1 + codes[1..].iter().take_while(|code| matches!(code, Sentence::Param(_))).count();
+    let init = undeclared.into_iter().map(|id| Sentence::Assign { target: Variable::Id(id), var: Variable::Number(0) });
+    codes.splice(start..start, init);
+    codes
+}
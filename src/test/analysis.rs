@@ -0,0 +1,28 @@
+use crate::analysis::uninitialized_reads;
+use super::utils::parse;
+
+#[test]
+fn test_uninitialized_reads() {
+    let codes = parse("
+        FUNCTION main :
+        READ a
+        IF a > #0 GOTO l1
+        GOTO l2
+        LABEL l1 :
+        b := #1
+        LABEL l2 :
+        WRITE b
+        LABEL l3 :
+        IF a > #5 GOTO l4
+        c := #2
+        e := &c
+        LABEL l4 :
+        a := a - #1
+        d := c
+        IF a > #0 GOTO l3
+        RETURN d
+        WRITE f
+    ");
+    let lines: Vec<usize> = uninitialized_reads(&codes).iter().map(|w| w.i).collect();
+    assert_eq!(lines, vec![7, 14]);
+}
@@ -0,0 +1,397 @@
+use std::{collections::VecDeque, io::Write, process::{Command, Stdio}};
+
+use wasmtime::{Engine, Linker, Module, Store};
+
+use crate::{
+    backend::{c, llvm, mips, wasm, sim::Simulator},
+    error::RuntimeErrorKind,
+    utils::io::{read_from_values, write_to_buffer},
+};
+use super::utils::{parse, run};
+
+/// run the assembly and return the output and the running count
+fn simulate(asm: &str, input: &[i32]) -> (String, usize) {
+    let (output, write_func) = write_to_buffer();
+    let read_func = read_from_values(input.iter().map(|i| i.to_string()).collect());
+    let mut simulator = Simulator::new(asm, read_func, write_func).unwrap();
+    let count = simulator.run().unwrap_or_else(|err| panic!("{}\n{}", err, asm));
+    let output = output.borrow().clone();
+    (output, count)
+}
+
+/// the output of the program and its MIPS translation should be the same
+fn crosscheck(source: &str, input: &[i32]) {
+    let codes = parse(source);
+    let asm = mips::generate(&codes);
+    let (expected, _) = run(codes, input);
+    let (actual, _) = simulate(&asm, input);
+    assert_eq!(expected, actual, "assembly:\n{}", asm);
+}
+
+#[test]
+fn test_mips() {
+    let codes = parse("
+        FUNCTION add :
+        PARAM a
+        PARAM b
+        t := a + b
+        RETURN t
+        FUNCTION main :
+        DEC arr 8
+        p := &arr
+        READ x
+        *p := x
+        ARG #2
+        ARG *p
+        r := CALL add
+        IF r != #0 GOTO out
+        r := #1
+        LABEL out :
+        WRITE r
+        RETURN #0
+    ");
+    let asm = mips::generate(&codes);
+    let lines: Vec<&str> = asm.lines().map(|line| line.trim()).collect();
+    let position = |inst: &str| match lines.iter().position(|line| *line == inst) {
+        Some(k) => k,
+        None => panic!("`{}` not found in\n{}", inst, asm),
+    };
+
+    // the first PARAM gets the last ARG
+    assert!(position("lw $t8, 8($fp)") < position("lw $t8, 12($fp)"));
+    assert!(position("f_add:") < position("main:"));
+    // the array takes 8 bytes in the frame
+    position("addi $t8, $fp, -8");
+    position("jal f_add");
+    position("addi $sp, $sp, 8");
+    // the registers are written back before the branch
+    assert!(lines[position("bne $t0, $t9, l_out") - 1].starts_with("sw "));
+    position("li $v0, 5");
+    position("li $v0, 10");
+}
+
+#[test]
+fn test_simulator() {
+    let (output, count) = simulate("
+        .data
+        msg: .asciiz \"sum = \"  # a comment
+        .text
+        main:
+          li $v0, 5
+          syscall
+          move $t0, $v0
+          li $t1, 0
+        loop:
+          beqz $t0, end
+          add $t1, $t1, $t0
+          addi $t0, $t0, -1
+          j loop
+        end:
+          la $a0, msg
+          li $v0, 4
+          syscall
+          move $a0, $t1
+          li $v0, 1
+          syscall
+          jr $ra
+    ", &[4]);
+    assert_eq!(output, "sum = 10");
+    assert_eq!(count, 4 + 4 * 4 + 1 + 7);
+
+    let (_, write_func) = write_to_buffer();
+    let err = Simulator::new("main:\n  foo $t0", read_from_values(vec![]), write_func).err().unwrap();
+    assert_eq!(err.line(), 2);
+
+    let (_, write_func) = write_to_buffer();
+    let mut simulator = Simulator::new("main:\n  lw $t0, 1($sp)", read_from_values(vec![]), write_func).unwrap();
+    let err = simulator.run().unwrap_err();
+    assert!(matches!(err.kind(), RuntimeErrorKind::MemoryError));
+    assert_eq!(err.line(), 2);
+}
+
+/// the programs with their input to check the backends
+const PROGRAMS: [(&str, &[i32]); 5] = [
+    // recursion and arguments
+    ("
+        FUNCTION fact :
+        PARAM n
+        IF n > #1 GOTO rec
+        RETURN #1
+        LABEL rec :
+        t1 := n - #1
+        ARG t1
+        t2 := CALL fact
+        t3 := n * t2
+        RETURN t3
+        FUNCTION sub :
+        PARAM a
+        PARAM b
+        c := a - b
+        RETURN c
+        FUNCTION main :
+        READ x
+        ARG x
+        r := CALL fact
+        WRITE r
+        ARG #3
+        ARG #10
+        d := CALL sub
+        WRITE d
+        RETURN #0
+    ", &[6]),
+    // arrays and pointers
+    ("
+        FUNCTION main :
+        DEC arr 20
+        i := #0
+        LABEL fill :
+        IF i >= #5 GOTO done
+        off := i * #4
+        p := &arr + off
+        v := i * i
+        *p := v
+        i := i + #1
+        GOTO fill
+        LABEL done :
+        x := #7
+        q := &x
+        *q := #9
+        WRITE x
+        p := &arr + #12
+        WRITE *p
+        s := #0
+        i := #0
+        LABEL sum :
+        IF i == #5 GOTO end
+        off := i * #4
+        p := &arr + off
+        s := s + *p
+        i := i + #1
+        GOTO sum
+        LABEL end :
+        WRITE s
+        RETURN #0
+    ", &[]),
+    // more variables than registers
+    ("
+        FUNCTION main :
+        READ a
+        b := a + #1
+        c := b + #2
+        d := c + #3
+        e := d + #4
+        f := e + #5
+        g := f + #6
+        h := g + #7
+        i := h + #8
+        j := i + #9
+        k := a * j
+        l := k / #3
+        m := b - l
+        WRITE a
+        WRITE e
+        WRITE j
+        WRITE k
+        WRITE l
+        WRITE m
+        RETURN #0
+    ", &[-5]),
+    // nested loops and a branch out of the inner loop
+    ("
+        FUNCTION main :
+        READ n
+        i := #1
+        LABEL outer :
+        IF i > n GOTO done
+        j := #1
+        LABEL inner :
+        IF j > i GOTO next
+        t := i * j
+        IF t == #6 GOTO skip
+        WRITE t
+        LABEL skip :
+        j := j + #1
+        GOTO inner
+        LABEL next :
+        i := i + #1
+        GOTO outer
+        LABEL done :
+        IF n != #3 GOTO early
+        WRITE #100
+        LABEL early :
+        RETURN n
+    ", &[3]),
+    // an irreducible loop entered from both a and b
+    ("
+        FUNCTION main :
+        READ x
+        IF x > #5 GOTO b
+        LABEL a :
+        x := x - #1
+        WRITE x
+        IF x < #0 GOTO end
+        LABEL b :
+        x := x - #2
+        WRITE x
+        IF x > #0 GOTO a
+        LABEL end :
+        RETURN #0
+    ", &[9]),
+];
+
+#[test]
+fn test_crosscheck() {
+    for (source, input) in PROGRAMS {
+        crosscheck(source, input);
+    }
+}
+
+#[test]
+fn test_c() {
+    // the system compiler is needed to run the C translation
+    let dir = std::env::temp_dir().join(format!("irsim-c-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for (k, (source, input)) in PROGRAMS.iter().enumerate() {
+        let codes = parse(source);
+        let (src, exe) = (dir.join(format!("{}.c", k)), dir.join(k.to_string()));
+        std::fs::write(&src, c::generate(&codes).unwrap()).unwrap();
+
+        let status = match Command::new("cc").args(["-std=c99", "-o"]).arg(&exe).arg(&src).status() {
+            Ok(status) => status,
+            Err(_) => { eprintln!("cc not found, skip test_c"); return }
+        };
+        assert!(status.success(), "can't compile {}", src.display());
+
+        let (expected, _) = run(codes, input);
+        assert_eq!(run_command(&mut Command::new(&exe), input), expected, "program:\n{}", source);
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// run the command with the input in stdin and return its stdout
+fn run_command(command: &mut Command, input: &[i32]) -> String {
+    let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    let text: Vec<String> = input.iter().map(|i| i.to_string()).collect();
+    child.stdin.take().unwrap().write_all(text.join(" ").as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    String::from_utf8_lossy(&output.stdout).into()
+}
+
+#[test]
+fn test_llvm() {
+    // `lli` is needed to run the LLVM IR, and LLVM 14 needs a flag for opaque pointers
+    let flags = match Command::new("lli").arg("--version").output() {
+        Ok(output) if String::from_utf8_lossy(&output.stdout).contains("version 14") => vec!["-opaque-pointers"],
+        Ok(_) => vec![],
+        Err(_) => { eprintln!("lli not found, skip test_llvm"); return }
+    };
+    let dir = std::env::temp_dir().join(format!("irsim-llvm-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for (k, (source, input)) in PROGRAMS.iter().enumerate() {
+        let codes = parse(source);
+        let ll = dir.join(format!("{}.ll", k));
+        std::fs::write(&ll, llvm::generate(&codes).unwrap()).unwrap();
+
+        let (expected, _) = run(codes, input);
+        let actual = run_command(Command::new("lli").args(&flags).arg(&ll), input);
+        assert_eq!(actual, expected, "program:\n{}", source);
+
+        // the optimized one gives the same output and counts its instructions
+        let optimized = dir.join(format!("{}.opt.ll", k));
+        assert!(Command::new("opt").args(&flags).args(["-O2", "-S", "-o"]).arg(&optimized).arg(&ll).status().unwrap().success());
+        let counted = llvm::instrument(&std::fs::read_to_string(&optimized).unwrap());
+        std::fs::write(&optimized, counted).unwrap();
+        let mut child = Command::new("lli").args(&flags).arg(&optimized)
+            .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped())
+            .spawn().unwrap();
+        let text: Vec<String> = input.iter().map(|i| i.to_string()).collect();
+        child.stdin.take().unwrap().write_all(text.join(" ").as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected, "program:\n{}", source);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let count: usize = stderr.trim().strip_prefix("instructions: ").unwrap().parse().unwrap();
+        assert!(count > 0);
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_instrument() {
+    let ll = "\
+define i32 @main() {
+  %a = call i32 @f()
+  br label %next
+
+next:                                             ; preds = %0
+  %b = phi i32 [ %a, %0 ]
+  call void @llvm.lifetime.start.p0(i64 4, ptr null)
+  ret i32 %b
+}
+";
+    let counted = llvm::instrument(ll);
+    assert!(counted.starts_with("\
+define i32 @irsim_main() {
+  %irsim.0 = atomicrmw add ptr @irsim.count, i64 2 monotonic
+  %a = call i32 @f()
+  br label %next
+
+next:                                             ; preds = %0
+  %b = phi i32 [ %a, %0 ]
+  %irsim.1 = atomicrmw add ptr @irsim.count, i64 1 monotonic
+  call void @llvm.lifetime.start.p0(i64 4, ptr null)
+"), "{}", counted);
+    assert!(counted.contains("define i32 @main() {"));
+}
+
+/// run the wat with the input and return the output
+fn run_wasm(wat: &str, input: &[i32]) -> String {
+    let engine = Engine::default();
+    let module = Module::new(&engine, wat).unwrap_or_else(|e| panic!("{}\n{}", e, wat));
+    let mut store = Store::new(&engine, (input.iter().copied().collect::<VecDeque<i32>>(), String::new()));
+
+    let mut linker = Linker::new(&engine);
+    linker.func_wrap("env", "read", |mut caller: wasmtime::Caller<'_, (VecDeque<i32>, String)>| {
+        caller.data_mut().0.pop_front().unwrap()
+    }).unwrap();
+    linker.func_wrap("env", "write", |mut caller: wasmtime::Caller<'_, (VecDeque<i32>, String)>, value: i32| {
+        caller.data_mut().1.push_str(&format!("{}\n", value));
+    }).unwrap();
+
+    let instance = linker.instantiate(&mut store, &module).unwrap();
+    let main = instance.get_typed_func::<(), i32>(&mut store, "main").unwrap();
+    main.call(&mut store, ()).unwrap();
+    store.into_data().1
+}
+
+#[test]
+fn test_wasm() {
+    for (source, input) in PROGRAMS {
+        let codes = parse(source);
+        let wat = wasm::generate(&codes).unwrap();
+        let (expected, _) = run(codes, input);
+        assert_eq!(run_wasm(&wat, input), expected, "wat:\n{}", wat);
+    }
+
+    // the reducible graph doesn't need the dispatching loop
+    let wat = wasm::generate(&parse(PROGRAMS[3].0)).unwrap();
+    assert!(wat.contains("loop") && !wat.contains("br_table"));
+    let wat = wasm::generate(&parse(PROGRAMS[4].0)).unwrap();
+    assert!(wat.contains("br_table"));
+}
+
+#[test]
+fn test_foreign_jump() {
+    // the labels are global for the interpreter, but a function is a function in the backends
+    let codes = parse("
+        FUNCTION f :
+        LABEL out :
+        RETURN #1
+        FUNCTION main :
+        GOTO out
+    ");
+    for err in [c::generate(&codes), wasm::generate(&codes), llvm::generate(&codes)].map(Result::unwrap_err) {
+        assert_eq!(err.line(), 4);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::cfg::{build, to_dot};
+use super::utils::parse;
+
+#[test]
+fn test_build() {
+    let codes = parse("
+        FUNCTION f :
+        PARAM n
+        IF n > #1 GOTO l1
+        RETURN #1
+        LABEL l1 :
+        t1 := n - #1
+        ARG t1
+        t2 := CALL f
+        GOTO l1
+        WRITE t2
+        FUNCTION main :
+        ARG #3
+        r := CALL f
+        RETURN r
+    ");
+    let cfgs = build(&codes);
+    assert_eq!(cfgs.len(), 2);
+
+    let f = &cfgs[0];
+    let edges: Vec<(usize, usize, &Vec<usize>)> = f.blocks.iter()
+        .map(|block| (block.start, block.end, &block.succs))
+        .collect();
+    assert_eq!(edges, vec![
+        (0, 3, &vec![1, 2]),
+        (3, 4, &vec![]),
+        (4, 9, &vec![2]),
+        (9, 10, &vec![]),
+    ]);
+    assert_eq!(f.blocks[2].preds, vec![0, 2]);
+    assert_eq!(f.blocks[2].calls, vec!["f"]);
+    assert_eq!(f.reachable(), vec![true, true, true, false]);
+    assert_eq!(f.reverse_postorder(), vec![0, 2, 1]);
+    let idom = f.dominators();
+    assert_eq!(idom, vec![None, Some(0), Some(0), None]);
+    assert!(f.dominates(&idom, 0, 2) && !f.dominates(&idom, 1, 2));
+    assert_eq!(cfgs[1].blocks[0].calls, vec!["f"]);
+
+    let dot = to_dot(&codes, &cfgs);
+    assert!(dot.contains("\"f_0\" -> \"f_2\";"));
+    assert!(dot.contains("\"main_0\" -> \"f_0\" [style=dashed];"));
+}
+
+#[test]
+fn test_foreign_entry() {
+    // the labels are global, `l9` is only reached from `main`
+    let codes = parse("
+        FUNCTION f :
+        RETURN #2
+        LABEL l9 :
+        WRITE #5
+        RETURN #1
+        FUNCTION main :
+        IF #1 > #0 GOTO l9
+        GOTO l9
+    ");
+    let cfgs = build(&codes);
+    let f = &cfgs[0];
+    assert_eq!(f.entries, vec![1]);
+    assert!(cfgs[1].entries.is_empty());
+    assert_eq!(f.reachable(), vec![true, true]);
+    assert_eq!(f.reverse_postorder(), vec![1, 0]);
+    assert_eq!(f.dominators(), vec![None, None]);
+}
@@ -0,0 +1,194 @@
+use core::{
+    error::{
+        CompileError, CompileErrorKind,
+        InterpreterError as IError, InterpreterErrorKind,
+        RuntimeError as RError, RuntimeErrorKind,
+    },
+    interpreter::Interpreter,
+    utils::{io::{read_from_values, write_to_buffer}, json::Json},
+};
+use lalrpop_util::ParseError;
+
+use crate::code::*;
+
+/// run the source like `irsim-cli FILE` and return the output and the exit code
+fn run(source: &str, exit_with_return: bool) -> (String, i32) {
+    let lines: Vec<&str> = source.lines().map(str::trim).collect();
+    let (output, write_func) = write_to_buffer();
+    let interpreter = Interpreter::from_lines(&lines, read_from_values(vec![]), write_func).unwrap();
+    let result = interpreter.run();
+    let code = crate::exit_code(&result, &interpreter, exit_with_return);
+    let output = output.borrow().clone();
+    (output, code)
+}
+
+#[test]
+fn test_of_static() {
+    let parse = IError::new_err::<()>(InterpreterErrorKind::ParseError(ParseError::InvalidToken { location: 0 }), 0);
+    assert_eq!(of_static(&parse.unwrap_err()), PARSE_ERROR);
+    [
+        InterpreterErrorKind::IRSyntaxError,
+        InterpreterErrorKind::UndefinedLabelError,
+        InterpreterErrorKind::UndefinedVariableError,
+        InterpreterErrorKind::DuplicatedFuncError,
+    ].into_iter().for_each(|kind| {
+        assert_eq!(of_static(&IError::new_err::<()>(kind, 0).unwrap_err()), SEMANTIC_ERROR)
+    });
+}
+
+#[test]
+fn test_of_compile() {
+    [
+        (CompileErrorKind::LexicalError, PARSE_ERROR),
+        (CompileErrorKind::SyntaxError, PARSE_ERROR),
+        (CompileErrorKind::SemanticError(1), SEMANTIC_ERROR),
+        (CompileErrorKind::UnsupportedError, SEMANTIC_ERROR),
+    ].into_iter().for_each(|(kind, code)| {
+        assert_eq!(of_compile(&CompileError::new_err::<()>(kind, String::new(), 1).unwrap_err()), code)
+    });
+}
+
+#[test]
+fn test_of_runtime() {
+    [
+        (RuntimeErrorKind::StepLimitError, LIMIT_EXCEEDED),
+        (RuntimeErrorKind::InputError, RUNTIME_ERROR),
+        (RuntimeErrorKind::InputExhaustedError, RUNTIME_ERROR),
+        (RuntimeErrorKind::MemoryError, RUNTIME_ERROR),
+        (RuntimeErrorKind::DivideByZeroError, RUNTIME_ERROR),
+        (RuntimeErrorKind::NoReturnError, RUNTIME_ERROR),
+        (RuntimeErrorKind::ArgumentError, RUNTIME_ERROR),
+    ].into_iter().for_each(|(kind, code)| {
+        assert_eq!(of_runtime(&RError::new_err::<()>(kind, 0).unwrap_err()), code)
+    });
+}
+
+#[test]
+fn test_runtime_exit() {
+    assert_eq!(run("FUNCTION main :\nWRITE #1\nRETURN #0", false), ("1\n".into(), SUCCESS));
+    // dividing by zero and running past the end are errors, not panics
+    assert_eq!(run("FUNCTION main :\nt0 := #0\nt1 := #1 / t0\nRETURN t1", false).1, RUNTIME_ERROR);
+    assert_eq!(run("FUNCTION main :\nWRITE #1", false), ("1\n".into(), RUNTIME_ERROR));
+    assert_eq!(run("FUNCTION main :\nPARAM x\nWRITE x\nRETURN #0", false), ("".into(), RUNTIME_ERROR));
+    // the arithmetic wraps around like the backends
+    assert_eq!(run("FUNCTION main :\nx := #2147483647\nx := x + #1\nWRITE x\nRETURN #0", false), ("-2147483648\n".into(), SUCCESS));
+    // the memory runs out in the endless recursion
+    assert_eq!(run("FUNCTION f :\nDEC a 40\nt := CALL f\nRETURN t\nFUNCTION main :\nt := CALL f\nRETURN t", false).1, RUNTIME_ERROR);
+}
+
+#[test]
+fn test_exit_with_return() {
+    assert_eq!(run("FUNCTION main :\nRETURN #42", true).1, 42);
+    assert_eq!(run("FUNCTION main :\nRETURN #42", false).1, SUCCESS);
+    // the returned value isn't told apart from the error codes
+    assert_eq!(run("FUNCTION main :\nRETURN #5", true).1, RUNTIME_ERROR);
+    assert_eq!(run("FUNCTION main :\nRETURN #261", true).1 as u8 as i32, RUNTIME_ERROR);
+    // an error still wins over the returned value
+    assert_eq!(run("FUNCTION main :\nt0 := #0\nt1 := #1 / t0\nRETURN #0", true).1, RUNTIME_ERROR);
+}
+
+#[test]
+fn test_read_input() {
+    let values = |v: &[&str]| Some(v.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+    // `--args` is split by commas, the spaces around the values are trimmed
+    assert_eq!(crate::read_input(None, Some("1, 2,3 ,,".into())).unwrap(), values(&["1", "2", "3"]));
+    assert_eq!(crate::read_input(None, Some("1 2,3".into())).unwrap(), values(&["1 2", "3"]));
+    assert_eq!(crate::read_input(None, None).unwrap(), None);
+
+    // `--input` is split by whitespace, and wins over `--args`
+    let path = std::env::temp_dir().join(format!("irsim-input-{}.txt", std::process::id()));
+    std::fs::write(&path, "1 2\n  3\n\n-4\t5\n").unwrap();
+    let input = Some(path.to_string_lossy().into_owned());
+    assert_eq!(crate::read_input(input.clone(), Some("9".into())).unwrap(), values(&["1", "2", "3", "-4", "5"]));
+    std::fs::remove_file(&path).unwrap();
+
+    // the file is gone now
+    let (message, code) = crate::read_input(input, None).unwrap_err();
+    assert_eq!(code, IO_ERROR);
+    assert!(message.starts_with("can't read "), "{}", message);
+}
+
+fn keys(json: &Json) -> Vec<&'static str> {
+    match json {
+        Json::Object(fields) => fields.iter().map(|(key, _)| *key).collect(),
+        _ => panic!("not an object: {}", json),
+    }
+}
+
+#[test]
+fn test_report() {
+    let err = IError::new_err::<()>(InterpreterErrorKind::UndefinedLabelError, 3).unwrap_err();
+    let static_error = crate::report::static_error(&err);
+    let result: Result<usize, RError> = Ok(4);
+    let ok = crate::report::run_result(&result, SUCCESS, "1\n", &[], None);
+    assert_eq!(keys(&static_error), keys(&ok));
+    assert!(static_error.to_string().ends_with(r#""warnings":[]}"#), "{}", static_error);
+
+    // a runtime error is reported with the output written before it
+    let source = "FUNCTION main :\nWRITE #1\nt0 := #0\nt1 := #1 / t0\nRETURN #0";
+    let lines: Vec<&str> = source.lines().collect();
+    let (output, write_func) = write_to_buffer();
+    let interpreter = Interpreter::from_lines(&lines, read_from_values(vec![]), write_func).unwrap();
+    let result = interpreter.run();
+    let code = crate::exit_code(&result, &interpreter, false);
+    let report = crate::report::run_result(&result, code, &output.borrow(), &[], None);
+    assert_eq!(keys(&report), keys(&ok));
+    assert_eq!(report.to_string(), concat!(
+        r#"{"status":"runtime_error","exit_code":5,"count":null,"output":"1\n","#,
+        r#""error":{"kind":"DivideByZeroError","line":4,"message":"Runtime error at line 4: divided by zero"},"#,
+        r#""warnings":[]}"#,
+    ));
+}
+
+#[test]
+fn test_golden() {
+    use crate::golden::{collect_cases, run_cases, Options, Outcome};
+
+    let dir = std::env::temp_dir().join(format!("irsim-golden-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str, text: &str| std::fs::write(dir.join(name), text).unwrap();
+    write("a_pass.ir", "FUNCTION main :\nREAD x\nWRITE x\nRETURN #0\n");
+    write("a_pass.in", "7\n");
+    write("a_pass.out", "7\n");
+    write("b_wrong.ir", "FUNCTION main :\nWRITE #1\nRETURN #0\n");
+    write("b_wrong.out", "2\n");
+    // the runtime errors fail their own cases only
+    write("c_zero.ir", "FUNCTION main :\nt0 := #0\nt1 := #1 / t0\nRETURN #0\n");
+    write("c_zero.out", "");
+    write("d_loop.ir", "FUNCTION main :\nLABEL l :\nGOTO l\n");
+    write("d_loop.out", "");
+    write("e_missing.ir", "FUNCTION main :\nRETURN #0\n");
+
+    let cases = collect_cases(&dir).unwrap();
+    let options = Options { jobs: 2, budget: None, max_steps: 100 };
+    let outcomes = run_cases(&cases, &options);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(outcomes.len(), 5);
+    assert_eq!(outcomes[0], Outcome::Pass(4));
+    let reasons: Vec<String> = outcomes[1..].iter().map(|outcome| match outcome {
+        Outcome::Fail(reason) => reason.clone(),
+        Outcome::Pass(_) => panic!("{:?}", outcome),
+    }).collect();
+    assert_eq!(reasons[0], "  line 1:\n    - 2\n    + 1\n");
+    assert!(reasons[1].contains("divided by zero"), "{}", reasons[1]);
+    assert!(reasons[2].contains("instruction count exceeds the limit"), "{}", reasons[2]);
+    assert!(reasons[3].contains("can't read"), "{}", reasons[3]);
+}
+
+#[test]
+fn test_read_function() {
+    use core::{ast::Sentence, interpreter::parser::LineParser};
+    use crate::repl::read_function;
+
+    let parser = LineParser::new();
+    let head = || Sentence::Func("f");
+    let mut lines = ["WRITE #1", "", "RETURN #0", "WRITE #2", "RETURN #0", "", "WRITE #3"].into_iter();
+    let codes = read_function(&parser, head(), || lines.next()).unwrap();
+    assert_eq!(codes, vec![head(), Sentence::Write(core::ast::Variable::Number(1))]);
+
+    // a bad line drops the function, but its body is still consumed
+    let mut lines = ["WRITE #1", "WRITE", "RETURN #0", "", "WRITE #3"].into_iter();
+    assert_eq!(read_function(&parser, head(), || lines.next()), None);
+    assert_eq!(lines.next(), Some("WRITE #3"));
+}
@@ -0,0 +1,77 @@
+use crate::diff::{compare, random_inputs, run, Divergence, Outcome, Status};
+use super::utils::parse;
+
+const PROGRAM: &str = "
+    FUNCTION main :
+    READ n
+    i := #0
+    LABEL top :
+    IF i >= n GOTO end
+    WRITE i
+    i := i + #1
+    GOTO top
+    LABEL end :
+    t := #100 / n
+    WRITE t
+    RETURN #0
+";
+
+fn input(values: &[i32]) -> Vec<String> {
+    values.iter().map(|n| n.to_string()).collect()
+}
+
+#[test]
+fn test_run() {
+    let codes = parse(PROGRAM);
+    let outcome = run(&codes, &input(&[3]), None);
+    assert_eq!(outcome.output, "0\n1\n2\n33\n");
+    assert!(matches!(outcome.status, Status::Exited(_)));
+
+    let outcome = run(&codes, &input(&[0]), None);
+    assert!(matches!(outcome.status, Status::Failed { kind: "DivideByZeroError", .. }), "{:?}", outcome.status);
+
+    let outcome = run(&codes, &[], None);
+    assert!(matches!(outcome.status, Status::Failed { kind: "InputExhaustedError", .. }));
+    let outcome = run(&codes, &input(&[1000]), Some(100));
+    assert!(matches!(outcome.status, Status::Failed { kind: "StepLimitError", .. }));
+    assert!(outcome.output.starts_with("0\n1\n"));
+}
+
+#[test]
+fn test_compare() {
+    let a = parse(PROGRAM);
+    // the loop starts from 1 and the division is by 50
+    let source = PROGRAM.replace("i := #0", "i := #1").replace("#100 / n", "#50 / n");
+    let b = parse(&source);
+
+    let (x, y) = (run(&a, &input(&[3]), None), run(&b, &input(&[3]), None));
+    assert_eq!(compare(&x, &y), Some(Divergence::Output { line: 0, a: Some("0".into()), b: Some("1".into()) }));
+    assert_eq!(compare(&x, &y).unwrap().to_string(), "the output diverges at line 1: `0` vs `1`");
+
+    // `b` writes nothing but `a` writes `0` before its division
+    let (x, y) = (run(&a, &input(&[1]), None), run(&b, &input(&[1]), None));
+    assert_eq!(compare(&x, &y), Some(Divergence::Output { line: 0, a: Some("0".into()), b: Some("50".into()) }));
+    let (x, y) = (run(&a, &input(&[0]), None), run(&b, &input(&[0]), None));
+    assert_eq!(compare(&x, &y), None);
+
+    // the output is over earlier when the limit is reached
+    let (x, y) = (run(&a, &input(&[2]), Some(10)), run(&a, &input(&[2]), None));
+    assert_eq!(compare(&x, &y), Some(Divergence::Output { line: 2, a: None, b: Some("50".into()) }));
+    assert_eq!(compare(&x, &y).unwrap().to_string(), "the output diverges at line 3: the end vs `50`");
+
+    // the same output, only the status diverges
+    let y = Outcome { output: x.output.clone(), status: Status::Exited(10) };
+    assert!(matches!(compare(&x, &y), Some(Divergence::Status(Status::Failed { .. }, Status::Exited(10)))));
+    let z = Outcome { output: x.output.clone(), status: Status::Exited(99) };
+    assert_eq!(compare(&y, &z), None);
+}
+
+#[test]
+fn test_random_inputs() {
+    let inputs = random_inputs(7, 10, 4);
+    assert_eq!(inputs.len(), 10);
+    assert!(inputs.iter().all(|input| input.len() == 4));
+    assert!(inputs.iter().flatten().all(|n| (-100..=100).contains(&n.parse::<i32>().unwrap())));
+    assert_eq!(inputs, random_inputs(7, 10, 4));
+    assert_ne!(inputs, random_inputs(8, 10, 4));
+}
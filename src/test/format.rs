@@ -0,0 +1,32 @@
+use crate::format::{format_source, split_comment};
+
+#[test]
+fn test_split_comment() {
+    assert_eq!(split_comment("t1 := t2 / t3 // note"), ("t1 := t2 / t3", Some("// note")));
+    assert_eq!(split_comment("WRITE t1;note"), ("WRITE t1", Some(";note")));
+    assert_eq!(split_comment("WRITE t1  "), ("WRITE t1", None));
+}
+
+#[test]
+fn test_format() {
+    let source = [
+        "", "; header", "FUNCTION   main:", "  READ   t1 ; read", "", "", 
+        " LABEL l1:", "t1:=t1+#-1", "IF t1>=#0 GOTO l1", "RETURN #0", "",
+    ];
+    let formatted = format_source(&source, Some(2)).unwrap();
+    assert_eq!(formatted, "\
+; header
+FUNCTION main :
+  READ t1 ; read
+
+  LABEL l1 :
+    t1 := t1 + #-1
+    IF t1 >= #0 GOTO l1
+    RETURN #0
+");
+
+    // the formatted source is stable
+    let lines: Vec<&str> = formatted.lines().collect();
+    assert_eq!(format_source(&lines, Some(2)).unwrap(), formatted);
+    assert!(format_source(&["FUNCTION main"], None).is_err());
+}
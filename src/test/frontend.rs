@@ -0,0 +1,252 @@
+use crate::{
+    ast::{Operator, Sentence, Variable},
+    error::CompileErrorKind,
+    frontend::{compile, lexer::{Token, tokenize}, parser, syntax::{ExprKind, ExtDef, Stmt}},
+    opt::to_source,
+};
+use super::utils::{parse, run};
+
+/// the examples of the course and the features beyond them, with the input and the expected output
+const PROGRAMS: [(&str, &[i32], &str); 7] = [
+    // recursion
+    ("
+    int fact(int n) {
+        if (n == 1)
+            return n;
+        else
+            return (n * fact(n - 1));
+    }
+    int main() {
+        int m, result;
+        m = read();
+        if (m > 1) result = fact(m);
+        else result = 1;
+        write(result);
+        return 0;
+    }", &[5], "120\n"),
+    // a struct is passed by its address
+    ("
+    struct Operands { int o1; int o2; };
+    int add(struct Operands temp) {
+        return (temp.o1 + temp.o2);
+    }
+    int main() {
+        int n;
+        struct Operands op;
+        op.o1 = 1;
+        op.o2 = 2;
+        n = add(op);
+        write(n);
+        return 0;
+    }", &[], "3\n"),
+    // arrays of arrays
+    ("
+    int add(int temp[2]) {
+        return (temp[0] + temp[1]);
+    }
+    int main() {
+        int op[2];
+        int r[1][2];
+        int i = 0, j = 0;
+        while (i < 2) {
+            while (j < 2) {
+                op[j] = i + j;
+                j = j + 1;
+            }
+            r[0][i] = add(op);
+            write(r[0][i]);
+            i = i + 1;
+            j = 0;
+        }
+        return 0;
+    }", &[], "1\n3\n"),
+    // the logical operators as values and conditions
+    ("
+    int main() {
+        int a = read(), b = read();
+        write(a > b);
+        write(a < b && b < 10);
+        write(!(a == 0) || b / 0 == 1);
+        if (!a) write(-1); else write(-a - -b);
+        return 0;
+    }", &[3, 7], "0\n1\n1\n4\n"),
+    // the shadowed variables, the structs with arrays, the copy of struct
+    ("
+    struct P { int x; int ys[3]; };
+    int sum(struct P p) {
+        int i = 0, s = p.x;
+        while (i < 3) { s = s + p.ys[i]; i = i + 1; }
+        return s;
+    }
+    int main() {
+        struct P a, b;
+        int x = 1;
+        {
+            int x = 2;
+            a.x = x;
+        }
+        a.ys[0] = x; a.ys[1] = 10; a.ys[2] = 100;
+        b = a;
+        a.ys[2] = 0;
+        write(sum(a));
+        write(sum(b));
+        return 0;
+    }", &[], "13\n113\n"),
+    // the arguments are pushed in reverse, the assignment is a value
+    ("
+    int sub(int a, int b, int c) { return a - b - c; }
+    int Main_(int n) { return n * 2; }
+    int main() {
+        int x, y;
+        x = y = 3;
+        write(sub(10, Main_(x), y));
+        write(sub(sub(1, 2, 3), 4, 5));
+        return 0;
+    }", &[], "1\n-13\n"),
+    // the array in the loop is allocated once, or the memory runs out
+    ("
+    int main() {
+        int i = 0, s = 0;
+        while (i < 300) {
+            int a[4];
+            a[0] = i;
+            s = s + a[0];
+            i = i + 1;
+        }
+        write(s);
+        return 0;
+    }", &[], "44850\n"),
+];
+
+#[test]
+fn test_lexer() {
+    let tokens: Vec<Token> = tokenize("
+        int a = 0x1F + 017; // comment
+        /* multi
+           line */ float b = 1.5e1;
+        a.x >= !b")
+        .unwrap().into_iter().map(|(token, _)| token).collect();
+    assert_eq!(tokens, vec![
+        Token::Type("int"), Token::Id("a"), Token::Assign, Token::Int(31), Token::Plus, Token::Int(15), Token::Semi,
+        Token::Type("float"), Token::Id("b"), Token::Assign, Token::Float(15.0), Token::Semi,
+        Token::Id("a"), Token::Dot, Token::Id("x"), Token::Relop(Operator::GreaterEqual), Token::Not, Token::Id("b"),
+    ]);
+
+    let lines: Vec<usize> = tokenize("a\n/*\n\n*/ b\n\nc").unwrap().into_iter().map(|(_, line)| line).collect();
+    assert_eq!(lines, vec![1, 4, 6]);
+
+    for (source, line) in [("int a;\n a = @;", 2), ("09", 1), ("1.", 1), ("\n4294967296", 2), ("\n/* open", 2)] {
+        let err = tokenize(source).unwrap_err();
+        assert_eq!(*err.kind(), CompileErrorKind::LexicalError, "{}", source);
+        assert_eq!(err.line(), line, "{}", source);
+    }
+}
+
+#[test]
+fn test_parser() {
+    let tokens = tokenize("int main() { a = b = c + d * e < f || g && !h; if (a) if (b) c; else d; }").unwrap();
+    let program = parser::parse(&tokens).unwrap();
+    let ExtDef::Func(func) = &program.defs[0] else { panic!() };
+
+    // `=` is right associative and binds the loosest
+    let Stmt::Expr(expr) = &func.body.stmts[0] else { panic!() };
+    let ExprKind::Assign(_, r) = &expr.kind else { panic!() };
+    let ExprKind::Assign(_, r) = &r.kind else { panic!() };
+    let ExprKind::Or(l, r) = &r.kind else { panic!() };
+    assert!(matches!(&r.kind, ExprKind::And(_, r) if matches!(r.kind, ExprKind::Not(_))));
+    let ExprKind::Rel(Operator::Less, l, _) = &l.kind else { panic!() };
+    assert!(matches!(&l.kind, ExprKind::Arith(Operator::Plus, _, r) if matches!(r.kind, ExprKind::Arith(Operator::Mul, ..))));
+
+    // `else` belongs to the inner `if`
+    let Stmt::If(_, then, None) = &func.body.stmts[1] else { panic!() };
+    assert!(matches!(**then, Stmt::If(_, _, Some(_))));
+
+    for (source, line) in [
+        ("int main() {\n int a\n }", 3),
+        ("int main() {\n a = 1;\n int b;\n }", 3),
+        ("int main() {\n a = (1 + 2;\n }", 2),
+        ("int main() {\n a = 1;\n", 2),
+        ("struct { int a; } ;\nint f(int a[n]) {}", 2),
+    ] {
+        let err = parser::parse(&tokenize(source).unwrap()).unwrap_err();
+        assert_eq!(*err.kind(), CompileErrorKind::SyntaxError, "{}", source);
+        assert_eq!(err.line(), line, "{}", source);
+    }
+}
+
+#[test]
+fn test_semantic() {
+    for (source, t, line) in [
+        ("int main() {\n int i;\n i = j;\n return 0; }", 1, 3),
+        ("int main() {\n int i;\n i = inc(i);\n return 0; }", 2, 3),
+        ("int main() {\n int i, j;\n int i;\n return 0; }", 3, 3),
+        ("int f() { return 0; }\nint f() { return 1; }\nint main() { return 0; }", 4, 2),
+        ("int main() {\n int i;\n float j;\n i = j;\n return 0; }", 5, 4),
+        ("int main() {\n int i;\n 1 = i;\n return 0; }", 6, 3),
+        ("int main() {\n float j;\n j = 1.5 + 3;\n return 0; }", 7, 3),
+        ("int main() {\n float j;\n return j;\n }", 8, 3),
+        ("int f(int a, int b) { return a + b; }\nint main() {\n return f(1);\n }", 9, 3),
+        ("int main() {\n int i;\n return i[0];\n }", 10, 3),
+        ("int main() {\n int i;\n return i();\n }", 11, 3),
+        ("int main() {\n int a[3];\n float f;\n return a[f];\n }", 12, 4),
+        ("int main() {\n int i;\n return i.x;\n }", 13, 3),
+        ("struct A { int x; };\nint main() {\n struct A a;\n return a.y;\n }", 14, 4),
+        ("struct A {\n int x;\n int x;\n };", 15, 3),
+        ("struct A {\n int x = 1;\n };", 15, 2),
+        ("struct A { int x; };\nstruct A { int y; };", 16, 2),
+        ("int main() {\n struct B b;\n return 0;\n }", 17, 2),
+        // the logical operators only take int, and so does the condition
+        ("int main() {\n float f;\n if (f) return 1;\n return 0;\n }", 7, 3),
+        ("int main() {\n float f;\n return f && 1;\n }", 7, 3),
+    ] {
+        let err = compile(source).unwrap_err();
+        assert_eq!(*err.kind(), CompileErrorKind::SemanticError(t), "{}", source);
+        assert_eq!(err.line(), line, "{}", source);
+    }
+
+    // the variable in the inner block shadows the outer one, and the scope ends with the block
+    assert!(compile("int main() { int a; { int a[2]; a[0] = 1; } a = 1; return a; }").is_ok());
+    let err = compile("int main() {\n { int b; }\n return b;\n }").unwrap_err();
+    assert_eq!((err.kind(), err.line()), (&CompileErrorKind::SemanticError(1), 3));
+}
+
+#[test]
+fn test_compile() {
+    let codes = compile(PROGRAMS[0].0).unwrap();
+    assert_eq!(codes[..4], [
+        Sentence::Func("fact"),
+        Sentence::Param(Variable::Id("v1")),
+        Sentence::IfGoto { l: Variable::Id("v1"), r: Variable::Number(1), opt: Operator::Equal, label: "label1" },
+        Sentence::Goto("label2"),
+    ]);
+    // `m = read()` is a single sentence
+    assert!(codes.contains(&Sentence::Read(Variable::Id("v2"))));
+
+    for (source, input, expected) in PROGRAMS {
+        let codes = compile(source).unwrap();
+        let (output, _) = run(codes.clone(), input);
+        assert_eq!(output, expected, "{}", to_source(&codes));
+
+        // the generated IR is valid source too
+        let text = to_source(&codes);
+        assert_eq!(parse(&text), codes);
+    }
+
+    // the `DEC`s are at the entrance of function
+    let codes = compile(PROGRAMS[6].0).unwrap();
+    assert!(matches!(codes[1], Sentence::Dec { size: 16, .. }), "{}", to_source(&codes));
+
+    // the function whose name isn't a valid IR id is renamed
+    let codes = compile(PROGRAMS[5].0).unwrap();
+    assert!(codes.contains(&Sentence::Func("f_Main_")));
+
+    for (source, line) in [
+        ("int g;\nint main() { return 0; }", 1),
+        ("int main() {\n float f = 1.0;\n return 0;\n }", 2),
+        ("struct A { float x; };\nint main() {\n struct A a;\n return 0;\n }", 3),
+    ] {
+        let err = compile(source).unwrap_err();
+        assert_eq!(*err.kind(), CompileErrorKind::UnsupportedError, "{}", source);
+        assert_eq!(err.line(), line, "{}", source);
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    interpreter::{Interpreter, parser},
+    utils::io::{read_from_values, write_to_buffer},
+};
+
+#[test]
+fn test_eval() {
+    let parser = parser::SentenceParser::new();
+    let (output, write_func) = write_to_buffer();
+    let interpreter = Interpreter::empty(read_from_values(vec!["7".into()]), write_func);
+    let mut symbol_table = BTreeSet::new();
+
+    let function = ["FUNCTION double :", "PARAM x", "y := x + x", "RETURN y"];
+    interpreter.define(function.iter().map(|line| parser.parse(line).unwrap()).collect()).unwrap();
+
+    ["READ a", "ARG a", "b := CALL double", "WRITE b"].iter().for_each(|line| {
+        interpreter.eval(parser.parse(line).unwrap(), &mut symbol_table).unwrap();
+    });
+    assert_eq!(output.borrow().as_str(), "14\n");
+
+    // the error sentence changes nothing
+    assert!(interpreter.eval(parser.parse("WRITE c").unwrap(), &mut symbol_table).is_err());
+    assert!(interpreter.eval(parser.parse("GOTO l").unwrap(), &mut symbol_table).is_err());
+    assert_eq!(interpreter.variables(), vec![("a", vec![7]), ("b", vec![14])]);
+}
+
+#[test]
+fn test_define() {
+    let parser = parser::SentenceParser::new();
+    let (output, write_func) = write_to_buffer();
+    let interpreter = Interpreter::empty(read_from_values(vec![]), write_func);
+    let mut symbol_table = BTreeSet::new();
+    let parse = |lines: &[&'static str]| lines.iter().map(|line| parser.parse(line).unwrap()).collect::<Vec<_>>();
+
+    // the sentences evaluated later would be fallen into without `RETURN`
+    let err = interpreter.define(parse(&["FUNCTION f :", "WRITE #1"])).unwrap_err();
+    assert_eq!(err.kind().name(), "FuncEndError");
+    assert_eq!(err.line(), 1);
+    assert!(interpreter.functions().is_empty());
+
+    interpreter.define(parse(&["FUNCTION f :", "LABEL top :", "WRITE #1", "GOTO top"])).unwrap();
+    interpreter.define(parse(&["FUNCTION g :", "WRITE #2", "RETURN #0"])).unwrap();
+    interpreter.eval(parser.parse("t := CALL g").unwrap(), &mut symbol_table).unwrap();
+    assert_eq!(output.borrow().as_str(), "2\n");
+}
@@ -0,0 +1,25 @@
+use crate::utils::json::Json;
+
+#[test]
+fn test_escape() {
+    [
+        ("plain", r#""plain""#),
+        ("say \"hi\"", r#""say \"hi\"""#),
+        ("C:\\ir\\a.ir", r#""C:\\ir\\a.ir""#),
+        ("a\nb\r\tc", r#""a\nb\r\tc""#),
+        ("\u{0}\u{1b}\u{1f}", r#""\u0000\u001b\u001f""#),
+        ("\u{7f}é", "\"\u{7f}é\""),
+    ].iter().for_each(|(text, expected)| {
+        assert_eq!(Json::from(*text).to_string(), *expected, "case: {:?}", text)
+    });
+}
+
+#[test]
+fn test_object() {
+    let json = Json::Object(vec![
+        ("a\"b", Json::Array(vec![Json::Null, true.into(), 3usize.into(), (-1).into()])),
+        ("c", Json::from(None::<&str>)),
+        ("d", Some("x\\y").into()),
+    ]);
+    assert_eq!(json.to_string(), r#"{"a\"b":[null,true,3,-1],"c":null,"d":"x\\y"}"#);
+}
@@ -2,7 +2,7 @@ use lalrpop_util::lalrpop_mod;
 
 use crate::ast::Variable;
 
-lalrpop_mod!(pub parser);
+lalrpop_mod!(#[allow(clippy::all, unused)] pub parser);
 
 
 
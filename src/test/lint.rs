@@ -0,0 +1,38 @@
+use crate::lint::{lint, find_code, UNUSED_LABEL};
+use super::utils::parse;
+
+#[test]
+fn test_lint() {
+    let codes = parse("
+        FUNCTION f :
+        PARAM n
+        DEC arr 8
+        t := n + #1
+        GOTO l1
+        WRITE n
+        LABEL l1 :
+        LABEL l0 :
+        READ x
+        x := #3
+        WRITE x
+        GOTO l2
+        LABEL l2 :
+        ARG x
+        WRITE x
+        FUNCTION main :
+        ARG #1
+        r := CALL f
+        WRITE r
+        RETURN #0
+    ");
+    let codes_of = |allowed: &[&str]| -> Vec<(usize, &str)> {
+        lint(&codes, allowed).iter().map(|w| (w.i, w.code)).collect()
+    };
+
+    assert_eq!(codes_of(&[]), vec![
+        (0, "W001"), (2, "W005"), (3, "W004"), (5, "W007"), (7, "W002"),
+        (8, "W008"), (11, "W003"), (13, "W006"), (14, "W001"),
+    ]);
+    assert_eq!(find_code("unused-label"), Some(UNUSED_LABEL));
+    assert!(!codes_of(&["W002"]).contains(&(7, "W002")));
+}
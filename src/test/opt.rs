@@ -0,0 +1,826 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    ast::{Sentence, Variable},
+    frontend::compile,
+    opt::{copy::propagate, cse, dce::eliminate, fold::fold, inline::{inline, THRESHOLD},
+        peephole::{default_rules, parse_rules, peephole}, optimize, Pass, ssa::{construct, destruct}, to_source},
+};
+use super::utils::{parse, report, run};
+
+const PROGRAM: &str = "
+    FUNCTION main :
+    READ t1
+    t2 := #2 * #4
+    t3 := t2 + #1
+    t4 := t1 * #1
+    t5 := t4 - t4
+    IF t2 > #3 GOTO l1
+    WRITE t3
+    LABEL l1 :
+    IF t3 == #0 GOTO l1
+    t6 := t1 + t2
+    WRITE t6
+    WRITE t5
+    RETURN #0
+";
+
+#[test]
+fn test_fold() {
+    let codes = parse(PROGRAM);
+    let (folded, changes) = fold(&codes);
+
+    assert_eq!(to_source(&folded), "\
+FUNCTION main :
+READ t1
+t2 := #8
+t3 := #9
+t4 := t1
+t5 := #0
+GOTO l1
+WRITE #9
+LABEL l1 :
+IF t3 == #0 GOTO l1
+t6 := t1 + t2
+WRITE t6
+WRITE t5
+RETURN #0
+");
+    assert_eq!(changes.len(), 6);
+
+    let (before, after) = (run(codes, &[5]), run(folded, &[5]));
+    assert_eq!(before.0, after.0);
+    assert!(after.1 <= before.1);
+}
+
+#[test]
+fn test_eliminate() {
+    let codes = parse("
+        FUNCTION main :
+        READ t1
+        t2 := t1 + #1
+        t3 := t2 * #2
+        t4 := CALL f
+        IF t1 > #0 GOTO l1
+        GOTO l2
+        WRITE t1
+        LABEL l1 :
+        t2 := #5
+        LABEL l3 :
+        LABEL l2 :
+        WRITE t2
+        RETURN #0
+        FUNCTION f :
+        DEC arr 8
+        t5 := &arr
+        *t5 := #1
+        RETURN #0
+    ");
+    let (result, changes) = eliminate(&codes);
+
+    assert_eq!(to_source(&result), "\
+FUNCTION main :
+READ t1
+t2 := t1 + #1
+t4 := CALL f
+IF t1 > #0 GOTO l1
+GOTO l2
+LABEL l1 :
+t2 := #5
+LABEL l2 :
+WRITE t2
+RETURN #0
+FUNCTION f :
+DEC arr 8
+t5 := &arr
+*t5 := #1
+RETURN #0
+");
+    assert_eq!(changes.len(), 3);
+
+    for input in [-1, 1] {
+        assert_eq!(run(codes.clone(), &[input]).0, run(result.clone(), &[input]).0);
+    }
+}
+
+#[test]
+fn test_eliminate_first_def() {
+    // the first `x := ...` in the source is unreachable, but `x` is assigned before `WRITE x` at runtime
+    let codes = parse("
+        FUNCTION main :
+        GOTO l1
+        x := #1
+        LABEL l2 :
+        WRITE x
+        RETURN #0
+        LABEL l1 :
+        x := #3
+        GOTO l2
+    ");
+    let (result, _) = eliminate(&codes);
+
+    assert_eq!(to_source(&result), "\
+FUNCTION main :
+x := #0
+GOTO l1
+LABEL l2 :
+WRITE x
+RETURN #0
+LABEL l1 :
+x := #3
+GOTO l2
+");
+    assert_eq!(run(result, &[]).0, "3\n");
+
+    let (result, _) = optimize(&codes, Pass::level(2), &default_rules());
+    assert_eq!(run(result, &[]).0, "3\n");
+}
+
+const FOREIGN_JUMP: &str = "
+    FUNCTION f :
+    RETURN #2
+    LABEL l9 :
+    WRITE #5
+    RETURN #1
+    FUNCTION main :
+    GOTO l9
+";
+
+#[test]
+fn test_eliminate_foreign_jump() {
+    // `LABEL l9` can't be reached in `f`, but the labels are global and `main` jumps to it
+    let codes = parse(FOREIGN_JUMP);
+    let (result, changes) = eliminate(&codes);
+    assert_eq!(result, codes, "{:?}", changes);
+    assert_eq!(run(result, &[]).0, "5\n");
+}
+
+#[test]
+fn test_propagate() {
+    let codes = parse("
+        FUNCTION main :
+        READ v
+        t1 := v
+        t2 := t1
+        t3 := t2 + #1
+        IF v > #0 GOTO l1
+        w := t2
+        LABEL l1 :
+        WRITE t3
+        WRITE t1
+        t1 := #0
+        WRITE t2
+        IF v > #5 GOTO l2
+        u := v
+        GOTO l3
+        LABEL l2 :
+        u := t3
+        LABEL l3 :
+        WRITE u
+        RETURN #0
+    ");
+    let (result, changes) = propagate(&codes);
+
+    assert_eq!(to_source(&result), "\
+FUNCTION main :
+READ v
+t1 := v
+t2 := v
+t3 := v + #1
+IF v > #0 GOTO l1
+w := v
+LABEL l1 :
+WRITE t3
+WRITE v
+t1 := #0
+WRITE v
+IF v > #5 GOTO l2
+u := v
+GOTO l3
+LABEL l2 :
+u := t3
+LABEL l3 :
+WRITE u
+RETURN #0
+");
+    assert_eq!(changes.len(), 5);
+
+    // the copies are dead after propagation
+    let (optimized, _) = eliminate(&result);
+    for input in [-3, 2, 9] {
+        let input = [input.to_string()];
+        let report = report(&codes, &optimized, &input, None).unwrap();
+        assert!(report.after < report.before, "{}", report);
+    }
+}
+
+#[test]
+fn test_propagate_memory() {
+    let codes = parse("
+        FUNCTION main :
+        READ a
+        p := &a
+        b := a
+        *p := #3
+        WRITE b
+        c := b
+        x := CALL f
+        WRITE c
+        RETURN #0
+        FUNCTION f :
+        RETURN #1
+    ");
+    let (result, _) = propagate(&codes);
+
+    // `a` is changed by `*p`, but the local `b` isn't changed by the call
+    assert_eq!(result[3], codes[3]);
+    assert_eq!(result[5], codes[5]);
+    assert_eq!(result[8].to_string(), "WRITE b");
+    assert_eq!(run(codes, &[1]).0, run(result, &[1]).0);
+}
+
+#[test]
+fn test_cse() {
+    let codes = parse("
+        FUNCTION main :
+        DEC arr 8
+        READ a
+        READ b
+        t1 := a + b
+        t2 := b + a
+        t3 := a - b
+        t4 := b - a
+        p := &arr
+        *p := a
+        t5 := *p * #2
+        t6 := *p * #2
+        *p := b
+        t7 := *p * #2
+        t8 := a * b
+        t9 := CALL f
+        t10 := a * b
+        t11 := *p * #2
+        a := a + #1
+        t12 := a + b
+        LABEL l1 :
+        t13 := a - b
+        WRITE t2
+        WRITE t4
+        WRITE t6
+        WRITE t7
+        WRITE t10
+        WRITE t11
+        WRITE t12
+        WRITE t13
+        RETURN #0
+        FUNCTION f :
+        RETURN #0
+    ");
+    let (result, changes) = cse::eliminate(&codes);
+
+    let lines: Vec<String> = result.iter().map(|code| code.to_string()).collect();
+    assert_eq!(lines[5], "t2 := t1");
+    assert_eq!(lines[7], "t4 := b - a");
+    assert_eq!(lines[11], "t6 := t5");
+    // the memory is written between them
+    assert_eq!(lines[13], "t7 := *p * #2");
+    // the call doesn't change the local ids, but the memory may be changed
+    assert_eq!(lines[16], "t10 := t8");
+    assert_eq!(lines[17], "t11 := *p * #2");
+    // `a` is changed, and the block is over at the label
+    assert_eq!(lines[19], "t12 := a + b");
+    assert_eq!(lines[21], "t13 := a - b");
+    assert_eq!(changes.len(), 3);
+
+    let input = ["4".to_string(), "7".to_string()];
+    let report = report(&codes, &result, &input, None).unwrap();
+    assert_eq!(report.before, report.after);
+}
+
+#[test]
+fn test_cse_taken() {
+    // `v := #5` writes the memory `*p` reads
+    let codes = parse("
+        FUNCTION main :
+        v := #1
+        p := &v
+        t1 := *p + #1
+        WRITE t1
+        v := #5
+        t2 := *p + #1
+        WRITE t2
+        RETURN #0
+    ");
+    let (result, changes) = cse::eliminate(&codes);
+    assert!(changes.is_empty(), "{}", to_source(&result));
+    assert_eq!(run(result, &[]).0, "2\n6\n");
+}
+
+#[test]
+fn test_inline() {
+    let codes = parse("
+        FUNCTION abs :
+        PARAM x
+        IF x >= #0 GOTO pos
+        x := #0 - x
+        RETURN x
+        LABEL pos :
+        RETURN x
+        FUNCTION sub :
+        PARAM a
+        PARAM b
+        c := a - b
+        RETURN c
+        FUNCTION fact :
+        PARAM n
+        IF n > #1 GOTO more
+        RETURN #1
+        LABEL more :
+        t1 := n - #1
+        ARG t1
+        t2 := CALL fact
+        t3 := n * t2
+        RETURN t3
+        FUNCTION main :
+        READ x
+        READ y
+        ARG y
+        ARG x
+        t1 := CALL sub
+        ARG t1
+        t2 := CALL abs
+        WRITE t2
+        ARG t2
+        t3 := CALL fact
+        WRITE t3
+        ARG x
+        t4 := CALL abs
+        WRITE t4
+        RETURN #0
+    ");
+    let (result, changes) = inline(&codes, THRESHOLD);
+
+    let main = to_source(&result[result.iter().position(|code| *code == Sentence::Func("main")).unwrap()..]);
+    assert_eq!(main, "\
+FUNCTION main :
+READ x
+READ y
+a_1 := x
+b_1 := y
+c_1 := a_1 - b_1
+t1 := c_1
+x_1 := t1
+IF x_1 >= #0 GOTO pos_1
+x_1 := #0 - x_1
+t2 := x_1
+GOTO abs_end_1
+LABEL pos_1 :
+t2 := x_1
+LABEL abs_end_1 :
+WRITE t2
+ARG t2
+t3 := CALL fact
+WRITE t3
+x_2 := x
+IF x_2 >= #0 GOTO pos_2
+x_2 := #0 - x_2
+t4 := x_2
+GOTO abs_end_2
+LABEL pos_2 :
+t4 := x_2
+LABEL abs_end_2 :
+WRITE t4
+RETURN #0
+");
+    // `fact` calls itself
+    assert_eq!(changes.len(), 3);
+
+    for input in [[7, 3], [2, 5], [-4, 0]] {
+        let input = input.map(|n| n.to_string());
+        let report = report(&codes, &result, &input, None).unwrap();
+        assert!(report.after < report.before, "{}", report);
+    }
+
+    // `sub` has 4 sentences
+    assert_eq!(inline(&codes, 3).1.len(), 0);
+    assert_eq!(inline(&codes, 4).1.len(), 1);
+}
+
+#[test]
+fn test_inline_dec() {
+    // the callee allocates at every call, and `RETURN` frees it
+    let codes = parse("
+        FUNCTION f :
+        PARAM x
+        DEC a 8
+        p := &a
+        *p := x
+        t := *p
+        RETURN t
+        FUNCTION main :
+        i := #0
+        s := #0
+        LABEL l1 :
+        IF i >= #200 GOTO l2
+        ARG i
+        t1 := CALL f
+        s := s + t1
+        i := i + #1
+        GOTO l1
+        LABEL l2 :
+        WRITE s
+        RETURN #0
+    ");
+    let (result, changes) = inline(&codes, THRESHOLD);
+    assert!(changes.is_empty(), "{}", to_source(&result));
+
+    let (result, _) = optimize(&codes, Pass::level(2), &default_rules());
+    assert_eq!(run(result, &[]).0, "19900\n");
+}
+
+#[test]
+fn test_inline_compiled() {
+    let source = "
+        struct P { int x, y; };
+        int dot(struct P a, struct P b) { return a.x * b.x + a.y * b.y; }
+        int max(int a, int b) { if (a > b) return a; else return b; }
+        int main() {
+            struct P p, q;
+            int i = 0, best = -1000;
+            p.x = read(); p.y = read();
+            while (i < 5) {
+                q.x = i; q.y = 4 - i;
+                best = max(best, dot(p, q));
+                i = i + 1;
+            }
+            write(best);
+            return 0;
+        }";
+    let codes = compile(source).unwrap();
+    let (result, changes) = inline(&codes, THRESHOLD);
+    assert_eq!(changes.len(), 2);
+    for input in [[1, 2], [-3, 5]] {
+        let input = input.map(|n| n.to_string());
+        let report = report(&codes, &result, &input, None).unwrap();
+        assert!(report.after < report.before, "{}", report);
+    }
+}
+
+#[test]
+fn test_peephole() {
+    let codes = parse("
+        FUNCTION main :
+        READ a
+        READ b
+        IF a < b GOTO l1
+        GOTO l2
+        LABEL l1 :
+        t1 := a - b
+        c := t1
+        GOTO l3
+        LABEL l3 :
+        LABEL l2 :
+        t2 := a
+        t3 := t2
+        d := t3
+        d := d
+        WRITE d
+        t4 := a * b
+        c := t4
+        WRITE t4
+        WRITE c
+        RETURN #0
+    ");
+    let (result, changes) = peephole(&codes, &default_rules());
+
+    assert_eq!(to_source(&result), "\
+FUNCTION main :
+READ a
+READ b
+IF a >= b GOTO l2
+c := a - b
+LABEL l2 :
+d := a
+WRITE d
+t4 := a * b
+c := t4
+WRITE t4
+WRITE c
+RETURN #0
+");
+    let rules: Vec<&str> = changes.iter().map(|change| change.msg.rsplit('`').nth(1).unwrap()).collect();
+    assert_eq!(rules, [
+        "invert-lt", "sub-dead", "goto-next", "copy-dead", "self-assign", "label-unused", "label-unused", "copy-dead",
+    ]);
+    // the second pass finds what the first one leaves, `d := a` comes from the line of `t2 := a`
+    assert_eq!(changes[7].i, 11);
+
+    for input in [[1, 2], [5, 3]] {
+        let input = input.map(|n| n.to_string());
+        let report = report(&codes, &result, &input, None).unwrap();
+        assert!(report.after < report.before, "{}", report);
+    }
+}
+
+#[test]
+fn test_peephole_rules() {
+    // the rules given by users, the address is written back to the same place
+    let text = "
+        ; a comment
+        rule store-load
+        *p := x
+        y := *p
+        =>
+        *p := x
+        y := x
+
+        rule write-twice
+        WRITE x
+        WRITE x
+        =>
+        WRITE x
+        WRITE x
+        WRITE #0
+    ";
+    let rules = parse_rules(text).unwrap();
+    assert_eq!(rules.iter().map(|rule| rule.name).collect::<Vec<_>>(), ["store-load", "write-twice"]);
+
+    let codes = parse("
+        FUNCTION main :
+        DEC arr 8
+        READ a
+        p := &arr
+        *p := a
+        b := *p
+        WRITE b
+        WRITE b
+        RETURN #0
+    ");
+    let (result, changes) = peephole(&codes, &rules[..1]);
+    assert_eq!(result[5].to_string(), "b := a");
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].to_string(), "line 5: `*p := a`, `b := *p` becomes `*p := a`, `b := a` by `store-load`");
+
+    // the rule never stops, but the passes are limited
+    let (result, _) = peephole(&codes, &rules[1..]);
+    assert!(result.len() > codes.len());
+
+    for (text, line) in [
+        ("GOTO l\n", 1),
+        ("rule a\nGOTO l\n=>\nGOTO m\n", 4),
+        ("rule a\nx := y\n=>\nx := y\nwhere dead z\n", 5),
+        ("rule a\n=>\n", 2),
+        ("rule a\nFUNCTION f :\n", 2),
+        ("rule a\nx := \n", 2),
+        ("rule a\nrule b\nGOTO l\n", 3),
+    ] {
+        assert_eq!(parse_rules(text).unwrap_err().0, line, "{}", text);
+    }
+}
+
+#[test]
+fn test_peephole_compiled() {
+    let source = "
+        int main() {
+            int a = read(), b = read(), n = 0;
+            while (a > 0 && b != a) {
+                if (a < b) n = n + a; else n = n - b;
+                a = a - 1;
+            }
+            write(n);
+            return 0;
+        }";
+    let codes = compile(source).unwrap();
+    let (result, changes) = peephole(&codes, &default_rules());
+    assert!(!changes.is_empty());
+    for input in [[5, 3], [4, 9], [0, 0]] {
+        let input = input.map(|n| n.to_string());
+        let report = report(&codes, &result, &input, None).unwrap();
+        assert!(report.after <= report.before, "{}", report);
+    }
+}
+
+/// the programs whose SSA form has the tricky copies
+const SSA_PROGRAMS: [(&str, &[i32]); 4] = [
+    // the swap of `a` and `b` is a phi group reading each other
+    ("
+        FUNCTION main :
+        READ n
+        a := #1
+        b := #2
+        LABEL top :
+        IF n <= #0 GOTO end
+        c := a
+        a := b
+        b := c
+        n := n - #1
+        GOTO top
+        LABEL end :
+        WRITE a
+        WRITE b
+        RETURN #0
+    ", &[3]),
+    // the taken edges to `join` are critical, and `x` is only used after it
+    ("
+        FUNCTION main :
+        READ c
+        x := #1
+        IF c > #0 GOTO join
+        x := #2
+        IF c < #-5 GOTO join
+        x := #3
+        LABEL join :
+        WRITE x
+        i := #0
+        LABEL loop :
+        i := i + #1
+        IF i < c GOTO loop
+        WRITE i
+        RETURN #0
+    ", &[-7]),
+    // the array and the address taken variable stay in memory
+    ("
+        FUNCTION main :
+        DEC arr 8
+        READ n
+        k := #5
+        p := &k
+        IF n > #0 GOTO pos
+        *p := #7
+        LABEL pos :
+        t1 := &arr + #4
+        *t1 := k
+        t2 := &arr
+        *t2 := CALL f
+        WRITE arr
+        WRITE *t1
+        RETURN #0
+        FUNCTION f :
+        READ y
+        IF y > #0 GOTO done
+        y := #0 - y
+        LABEL done :
+        RETURN y
+    ", &[0, -4]),
+    // the variable assigned in one branch is read after the join only when it's assigned
+    ("
+        FUNCTION main :
+        READ c
+        IF c == #0 GOTO skip
+        y := c * #10
+        LABEL skip :
+        z := c + #1
+        IF c == #0 GOTO over
+        WRITE y
+        LABEL over :
+        WRITE z
+        RETURN #0
+    ", &[4]),
+];
+
+#[test]
+fn test_ssa() {
+    let codes = parse(SSA_PROGRAMS[0].0);
+    let funcs = construct(&codes);
+    let top = &funcs[0].blocks[1];
+
+    // the phis of loop header take the value before the loop and the one at the end of body
+    let phis: Vec<&str> = top.phis.iter().map(|phi| phi.var).collect();
+    assert_eq!(phis, ["a", "b", "n"]);
+    assert!(top.phis.iter().all(|phi| phi.args.len() == 2));
+    // `c` is assigned in the loop but dead at the header
+    assert!(!top.phis.iter().any(|phi| phi.var == "c"));
+
+    for (source, input) in SSA_PROGRAMS {
+        let codes = parse(source);
+        let funcs = construct(&codes);
+
+        // every renamed id is assigned once
+        for func in &funcs {
+            let mut assigned = BTreeSet::new();
+            for block in &func.blocks {
+                for phi in &block.phis {
+                    assert!(assigned.insert(phi.target), "{}", func);
+                }
+                for code in &block.codes {
+                    if let Some(id) = code.def().filter(|id| !["arr", "k"].contains(id)) {
+                        assert!(assigned.insert(id), "{}", func);
+                    }
+                }
+            }
+        }
+
+        let result = destruct(&funcs);
+        assert_eq!(run(codes, input).0, run(result.clone(), input).0, "{}", to_source(&result));
+        // the result is valid source
+        assert_eq!(parse(&to_source(&result)), result);
+    }
+
+    // the memory isn't renamed
+    let funcs = construct(&parse(SSA_PROGRAMS[2].0));
+    let text = funcs[0].to_string();
+    assert!(text.contains("p_1 := &k") && text.contains("*p_1 := #7") && text.contains("WRITE arr"), "{}", text);
+    assert!(funcs[0].blocks.iter().all(|block| block.phis.iter().all(|phi| phi.var != "k")));
+    assert!(funcs[1].blocks[2].phis[0].args.contains(&(1, Variable::Id("y_2"))));
+}
+
+#[test]
+fn test_ssa_foreign_jump() {
+    let codes = parse(FOREIGN_JUMP);
+    let funcs = construct(&codes);
+    assert_eq!(funcs[0].blocks.len(), 2);
+    let result = destruct(&funcs);
+    assert_eq!(result, codes);
+
+    // the ids keep their names since `l9` reads them in the frame of `main`
+    let codes = parse("
+        FUNCTION main :
+        x := #1
+        IF x > #0 GOTO l9
+        x := #2
+        RETURN x
+        FUNCTION f :
+        x := #3
+        x := x + #1
+        RETURN x
+        LABEL l9 :
+        WRITE x
+        RETURN #0
+    ");
+    let result = destruct(&construct(&codes));
+    assert_eq!(result, codes);
+    assert_eq!(run(result, &[]).0, "1\n");
+}
+
+#[test]
+fn test_ssa_compiled() {
+    let source = "
+        int gcd(int a, int b) {
+            while (b != 0) {
+                int t = a - a / b * b;
+                a = b;
+                b = t;
+            }
+            return a;
+        }
+        int main() {
+            int xs[4], i = 0, n = read();
+            while (i < 4) {
+                if (i == 0 || i > 2 && n > 0) xs[i] = n * i;
+                else xs[i] = gcd(n, i + 6);
+                i = i + 1;
+            }
+            i = 0;
+            while (i < 4) { write(xs[i]); i = i + 1; }
+            return 0;
+        }";
+    let codes = compile(source).unwrap();
+    let result = destruct(&construct(&codes));
+    for input in [12, -9, 0] {
+        assert_eq!(run(codes.clone(), &[input]).0, run(result.clone(), &[input]).0, "{}", to_source(&result));
+    }
+}
+
+#[test]
+fn test_optimize() {
+    let compiled = compile("
+        int sq(int x) { return x * x; }
+        int main() {
+            int a[5], i = 0, n = read(), s = 0;
+            while (i < 5) { a[i] = sq(i + n); i = i + 1; }
+            i = 0;
+            while (i < 5) {
+                if (a[i] > 10) s = s + a[i]; else s = s - 1;
+                i = i + 1;
+            }
+            write(s);
+            return 0;
+        }").unwrap();
+    let mut programs: Vec<(Vec<Sentence>, Vec<i32>)> = SSA_PROGRAMS.iter()
+        .map(|(source, input)| (parse(source), input.to_vec()))
+        .collect();
+    programs.push((compiled.clone(), vec![3]));
+    programs.push((parse(FOREIGN_JUMP), vec![]));
+
+    let rules = default_rules();
+    let single: Vec<Vec<Pass>> = Pass::ALL.iter().map(|pass| vec![*pass]).collect();
+    let levels: Vec<Vec<Pass>> = (0..=2).map(|level| Pass::level(level).to_vec()).collect();
+    for (codes, input) in &programs {
+        let input: Vec<String> = input.iter().map(|n| n.to_string()).collect();
+        for passes in single.iter().chain(&levels) {
+            let (result, _) = optimize(codes, passes, &rules);
+            let report = report(codes, &result, &input, None)
+                .unwrap_or_else(|err| panic!("{:?}: {}\n{}", passes, err, to_source(&result)));
+            assert!(report.after <= report.before, "{:?}: {}", passes, report);
+        }
+    }
+
+    let input = ["3".to_string()];
+    let (o1, _) = optimize(&compiled, Pass::level(1), &rules);
+    let (o2, changes) = optimize(&compiled, Pass::level(2), &rules);
+    let (r1, r2) = (report(&compiled, &o1, &input, None).unwrap(), report(&compiled, &o2, &input, None).unwrap());
+    assert!(r2.after < r1.after && r1.after < r1.before, "{} {}", r1, r2);
+    assert!(changes.iter().any(|(pass, _)| *pass == Pass::Inline));
+    assert_eq!(Pass::from_name("cse"), Some(Pass::Cse));
+    assert_eq!(Pass::from_name("ssa"), None);
+}
@@ -2,7 +2,7 @@ use lalrpop_util::lalrpop_mod;
 
 use crate::ast::{Variable::{*, self}, Sentence::*, Operator::*};
 
-lalrpop_mod!(pub parser);
+lalrpop_mod!(#[allow(clippy::all, unused)] pub parser);
 
 #[test]
 fn test_parser() {
@@ -40,6 +40,12 @@ fn test_parser() {
             opt: Less, 
             label: ("label2") 
         }),
+        ("IF vcnt != #-1 GOTO label3", IfGoto { 
+            l: new_id("vcnt"), 
+            r: Number(-1), 
+            opt: NotEqual, 
+            label: ("label3") 
+        }),
         ("RETURN #0", Return(Number(0))),
         ("DEC varray 40", Dec{ target: new_id("varray"), size: 40}),
         ("t161 := CALL mod", Call { target: new_id("t161"), func: ("mod") })
@@ -58,3 +64,27 @@ fn test_parser() {
 fn new_id(id: &str) -> Variable {
     Id(id)
 }
+
+#[test]
+fn test_line() {
+    let parser = parser::LineParser::new();
+
+    [
+        ("", None),
+        ("; comment", None),
+        ("// comment", None),
+        ("WRITE t1 ; comment", Some(Write(new_id("t1")))),
+        ("t1 := t2 / t3 // comment", Some(Arith { 
+            l: new_id("t2"), 
+            r: new_id("t3"), 
+            opt: Div, 
+            target: new_id("t1") 
+        })),
+    ].iter().for_each(|case|{
+        let parser_result = match parser.parse(case.0) {
+            Ok(r) => r,
+            Err(e) => panic!("case: \"{}\"\n{}", case.0, e)
+        };
+        assert_eq!(parser_result, case.1)
+    });
+}
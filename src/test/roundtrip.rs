@@ -0,0 +1,184 @@
+use crate::{
+    ast::{Operator::{self, *}, Sentence, Variable},
+    interpreter::{Interpreter, parser},
+    opt::to_source,
+    utils::io::{read_from_values, write_to_buffer},
+};
+use super::utils::{Rng, name};
+
+const ARITH: [Operator; 4] = [Plus, Sub, Mul, Div];
+const REL: [Operator; 6] = [Equal, NotEqual, Greater, Less, GreaterEqual, LessEqual];
+
+fn id(rng: &mut Rng) -> &'static str {
+    let prefix = rng.pick(&["t", "v", "tmp_", "aB"]);
+    name(prefix, rng.below(20))
+}
+
+fn var(rng: &mut Rng) -> Variable<'static> {
+    match rng.below(4) {
+        0 => Variable::Number(rng.number()),
+        1 => Variable::Pointer(id(rng)),
+        2 => Variable::Deref(id(rng)),
+        _ => Variable::Id(id(rng)),
+    }
+}
+
+fn sentence(rng: &mut Rng) -> Sentence<'static> {
+    match rng.below(14) {
+        0 => Sentence::Label(name("label", rng.below(10))),
+        1 => Sentence::Func(id(rng)),
+        2 => Sentence::Assign { target: var(rng), var: var(rng) },
+        3 => Sentence::Arith { l: var(rng), r: var(rng), opt: rng.pick(&ARITH), target: var(rng) },
+        4 => Sentence::Goto(name("label", rng.below(10))),
+        5 => Sentence::IfGoto { 
+            l: var(rng), r: var(rng), opt: rng.pick(&REL), label: name("label", rng.below(10)) 
+        },
+        6 => Sentence::Return(var(rng)),
+        7 => Sentence::Dec { target: var(rng), size: rng.below(1000) as i32 },
+        8 => Sentence::Arg(var(rng)),
+        9 => Sentence::Call { target: var(rng), func: id(rng) },
+        10 => Sentence::Param(var(rng)),
+        11 => Sentence::Read(var(rng)),
+        12 => Sentence::Write(var(rng)),
+        _ => Sentence::Assign { target: var(rng), var: Variable::Number(i32::MIN) },
+    }
+}
+
+#[test]
+fn test_sentence_roundtrip() {
+    let parser = parser::SentenceParser::new();
+    let mut rng = Rng::new(20231219);
+
+    for _ in 0..2000 {
+        let code = sentence(&mut rng);
+        let text = code.to_string();
+        match parser.parse(&text) {
+            Ok(parsed) => assert_eq!(parsed, code, "case: \"{}\"", text),
+            Err(e) => panic!("case: \"{}\"\n{}", text, e),
+        }
+    }
+}
+
+/// a program which should pass the checking of interpreter
+fn program(rng: &mut Rng) -> Vec<Sentence<'static>> {
+    let funcs: Vec<&'static str> = (0..rng.below(3)).map(|i| name("f", i)).chain(["main"]).collect();
+    let mut codes = Vec::new();
+    let mut label_count = 0;
+
+    for func in &funcs {
+        codes.push(Sentence::Func(func));
+        let mut vars: Vec<&'static str> = Vec::new();
+        let mut arrays = 0;
+        let labels: Vec<&'static str> = (0..rng.below(4)).map(|i| name("l", label_count + i)).collect();
+        label_count += labels.len();
+
+        for i in 0..rng.below(3) {
+            vars.push(name("p", i));
+            codes.push(Sentence::Param(Variable::Id(name("p", i))));
+        }
+
+        let mut placed = 0;
+        for _ in 0..rng.below(30) {
+            // a right value is a number or a variable defined before
+            let operand = |rng: &mut Rng, vars: &[&'static str]| {
+                if vars.is_empty() {
+                    return Variable::Number(rng.number())
+                }
+                let id = rng.pick(vars);
+                match rng.below(5) {
+                    0 => Variable::Deref(id),
+                    1 => Variable::Pointer(id),
+                    2 | 3 => Variable::Id(id),
+                    _ => Variable::Number(rng.number()),
+                }
+            };
+            let new_var = |rng: &mut Rng, vars: &mut Vec<&'static str>| {
+                let id = name("t", rng.below(10));
+                if !vars.contains(&id) {
+                    vars.push(id);
+                }
+                Variable::Id(id)
+            };
+
+            if placed < labels.len() && rng.below(4) == 0 {
+                codes.push(Sentence::Label(labels[placed]));
+                placed += 1;
+            }
+            let code = match rng.below(9) {
+                0 => {
+                    let var = operand(rng, &vars);
+                    Sentence::Assign { var, target: new_var(rng, &mut vars) }
+                }
+                1 => {
+                    let (l, r) = (operand(rng, &vars), operand(rng, &vars));
+                    Sentence::Arith { l, r, opt: rng.pick(&ARITH), target: new_var(rng, &mut vars) }
+                }
+                2 if !labels.is_empty() => Sentence::IfGoto { 
+                    l: operand(rng, &vars), 
+                    r: operand(rng, &vars), 
+                    opt: rng.pick(&REL), 
+                    label: rng.pick(&labels),
+                },
+                3 if !labels.is_empty() => Sentence::Goto(rng.pick(&labels)),
+                4 => Sentence::Read(new_var(rng, &mut vars)),
+                5 => Sentence::Write(operand(rng, &vars)),
+                6 => {
+                    arrays += 1;
+                    let target = Variable::Id(name(&format!("{}_arr", func), arrays));
+                    vars.push(target.get_id().unwrap());
+                    Sentence::Dec { target, size: 4 * (1 + rng.below(10) as i32) }
+                }
+                7 => {
+                    for _ in 0..rng.below(3) {
+                        codes.push(Sentence::Arg(operand(rng, &vars)));
+                    }
+                    Sentence::Call { func: rng.pick(&funcs), target: new_var(rng, &mut vars) }
+                }
+                8 if !vars.is_empty() => Sentence::Assign { 
+                    target: Variable::Deref(rng.pick(&vars)), 
+                    var: operand(rng, &vars),
+                },
+                _ => Sentence::Return(operand(rng, &vars)),
+            };
+            codes.push(code);
+        }
+        for label in &labels[placed..] {
+            codes.push(Sentence::Label(label));
+        }
+        codes.push(Sentence::Return(operand_or_zero(rng, &vars)));
+    }
+
+    codes
+}
+
+fn operand_or_zero(rng: &mut Rng, vars: &[&'static str]) -> Variable<'static> {
+    match vars.is_empty() {
+        true => Variable::Number(0),
+        false => Variable::Id(rng.pick(vars)),
+    }
+}
+
+#[test]
+fn test_program_roundtrip() {
+    // creating parser is slow, so it's shared by all the cases
+    let parser = parser::SentenceParser::new();
+    let mut rng = Rng::new(42);
+
+    for _ in 0..300 {
+        let codes = program(&mut rng);
+        let source = to_source(&codes);
+
+        let parsed: Vec<Sentence> = source.lines()
+            .map(|line| match parser.parse(line) {
+                Ok(code) => code,
+                Err(e) => panic!("program:\n{}\n{}", source, e),
+            })
+            .collect();
+        assert_eq!(parsed, codes, "program:\n{}", source);
+
+        let (_, write_func) = write_to_buffer();
+        if let Err(e) = Interpreter::from_codes(parsed, read_from_values(vec![]), write_func) {
+            panic!("program:\n{}\n{}", source, e);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{
+    ast::Sentence,
+    diff::{self, Status},
+    interpreter::{Interpreter, parse_lines},
+    opt::Report,
+    utils::io::{read_from_values, write_to_buffer},
+};
+
+pub use crate::utils::rng::Rng;
+
+pub fn parse(source: &str) -> Vec<Sentence<'_>> {
+    let lines: Vec<&str> = source.lines().map(|line| line.trim()).collect();
+    parse_lines(&lines).unwrap().0
+}
+
+/// run the codes and return the output and the running count
+pub fn run(codes: Vec<Sentence>, input: &[i32]) -> (String, usize) {
+    let (output, write_func) = write_to_buffer();
+    let read_func = read_from_values(input.iter().map(|i| i.to_string()).collect());
+    let interpreter = Interpreter::from_codes(codes, read_func, write_func).unwrap();
+    let count = interpreter.run().unwrap();
+    let output = output.borrow().clone();
+    (output, count)
+}
+
+/// run both versions with the input, they must write the same output and exit
+pub fn report(before: &[Sentence], after: &[Sentence], input: &[String], limit: Option<usize>) -> Result<Report, String> {
+    let (a, b) = (diff::run(before, input, limit), diff::run(after, input, limit));
+    if let Some(divergence) = diff::compare(&a, &b) {
+        return Err(divergence.to_string())
+    }
+    match (a.status, b.status) {
+        (Status::Exited(before), Status::Exited(after)) => Ok(Report { before, after }),
+        (a, b) => Err(format!("the programs don't exit: {} / {}", a, b)),
+    }
+}
+
+/// the names live as long as the test, the leak is fine
+pub fn name(prefix: &str, i: usize) -> &'static str {
+    Box::leak(format!("{}{}", prefix, i).into_boxed_str())
+}
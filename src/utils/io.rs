@@ -1,15 +1,52 @@
-use std::{fs, io::stdin};
+use std::{fs, io::{self, stdin}, rc::Rc, cell::RefCell, collections::VecDeque};
 
-pub fn read_lines_from_file (filename: &str) -> Vec<String> {
-    fs::read_to_string(filename)
-        .expect("Should have been able to read the file")
+/// `None` means there is no more input
+pub type ReadFunc = Box<dyn Fn() -> Option<String>>;
+pub type WriteFunc = Box<dyn Fn(String)>;
+
+pub fn read_lines_from_file (filename: &str) -> io::Result<Vec<String>> {
+    Ok(fs::read_to_string(filename)?
         .lines()
         .map(|str| str.trim().into())
-        .collect()
+        .collect())
 }
 
 pub fn read_line() -> String {
     let mut input = String::new();
     let _ = stdin().read_line(&mut input);
     input.trim().into()
-}
\ No newline at end of file
+}
+
+/// the values are separated by whitespace, e.g. the content of an `.in` file
+pub fn read_values(text: &str) -> Vec<String> {
+    text.split_whitespace().map(|s| s.into()).collect()
+}
+
+/// feed READ with the given values one by one
+pub fn read_from_values(values: Vec<String>) -> ReadFunc {
+    let values = RefCell::new(VecDeque::from(values));
+    Box::new(move || values.borrow_mut().pop_front())
+}
+
+/// read the input line by line from stdin until EOF
+pub fn read_from_stdin() -> ReadFunc {
+    Box::new(|| {
+        let mut input = String::new();
+        match stdin().read_line(&mut input) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(input),
+        }
+    })
+}
+
+/// the values are separated by comma, e.g. `3,5,7`
+pub fn read_args(args: &str) -> Vec<String> {
+    args.split(',').map(|s| s.trim().into()).filter(|s: &String| !s.is_empty()).collect()
+}
+
+/// collect the WRITE output into a buffer which can be read after running
+pub fn write_to_buffer() -> (Rc<RefCell<String>>, WriteFunc) {
+    let buffer = Rc::new(RefCell::new(String::new()));
+    let writer = buffer.clone();
+    (buffer, Box::new(move |text: String| writer.borrow_mut().push_str(&text)))
+}
@@ -0,0 +1,43 @@
+use std::{collections::{BTreeMap, BTreeSet}, sync::Mutex};
+
+/// the generated names are leaked once and shared by all the programs,
+/// so compiling or optimizing many programs costs no more than the largest one
+static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+/// the name which lives as long as the program, so it fits in any `Sentence`
+pub fn intern(name: String) -> &'static str {
+    let mut names = NAMES.lock().unwrap();
+    match names.get(name.as_str()) {
+        Some(name) => name,
+        None => {
+            let name: &'static str = Box::leak(name.into_boxed_str());
+            names.insert(name);
+            name
+        }
+    }
+}
+
+/// a generator of the names like `x_1`, `x_2`, which never clash with the used ones
+#[derive(Debug, Default)]
+pub struct Fresh {
+    used: BTreeSet<String>,
+    next: BTreeMap<String, usize>,
+}
+
+impl Fresh {
+    pub fn new<'a>(used: impl IntoIterator<Item = &'a str>) -> Self {
+        Fresh { used: used.into_iter().map(String::from).collect(), next: BTreeMap::new() }
+    }
+
+    /// a new name derived from `base`
+    pub fn name(&mut self, base: &str) -> &'static str {
+        let next = self.next.entry(base.to_string()).or_insert(1);
+        loop {
+            let name = format!("{}_{}", base, next);
+            *next += 1;
+            if self.used.insert(name.clone()) {
+                return intern(name)
+            }
+        }
+    }
+}